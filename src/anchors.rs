@@ -0,0 +1,76 @@
+//! Anchor comments marking where each item starts, for grepping large files
+use quote::{
+  ToTokens,
+};
+use syn::{
+  Ident, Item,
+};
+use crate::{
+  locate::{
+    item_lines,
+  },
+  parse::{
+    describe_item,
+  },
+};
+
+/// The prefix of each anchor comment
+pub(crate) static ANCHOR_PREFIX: &str = "// procout-item: ";
+
+/// Insert an anchor comment above each top-level item of `source` but the test named `test_ident`
+/// - Anchors go above the item's attributes and doc comments, so the formatter keeps them attached.
+/// - The items are found in the text rather than by their spans, which inside a proc macro don't point into it.
+/// - Returns [None] if `source` doesn't parse.
+pub(crate) fn insert_anchors(source: &str, test_ident: &Ident) -> Option<String> {
+  let file = syn::parse_file(source).ok()?;
+  let mut anchors: Vec<(usize, String)> = file.items.iter()
+    .zip(item_lines(source, &file.items)?)
+    .filter(|(item, _)| !matches!(item, Item::Fn(item_fn) if &item_fn.sig.ident == test_ident))
+    .map(|(item, (start_line, _))| (start_line, anchor_name(item)))
+    .collect();
+  anchors.sort_by_key(|(line, _)| *line);
+  
+  let mut anchored = String::with_capacity(source.len());
+  let mut anchors = anchors.into_iter().peekable();
+  for (index, line) in source.split_inclusive('\n').enumerate() {
+    while let Some((_, name)) = anchors.next_if(|(anchor_line, _)| *anchor_line == index + 1) {
+      let indent = &line[..line.len() - line.trim_start().len()];
+      anchored.push_str(&format!("{}{}{}\n", indent, ANCHOR_PREFIX, name));
+    }
+    anchored.push_str(line);
+  }
+  Some(anchored)
+}
+
+/// What an anchor calls an item: its name, or for an `impl` what it's for
+fn anchor_name(item: &Item) -> String {
+  match item {
+    Item::Impl(item_impl) => {
+      let self_ty = item_impl.self_ty.to_token_stream();
+      match &item_impl.trait_ {
+        Some((_, trait_path, _)) => format!("impl {} for {}", trait_path.to_token_stream(), self_ty),
+        None => format!("impl {}", self_ty),
+      }
+    },
+    item => {
+      let (kind, ident) = describe_item(item);
+      ident.unwrap_or_else(|| kind.to_string())
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proc_macro2::{Span};
+  #[test]
+  fn test_insert_anchors() {
+    let source = "pub mod anchored {}\n\n/// Documented\n#[derive(Debug)]\nstruct Anchored;\nimpl Default for Anchored {\n    fn default() -> Self { Anchored }\n}\n#[test]\nfn macro_test() {}\n";
+    assert_eq!(
+      insert_anchors(source, &Ident::new("macro_test", Span::call_site())).as_deref(),
+      Some("// procout-item: anchored\npub mod anchored {}\n\n// procout-item: Anchored\n/// Documented\n#[derive(Debug)]\nstruct Anchored;\n// procout-item: impl Default for Anchored\nimpl Default for Anchored {\n    fn default() -> Self { Anchored }\n}\n#[test]\nfn macro_test() {}\n"),
+      "Must anchor each item above its attributes, skipping the test"
+    );
+    assert_eq!(insert_anchors("mod {", &Ident::new("macro_test", Span::call_site())), None, "Must skip what doesn't parse");
+  }
+}
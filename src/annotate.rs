@@ -0,0 +1,32 @@
+//! A copy of the written file with its line numbers, for reading alongside compiler errors
+
+/// Render `contents` with each line starting with a `/* NNN */` comment holding its line number
+/// - Numbers are padded to the same width, at least three digits, so the code stays aligned.
+/// - Empty lines get just the number, so no trailing whitespace is added.
+pub(crate) fn render_annotated(contents: &str) -> String {
+  let width = contents.lines().count().to_string().len().max(3);
+  let mut annotated = String::with_capacity(contents.len() + contents.lines().count() * (width + 7));
+  for (index, line) in contents.lines().enumerate() {
+    if line.is_empty() {
+      annotated.push_str(&format!("/* {:0width$} */\n", index + 1, width = width));
+    } else {
+      annotated.push_str(&format!("/* {:0width$} */ {}\n", index + 1, line, width = width));
+    }
+  }
+  annotated
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_render_annotated() {
+    assert_eq!(
+      render_annotated("pub mod annotated {\n\n    const CUSS: &str = \"SPIT\";\n}\n"),
+      "/* 001 */ pub mod annotated {\n/* 002 */\n/* 003 */     const CUSS: &str = \"SPIT\";\n/* 004 */ }\n",
+      "Must number each line"
+    );
+    let long = "x\n".repeat(1000);
+    assert!(render_annotated(&long).ends_with("/* 1000 */ x\n"), "Must widen the numbers for long files");
+  }
+}
@@ -0,0 +1,75 @@
+//! Recording what a macro was given alongside what it generated
+use proc_macro2::{
+  TokenStream,
+};
+use syn::{
+  Ident, Item,
+};
+use crate::{
+  embed::{
+    block_comment_text, line_comment_text,
+  },
+};
+
+/// The name of the item an attribute macro is applied to, if it has one
+pub(crate) fn item_ident(item: &Item) -> Option<&Ident> {
+  match item {
+    Item::Const(item) => Some(&item.ident),
+    Item::Enum(item) => Some(&item.ident),
+    Item::Fn(item) => Some(&item.sig.ident),
+    Item::Macro(item) => item.ident.as_ref(),
+    Item::Mod(item) => Some(&item.ident),
+    Item::Static(item) => Some(&item.ident),
+    Item::Struct(item) => Some(&item.ident),
+    Item::Trait(item) => Some(&item.ident),
+    Item::Type(item) => Some(&item.ident),
+    Item::Union(item) => Some(&item.ident),
+    _ => None,
+  }
+}
+
+/// Render the comment recording the attribute's arguments and the item it was applied to
+/// - A line break in either, e.g. in a raw string, continues the comment on the next line.
+pub(crate) fn render_attr_notes(attr: &TokenStream, item: &TokenStream) -> String {
+  format!(
+    "// attribute args: {}\n// original item: {}\n",
+    line_comment_text(&attr.to_string()),
+    line_comment_text(&item.to_string()),
+  )
+}
+
+/// Render the input of a macro as a `/* procout input: ... */` block comment
+/// - Block comments nest, so `/*` and `*/` in the input, e.g. inside string literals, are broken up to keep 
+///   the comment closed where it should be.
+pub(crate) fn render_input_comment(input: &TokenStream) -> String {
+  format!("/* procout input: {} */\n", block_comment_text(&input.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{
+    quote,
+  };
+  #[test]
+  fn test_item_ident() {
+    let item: Item = syn::parse2(quote!{ fn traced() {} }).expect("Must parse fn");
+    assert_eq!(item_ident(&item).map(ToString::to_string), Some("traced".to_string()), "Must name the fn");
+    let item: Item = syn::parse2(quote!{ impl Traced {} }).expect("Must parse impl");
+    assert_eq!(item_ident(&item), None, "An impl has no name");
+    assert_eq!(
+      render_attr_notes(&quote!{ level = "debug" }, &quote!{ fn traced() {} }),
+      "// attribute args: level = \"debug\"\n// original item: fn traced () { }\n",
+      "Must record the attribute's input"
+    );
+    assert_eq!(
+      render_input_comment(&quote!{ const C: &str = "*/ /*"; }),
+      "/* procout input: const C : & str = \"* / / *\" ; */\n",
+      "Must keep the comment closed"
+    );
+    let item: TokenStream = syn::parse_str("const RAW: &str = r#\"two\nlines */\"#;").expect("Must parse the item");
+    let notes = render_attr_notes(&quote!{}, &item);
+    let source = format!("{}{}const AFTER: u8 = 1;\n", notes, render_input_comment(&item));
+    assert_eq!(syn::parse_file(&source).expect("Must keep the notes in comments").items.len(), 1, "Must comment out every line: {}", source);
+  }
+}
@@ -0,0 +1,329 @@
+//! Collecting the output of many macro calls and writing it all at once
+use proc_macro2::{
+  Ident, TokenStream,
+};
+use std::{
+  any::{
+    Any,
+  },
+  collections::{
+    HashSet,
+  },
+  error::{
+    Error,
+  },
+  fmt,
+  mem,
+  panic::{
+    self, AssertUnwindSafe,
+  },
+  path::{
+    Path, PathBuf,
+  },
+};
+use crate::{
+  color::{
+    paint, Tone,
+  },
+  writes, ProcoutConfig, WrittenDump,
+};
+
+/// What a [ProcoutBatch::run] did with each file added
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+  /// How many files were written
+  pub written: usize,
+  /// How many weren't written, because files aren't written at all, a later one for the same module replaced 
+  /// it with [ProcoutBatch::dedup], or [ProcoutConfig::dump_when] skipped it
+  pub skipped: usize,
+  /// How many couldn't be written
+  pub failed: usize,
+  /// The paths written, in order
+  pub paths: Vec<PathBuf>,
+}
+
+/// Why a [ProcoutBatch::run] failed, which is that at least one file couldn't be written
+#[derive(Debug)]
+pub struct ProcoutError {
+  /// What was done, including the files that were written
+  pub summary: Summary,
+  /// Each module that couldn't be written, with the message it panicked with
+  pub failures: Vec<(String, String)>,
+}
+
+impl fmt::Display for ProcoutError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "procout could not write {} of {} files", self.summary.failed, self.summary.written + self.summary.failed)?;
+    for (module_name, message) in &self.failures {
+      write!(f, "\n  `{}`: {}", module_name, message)?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for ProcoutError {}
+
+/// The message a panic was raised with, if it was a string
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  payload.downcast_ref::<&str>().map(ToString::to_string)
+    .or_else(|| payload.downcast_ref::<String>().cloned())
+    .unwrap_or_else(|| "a panic without a message".to_string())
+}
+
+/// Collects the output of many macro calls and writes it when flushed, formatting every file with a single
+/// run of `rustfmt` instead of one per call
+/// - [ProcoutBatch::flush] writes what's been added so far. Whatever's left is flushed when the batch is dropped.
+/// - Each file is written as by [ProcoutConfig::procout], and formatted as by [ProcoutConfig::format_files].
+///   If that fails, each file is formatted on its own so the one to blame is reported.
+/// ```ignore
+/// let mut batch = ProcoutBatch::new(ProcoutConfig::new(), Some("tests/dumps")).dedup(true);
+/// for (module_ident, code_block) in expansions {
+///   batch.add(&code_block, module_ident);
+/// }
+/// ```
+#[derive(Debug)]
+#[must_use = "the batch is written when it's flushed or dropped"]
+pub struct ProcoutBatch {
+  config: ProcoutConfig,
+  output_path: Option<PathBuf>,
+  dedup: bool,
+  entries: Vec<(Ident, TokenStream)>,
+}
+
+impl ProcoutBatch {
+  /// Start an empty batch writing with `config` to the directory `output_path`, as in [ProcoutConfig::procout_to]
+  pub fn new(config: ProcoutConfig, output_path: Option<impl AsRef<Path>>) -> Self {
+    Self {
+      config,
+      output_path: output_path.map(|output_path| output_path.as_ref().to_path_buf()),
+      dedup: false,
+      entries: Vec::new(),
+    }
+  }
+  
+  /// Keep only the last code added for each module, since the earlier ones would just be overwritten
+  pub fn dedup(mut self, dedup: bool) -> Self {
+    self.dedup = dedup;
+    self
+  }
+  
+  /// Add `code_block` to be written as `module_ident` on the next flush
+  pub fn add(&mut self, code_block: &TokenStream, module_ident: Ident) {
+    self.entries.push((module_ident, code_block.clone()));
+  }
+  
+  /// Write everything added since the last flush, returning the paths written
+  /// - Nothing is written, and nothing returned, unless files are written at all, see [ProcoutConfig::procout].
+  /// - A file that can't be written panics, as [ProcoutConfig::procout] does. See [ProcoutBatch::run] to carry on.
+  pub fn flush(&mut self) -> Vec<PathBuf> {
+    self.write_entries(false).0.paths
+  }
+  
+  /// Write everything added since the last flush like [ProcoutBatch::flush], but carry on past files that can't 
+  /// be written, and report what was done, e.g. for a wrapper binary to exit with
+  /// - It's `Ok` when nothing failed, even if nothing was written. Otherwise the [ProcoutError] holds the 
+  ///   [Summary] along with why each file failed.
+  /// - The failures are caught panics, so their messages are still printed by the panic hook as they happen.
+  /// ```ignore
+  /// match batch.run() {
+  ///   Ok(summary) => println!("wrote {} files", summary.written),
+  ///   Err(err) => {
+  ///     eprintln!("{}", err);
+  ///     std::process::exit(1);
+  ///   },
+  /// }
+  /// ```
+  pub fn run(&mut self) -> Result<Summary, ProcoutError> {
+    match self.write_entries(true) {
+      (summary, failures) if failures.is_empty() => Ok(summary),
+      (summary, failures) => Err(ProcoutError { summary, failures }),
+    }
+  }
+  
+  /// Write and format everything added since the last flush, catching the panics of files that can't be written 
+  /// when `carry_on` is set, and returning what was done along with each failure
+  fn write_entries(&mut self, carry_on: bool) -> (Summary, Vec<(String, String)>) {
+    let mut entries = mem::take(&mut self.entries);
+    let mut summary = Summary::default();
+    let mut failures = Vec::new();
+    if !writes() || entries.is_empty() {
+      summary.skipped = entries.len();
+      return (summary, failures);
+    }
+    if self.dedup {
+      let mut seen = HashSet::new();
+      let added = entries.len();
+      entries.reverse();
+      entries.retain(|(module_ident, _)| seen.insert(module_ident.to_string()));
+      entries.reverse();
+      summary.skipped += added - entries.len();
+    }
+    
+    // Write everything, then format it in one go, then finish each file as it's going to stay. A module written 
+    // again, or a file every module shares, is left until the writes before it are finished.
+    let shares_file = self.config.output_file.is_some() || self.config.upsert_module.is_some();
+    let mut written = Vec::new();
+    let mut written_modules = HashSet::new();
+    let mut paths = Vec::new();
+    for (module_ident, code_block) in &entries {
+      if !self.config.dumps(code_block, &module_ident.to_string()) {
+        summary.skipped += 1;
+        continue;
+      }
+      let (file_ident, _, _) = self.config.module_import(module_ident);
+      if shares_file || !written_modules.insert(file_ident.to_string()) {
+        self.finish_entries(mem::take(&mut written), carry_on, &mut paths, &mut failures);
+        written_modules = HashSet::from([file_ident.to_string()]);
+      }
+      match attempt(carry_on, || self.config.start_module(code_block, module_ident, self.output_path.as_deref())) {
+        Ok(dump) => written.push((module_ident.to_string(), dump)),
+        Err(message) => failures.push((module_ident.to_string(), message)),
+      }
+    }
+    self.finish_entries(written, carry_on, &mut paths, &mut failures);
+    summary.written = paths.len();
+    summary.failed = failures.len();
+    summary.paths = paths;
+    (summary, failures)
+  }
+  
+  /// Format the files `written` that are still to be formatted together, then finish each, adding its path to 
+  /// `paths` or, when `carry_on` is set, why it failed to `failures`
+  fn finish_entries(&self, mut written: Vec<(String, WrittenDump)>, carry_on: bool, paths: &mut Vec<PathBuf>, failures: &mut Vec<(String, String)>) {
+    let mut unique_paths: Vec<&Path> = written.iter()
+      .filter(|(_, dump)| self.config.formats_after(dump.formats))
+      .map(|(_, dump)| dump.target_path.as_path())
+      .collect();
+    unique_paths.sort();
+    unique_paths.dedup();
+    if !unique_paths.is_empty() {
+      match self.config.format_files(&unique_paths) {
+        Ok(()) => {
+          if self.config.notifies() {
+            self.config.notification_stream.print(format_args!("{}", paint(Tone::Success, &format!("rustfmt status: ok for {} files", unique_paths.len()))));
+          }
+          for (_, dump) in written.iter_mut().filter(|(_, dump)| self.config.formats_after(dump.formats)) {
+            dump.formats = false;
+            dump.formatted = Some(true);
+          }
+        },
+        // Each file is left to format on its own as it's finished
+        Err(err) if self.config.notifies() => self.config.notification_stream.print(format_args!("{}", paint(Tone::Skipped, &format!("Formatting the batch together failed, so each file is formatted on its own: {}", err)))),
+        Err(_) => {},
+      }
+    }
+    for (module_name, dump) in written {
+      match attempt(carry_on, || self.config.finish_dump(dump)) {
+        Ok(path) => paths.push(path),
+        Err(message) => failures.push((module_name, message)),
+      }
+    }
+  }
+}
+
+/// Run `step`, catching its panic as its message when `carry_on` is set
+fn attempt<T>(carry_on: bool, step: impl FnOnce() -> T) -> Result<T, String> {
+  if !carry_on {
+    return Ok(step());
+  }
+  panic::catch_unwind(AssertUnwindSafe(step)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+impl Drop for ProcoutBatch {
+  fn drop(&mut self) {
+    self.flush();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proc_macro2::{
+    Span,
+  };
+  use quote::{
+    quote,
+  };
+  use std::{
+    env, fs,
+  };
+  #[test]
+  fn test_batch() {
+    let output_dir = env::temp_dir().join("procout_tests").join("test_batch");
+    let _ = fs::remove_dir_all(&output_dir);
+    let ident = |name: &str| Ident::new(name, Span::mixed_site());
+    let mut batch = ProcoutBatch::new(ProcoutConfig::new().format(Some(true)), Some(&output_dir)).dedup(true);
+    batch.add(&quote!{ pub mod first_batched { const STALE: u8 = 0; } }, ident("first_batched"));
+    batch.add(&quote!{ pub mod second_batched {} }, ident("second_batched"));
+    batch.add(&quote!{ pub mod first_batched { const FRESH: u8 = 1; } }, ident("first_batched"));
+    let paths = batch.flush();
+    
+    assert_eq!(
+      paths,
+      vec![output_dir.join("second_batched.rs"), output_dir.join("first_batched.rs")],
+      "Must write each module once, in the order last added"
+    );
+    let contents = fs::read_to_string(&paths[1]).expect("Must write the batched file");
+    assert!(contents.contains("pub mod first_batched {\n    const FRESH: u8 = 1;\n}\n"), "Must format the batch: {}", contents);
+    assert!(batch.flush().is_empty(), "Must only flush what's been added since");
+    
+    let config = ProcoutConfig::new().format(Some(true)).notify(Some(false)).notification_stream(crate::Stream::Captured);
+    let mut batch = ProcoutBatch::new(config, Some(&output_dir));
+    batch.add(&quote!{ pub mod quiet_batched {} }, ident("quiet_batched"));
+    batch.flush();
+    assert_eq!(crate::take_captured(), "", "Must print nothing when told to stay quiet");
+  }
+  
+  #[test]
+  fn test_run() {
+    let output_dir = env::temp_dir().join("procout_tests").join("test_run");
+    let _ = fs::remove_dir_all(&output_dir);
+    let ident = |name: &str| Ident::new(name, Span::mixed_site());
+    let mut batch = ProcoutBatch::new(ProcoutConfig::new(), Some(&output_dir)).dedup(true);
+    batch.add(&quote!{ pub mod run_module { const STALE: u8 = 0; } }, ident("run_module"));
+    batch.add(&quote!{ pub mod run_module {} }, ident("run_module"));
+    assert_eq!(
+      batch.run().expect("Must write the batch"),
+      Summary { written: 1, skipped: 1, failed: 0, paths: vec![output_dir.join("run_module.rs")] },
+      "Must count what was written and skipped"
+    );
+    
+    let mut batch = ProcoutBatch::new(ProcoutConfig::new().overwrite_policy(crate::OverwritePolicy::Never), Some(&output_dir));
+    batch.add(&quote!{ pub mod run_module {} }, ident("run_module"));
+    batch.add(&quote!{ pub mod other_run_module {} }, ident("other_run_module"));
+    let err = batch.run().expect_err("Must fail to overwrite");
+    assert_eq!((err.summary.written, err.summary.failed), (1, 1), "Must carry on past the failure: {}", err);
+    assert_eq!(err.failures[0].0, "run_module", "Must name the module that failed");
+    assert!(err.failures[0].1.contains("already exists"), "Must keep why it failed: {}", err);
+  }
+  
+  #[test]
+  fn test_finish_formatted() {
+    let output_dir = env::temp_dir().join("procout_tests").join("test_finish_formatted");
+    let _ = fs::remove_dir_all(&output_dir);
+    let ident = |name: &str| Ident::new(name, Span::mixed_site());
+    let config = ProcoutConfig::new().format(Some(true)).annotated(true).mode(Some(0o444));
+    let mut batch = ProcoutBatch::new(config, Some(&output_dir));
+    batch.add(&quote!{ pub mod finished_batched { const FRESH: u8 = 1; } }, ident("finished_batched"));
+    batch.add(&quote!{ pub mod other_finished_batched {} }, ident("other_finished_batched"));
+    let paths = batch.flush();
+    
+    let annotated = fs::read_to_string(output_dir.join("finished_batched").join("finished_batched.annotated.rs"))
+      .expect("Must write the annotated copy");
+    assert!(annotated.contains(" */     const FRESH: u8 = 1;\n"), "Must annotate the formatted file: {}", annotated);
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      for path in &paths {
+        let mode = fs::metadata(path).expect("Must write the batched file").permissions().mode();
+        assert_eq!(mode & 0o777, 0o444, "Must keep the mode of `{}` once it's formatted", path.display());
+      }
+    }
+    
+    let config = ProcoutConfig::new().format(Some(true)).overwrite_policy(crate::OverwritePolicy::Never);
+    let mut batch = ProcoutBatch::new(config, Some(&output_dir));
+    batch.add(&quote!{ pub mod finished_batched {} }, ident("finished_batched"));
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| batch.flush())).is_err(), "Must fail to overwrite");
+    assert_eq!(batch.config.format, Some(true), "Must leave the format as configured after a panic");
+  }
+}
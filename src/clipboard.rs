@@ -0,0 +1,64 @@
+//! Copying output to the system clipboard
+//!
+//! Like `rustfmt`, the clipboard is reached through the platform's own tools rather than a library, so the
+//! feature adds no dependencies. The first tool that runs wins.
+use std::{
+  io::{
+    self, Write,
+  },
+  process::{
+    Command, Stdio,
+  },
+};
+
+/// The commands tried in order, each with its arguments
+#[cfg(target_os = "macos")]
+pub(crate) static CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[("pbcopy", &[])];
+/// The commands tried in order, each with its arguments
+#[cfg(windows)]
+pub(crate) static CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[("clip", &[])];
+/// The commands tried in order, each with its arguments
+#[cfg(not(any(target_os = "macos", windows)))]
+pub(crate) static CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[
+  ("wl-copy", &[]),
+  ("xclip", &["-selection", "clipboard"]),
+  ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Pipe `text` into the first of `commands` that runs successfully, returning its name
+pub(crate) fn copy_with(commands: &[(&'static str, &[&str])], text: &str) -> io::Result<&'static str> {
+  let mut last_err = io::Error::new(io::ErrorKind::NotFound, "No clipboard command to try");
+  for (program, args) in commands {
+    let copied = Command::new(program)
+      .args(*args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .spawn()
+      .and_then(|mut child| {
+        child.stdin.take().expect("Must pipe to the clipboard command").write_all(text.as_bytes())?;
+        child.wait()
+      });
+    match copied {
+      Ok(status) if status.success() => return Ok(program),
+      Ok(status) => last_err = io::Error::other(format!("`{}` failed: {}", program, status)),
+      Err(err) => last_err = err,
+    }
+  }
+  Err(last_err)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[cfg(unix)]
+  #[test]
+  fn test_copy_with() {
+    assert_eq!(
+      copy_with(&[("procout-no-such-clipboard", &[]), ("cat", &[])], "copied").expect("Must fall back to the next command"),
+      "cat",
+      "Must name the command that copied"
+    );
+    assert!(copy_with(&[("false", &[])], "copied").is_err(), "Must fail when no command succeeds");
+  }
+}
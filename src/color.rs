@@ -0,0 +1,68 @@
+//! Colouring messages so they stand out in a wall of cargo output
+//!
+//! With the `color` feature, messages are wrapped in ANSI colours, but only while both stdout and stderr are 
+//! terminals, so piped output and scripts still get plain text whichever [crate::Stream] messages go to.
+use std::{
+  borrow::{
+    Cow,
+  },
+};
+
+/// What a message reports, which picks its colour
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tone {
+  /// Green, e.g. a file written
+  Success,
+  /// Yellow, e.g. formatting skipped
+  Skipped,
+  /// Red, e.g. a formatter that couldn't run
+  Error,
+}
+
+impl Tone {
+  /// The ANSI escape that starts this colour
+  #[cfg(any(feature = "color", test))]
+  fn escape(&self) -> &'static str {
+    match self {
+      Tone::Success => "\x1b[32m",
+      Tone::Skipped => "\x1b[33m",
+      Tone::Error => "\x1b[31m",
+    }
+  }
+}
+
+/// Colour `message` by its [Tone] if stdout and stderr are terminals and the `color` feature is enabled
+pub(crate) fn paint(tone: Tone, message: &str) -> Cow<'_, str> {
+  #[cfg(feature = "color")]
+  {
+    use std::io::IsTerminal;
+    if std::io::stdout().is_terminal() && std::io::stderr().is_terminal() {
+      return Cow::Owned(render_painted(tone, message));
+    }
+  }
+  let _ = tone;
+  Cow::Borrowed(message)
+}
+
+/// Wrap `message` in the escapes for its [Tone]
+#[cfg(any(feature = "color", test))]
+fn render_painted(tone: Tone, message: &str) -> String {
+  format!("{}{}\x1b[0m", tone.escape(), message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_render_painted() {
+    assert_eq!(render_painted(Tone::Success, "Wrote"), "\x1b[32mWrote\x1b[0m", "Must colour success green");
+    assert_eq!(render_painted(Tone::Skipped, "Skipped"), "\x1b[33mSkipped\x1b[0m", "Must colour skips yellow");
+    assert_eq!(render_painted(Tone::Error, "Failed"), "\x1b[31mFailed\x1b[0m", "Must colour errors red");
+  }
+  
+  #[test]
+  fn test_paint() {
+    // Test output is captured rather than going to a terminal
+    assert_eq!(paint(Tone::Error, "Failed"), "Failed", "Must leave messages plain off a terminal");
+  }
+}
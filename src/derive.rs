@@ -0,0 +1,67 @@
+//! Preparing a derive macro's input so it compiles alongside the derive's output
+use syn::{
+  Attribute, Data, DeriveInput, Fields,
+};
+
+/// Attributes that resolve anywhere, so they're kept on the item
+static BUILTIN_ATTRIBUTES: &[&str] = &[
+  "allow", "cfg", "cfg_attr", "deny", "deprecated", "doc", "forbid", "must_use", "non_exhaustive", "repr", "warn",
+];
+
+/// Drop the attributes on the item, its variants and its fields that aren't built in
+/// - These are usually the derive's helper attributes, which only resolve while the derive is applied.
+/// - Any remaining `#[derive]` is dropped too, since the output already holds what it would generate.
+pub(crate) fn strip_helper_attributes(derive_input: &mut DeriveInput) {
+  retain_builtin(&mut derive_input.attrs);
+  match &mut derive_input.data {
+    Data::Struct(data) => strip_fields(&mut data.fields),
+    Data::Enum(data) => for variant in data.variants.iter_mut() {
+      retain_builtin(&mut variant.attrs);
+      strip_fields(&mut variant.fields);
+    },
+    Data::Union(data) => for field in data.fields.named.iter_mut() {
+      retain_builtin(&mut field.attrs);
+    },
+  }
+}
+
+fn strip_fields(fields: &mut Fields) {
+  for field in fields.iter_mut() {
+    retain_builtin(&mut field.attrs);
+  }
+}
+
+fn retain_builtin(attrs: &mut Vec<Attribute>) {
+  attrs.retain(|attr| BUILTIN_ATTRIBUTES.iter().any(|builtin| attr.path.is_ident(builtin)));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{quote};
+  #[test]
+  fn test_strip_helper_attributes() {
+    let mut derive_input: DeriveInput = syn::parse2(quote!{
+      #[derive(Debug)]
+      #[helper(rename = "other")]
+      #[repr(C)]
+      pub struct Derived {
+        #[helper(skip)]
+        /// Documented
+        field: u8,
+      }
+    }).expect("Must parse derive input");
+    strip_helper_attributes(&mut derive_input);
+    assert_eq!(
+      quote!{ #derive_input }.to_string(),
+      quote!{
+        #[repr(C)]
+        pub struct Derived {
+          /// Documented
+          field: u8,
+        }
+      }.to_string(),
+      "Must keep only built in attributes on the item and its fields"
+    );
+  }
+}
@@ -0,0 +1,163 @@
+//! Persisting the difference between runs as a reviewable artifact 
+use quote::{
+  ToTokens,
+};
+use similar::{
+  TextDiff,
+};
+use std::{
+  fs,
+  io,
+  path::{
+    Path, PathBuf,
+  },
+};
+use syn::{
+  Item,
+};
+use crate::{
+  parse::{
+    describe_item,
+  },
+};
+
+/// The path of the diff report that sits next to `target_path`, i.e. `<name>.diff`
+pub(crate) fn diff_path(target_path: &Path) -> PathBuf {
+  target_path.with_extension("diff")
+}
+
+/// Keep the `<name>.diff` next to `target_path` in step with the latest run
+/// - If `previous` differs from `current`, write their unified diff.
+/// - Otherwise delete any stale diff left by an earlier run. A first run has nothing to compare against 
+///   so counts as unchanged.
+pub(crate) fn update_diff_file(
+  target_path: &Path,
+  previous: Option<&str>,
+  current: &str,
+) -> io::Result<()> {
+  let diff_path = diff_path(target_path);
+  match previous {
+    Some(previous) if previous != current => {
+      let file_name = target_path.file_name().map_or_else(
+        || target_path.display().to_string(),
+        |file_name| file_name.to_string_lossy().into_owned()
+      );
+      let diff = TextDiff::from_lines(previous, current)
+        .unified_diff()
+        .header(&format!("a/{}", file_name), &format!("b/{}", file_name))
+        .to_string();
+      fs::write(diff_path, diff)
+    },
+    _ => match fs::remove_file(diff_path) {
+      Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+      _ => Ok(()),
+    },
+  }
+}
+
+/// Compare two dumps item by item, reporting the items added, removed or changed from `a` to `b`, so changes 
+/// to the code stand out from changes to its formatting
+/// - Items are matched by signature: their kind and name, or for an `impl` its trait and type, and for items 
+///   without a name their tokens. Inline modules are compared item by item too, and reported by path.
+/// - Items are compared by their tokens, so comments and layout are ignored, but doc comments aren't.
+/// - Each difference is a line like `+ fn outer::added`, `- struct Removed` or `~ impl Display for Changed`, 
+///   in the order of `a` and then of what `b` adds. An empty list means the dumps are the same code.
+/// - Fails if either file can't be read, or doesn't parse.
+pub fn ast_diff(a: &Path, b: &Path) -> io::Result<Vec<String>> {
+  let parse = |path: &Path| -> io::Result<syn::File> {
+    syn::parse_file(&fs::read_to_string(path)?).map_err(|err| {
+      io::Error::new(io::ErrorKind::InvalidData, format!("Could not parse `{}`: {}", path.display(), err))
+    })
+  };
+  let mut differences = Vec::new();
+  diff_items("", &parse(a)?.items, &parse(b)?.items, &mut differences);
+  Ok(differences)
+}
+
+/// The signature matching an item across dumps
+fn item_signature(item: &Item) -> String {
+  match (item, describe_item(item)) {
+    (_, (kind, Some(ident))) => format!("{} {}", kind, ident),
+    (Item::Impl(item_impl), _) => {
+      let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+      let trait_path = item_impl.trait_.as_ref().map(|(not, path, _)| format!("{}{} for ", if not.is_some() { "!" } else { "" }, path.to_token_stream()));
+      let signature = format!(
+        "impl{} {}{} {}",
+        impl_generics.to_token_stream(),
+        trait_path.unwrap_or_default(),
+        item_impl.self_ty.to_token_stream(),
+        where_clause.to_token_stream(),
+      );
+      signature.split_whitespace().collect::<Vec<_>>().join(" ")
+    },
+    _ => item.to_token_stream().to_string(),
+  }
+}
+
+/// The tokens of an inline module without its content, which must match for the content to be compared
+fn mod_header(item: &Item) -> String {
+  match item {
+    Item::Mod(item_mod) => syn::ItemMod { content: None, ..item_mod.clone() }.to_token_stream().to_string(),
+    _ => item.to_token_stream().to_string(),
+  }
+}
+
+/// Add the differences between `a_items` and `b_items`, which are in the module at `path`, to `differences`
+fn diff_items(path: &str, a_items: &[Item], b_items: &[Item], differences: &mut Vec<String>) {
+  let signed = |items: &[Item]| -> Vec<(String, Item)> {
+    items.iter().map(|item| (item_signature(item), item.clone())).collect()
+  };
+  let qualify = |signature: &str| match signature.split_once(' ') {
+    Some((kind, name)) if !path.is_empty() && !kind.starts_with("impl") => format!("{} {}{}", kind, path, name),
+    _ if !path.is_empty() => format!("{} (in {})", signature, path.trim_end_matches("::")),
+    _ => signature.to_string(),
+  };
+  let mut b_items = signed(b_items);
+  for (signature, a_item) in signed(a_items) {
+    let b_item = match b_items.iter().position(|(b_signature, _)| b_signature == &signature) {
+      Some(position) => b_items.remove(position).1,
+      None => {
+        differences.push(format!("- {}", qualify(&signature)));
+        continue;
+      },
+    };
+    match (&a_item, &b_item) {
+      (Item::Mod(syn::ItemMod { content: Some((_, a_content)), .. }), Item::Mod(syn::ItemMod { content: Some((_, b_content)), ident, .. }))
+        if mod_header(&a_item) == mod_header(&b_item) => {
+        diff_items(&format!("{}{}::", path, ident), a_content, b_content, differences);
+      },
+      _ if a_item.to_token_stream().to_string() != b_item.to_token_stream().to_string() => {
+        differences.push(format!("~ {}", qualify(&signature)));
+      },
+      _ => {},
+    }
+  }
+  for (signature, _) in b_items {
+    differences.push(format!("+ {}", qualify(&signature)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+  #[test]
+  fn test_ast_diff() {
+    let dir = env::temp_dir().join("procout_tests").join("test_ast_diff");
+    fs::create_dir_all(&dir).expect("Must create test dir");
+    let (a, b) = (dir.join("a.rs"), dir.join("b.rs"));
+    fs::write(&a, "pub mod outer {\n    pub struct Kept;\n    pub fn removed() {}\n    impl Kept { fn changed() -> u8 { 1 } }\n}\n")
+      .expect("Must write dump");
+    fs::write(&b, "// reformatted\npub mod outer {\n  pub struct Kept;\n  impl Kept {\n    fn changed() -> u8 { 2 }\n  }\n  pub fn added() {}\n}\n")
+      .expect("Must write dump");
+    
+    assert_eq!(
+      ast_diff(&a, &b).expect("Must compare the dumps"),
+      vec!["- fn outer::removed", "~ impl Kept (in outer)", "+ fn outer::added"],
+      "Must report the structural differences only"
+    );
+    assert!(ast_diff(&a, &a).expect("Must compare the dumps").is_empty(), "Must find no differences in the same dump");
+    fs::write(&b, "pub mod outer {").expect("Must write dump");
+    assert_eq!(ast_diff(&a, &b).map_err(|err| err.kind()), Err(io::ErrorKind::InvalidData), "Must fail on a dump that doesn't parse");
+  }
+}
@@ -0,0 +1,160 @@
+//! Finding files procout has already written
+use std::{
+  ffi::{
+    OsStr,
+  },
+  fs::{
+    self, File,
+  },
+  io::{
+    self, BufRead, BufReader,
+  },
+  path::{
+    Path, PathBuf,
+  },
+  time::{
+    SystemTime,
+  },
+};
+
+/// The first line of every file procout writes
+pub static GENERATED_HEADER: &str = "// @generated by procout";
+
+/// The prefix of the header line recording the module ident 
+pub(crate) static MODULE_HEADER_PREFIX: &str = "// module: ";
+
+/// Render the provenance header written at the top of every file
+pub(crate) fn render_header(module_ident: &str) -> String {
+  format!("{}\n{}{}\n", GENERATED_HEADER, MODULE_HEADER_PREFIX, module_ident)
+}
+
+/// The dir next to the dumps holding the [INCLUDE_FILE], which keeps it out of the `tests/` targets
+pub(crate) static INCLUDE_DIR: &str = "include";
+
+/// The file of `#[path]` stubs written in the [INCLUDE_DIR], see [crate::ProcoutConfig::include_stubs]
+pub(crate) static INCLUDE_FILE: &str = "mod.rs";
+
+/// Render the `#[path = "<file>"] mod <stem>;` stub that pulls in the dump at `path`
+/// - Paste it into a test file next to the dump, or give it the path from that file to the dump.
+pub fn render_include_stub(path: &Path) -> Option<String> {
+  render_stub(path, "")
+}
+
+/// Render the stub for the dump at `path`, naming its file after `dir_prefix`
+fn render_stub(path: &Path, dir_prefix: &str) -> Option<String> {
+  let file_name = path.file_name()?.to_str()?;
+  let stem = path.file_stem()?.to_str()?;
+  syn::parse_str::<syn::Ident>(stem).ok()?;
+  Some(format!("#[path = {:?}]\nmod {};\n", format!("{}{}", dir_prefix, file_name), stem))
+}
+
+/// Render the [INCLUDE_FILE] holding a stub for each dump
+/// - Stubs name the dumps from the [INCLUDE_DIR], so the include file pulls them in from wherever it's itself 
+///   pulled in, e.g. with `#[path = "dumps/include/mod.rs"] mod dumps;` in a test file.
+pub(crate) fn render_include_file(dumps: &[DumpInfo]) -> String {
+  let mut rendered = "// Stubs including every procout dump in this dir's parent, regenerated on each write\n".to_string();
+  for dump in dumps {
+    if let Some(stub) = render_stub(&dump.path, "../") {
+      rendered.push_str(&stub);
+    }
+  }
+  rendered
+}
+
+/// A file previously written by procout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpInfo {
+  /// Where the file is
+  pub path: PathBuf,
+  /// The module ident recorded in the header, or the timestamp of a file named by default 
+  pub module_ident: Option<String>,
+  /// When the file was last written
+  pub modified: SystemTime,
+}
+
+/// List the files procout has written in `dir`, sorted by path
+/// - Files are recognized by the [GENERATED_HEADER] or by a name following [crate::TIMESTAMP_FORMAT].
+/// - Subdirectories are not searched.
+pub fn list_dumps(dir: &Path) -> io::Result<Vec<DumpInfo>> {
+  let mut dumps = Vec::new();
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if !path.is_file() || path.extension() != Some(OsStr::new("rs")) {
+      continue;
+    }
+    let module_ident = match read_header(&path)? {
+      Some(module_ident) => module_ident,
+      None => match path.file_stem().and_then(|stem| stem.to_str()).filter(|stem| is_timestamp_name(stem)) {
+        Some(stem) => Some(stem.to_string()),
+        None => continue,
+      },
+    };
+    let modified = fs::metadata(&path)?.modified()?;
+    dumps.push(DumpInfo { path, module_ident, modified });
+  }
+  dumps.sort_by(|a, b| a.path.cmp(&b.path));
+  Ok(dumps)
+}
+
+/// Read the module ident from a file's header
+/// - `None` means the file has no procout header.
+/// - `Some(None)` means it has the header but no module line.
+fn read_header(path: &Path) -> io::Result<Option<Option<String>>> {
+  let mut lines = BufReader::new(File::open(path)?).lines();
+  match lines.next().transpose()? {
+    Some(first_line) if first_line.trim_end() == GENERATED_HEADER => {
+      let module_ident = lines.next().transpose()?.and_then(|line| {
+        line.strip_prefix(MODULE_HEADER_PREFIX).map(|module_ident| module_ident.trim().to_string())
+      });
+      Ok(Some(module_ident))
+    },
+    _ => Ok(None),
+  }
+}
+
+/// Whether a file stem looks like the default `out_%Y_%m%d_%H%S` name
+fn is_timestamp_name(stem: &str) -> bool {
+  let parts: Vec<&str> = stem.split('_').collect();
+  matches!(parts.as_slice(), ["out", year, month_day, hour_second] 
+    if [year, month_day, hour_second].iter().all(|part| part.len() == 4 && part.bytes().all(|byte| byte.is_ascii_digit()))
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+  #[test]
+  fn test_list_dumps() {
+    let dir = env::temp_dir().join("procout_tests").join("test_list_dumps");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Must create test dir");
+    fs::write(dir.join("listed_module.rs"), format!("{}pub mod listed_module {{}}\n", render_header("listed_module")))
+      .expect("Must write dump");
+    fs::write(dir.join("out_2021_0314_1559.rs"), "pub mod out_2021_0314_1559 {}\n").expect("Must write timestamped dump");
+    fs::write(dir.join("hand_written.rs"), "pub mod hand_written {}\n").expect("Must write unrelated file");
+    fs::write(dir.join("listed_module.diff"), GENERATED_HEADER).expect("Must write unrelated file");
+    
+    let module_idents: Vec<Option<String>> = list_dumps(&dir)
+      .expect("Must list dumps")
+      .into_iter()
+      .map(|dump| dump.module_ident)
+      .collect();
+    assert_eq!(
+      module_idents,
+      vec![Some("listed_module".to_string()), Some("out_2021_0314_1559".to_string())],
+      "Must list only procout's files, by header or by timestamp name"
+    );
+  }
+  
+  #[test]
+  fn test_render_include_file() {
+    let dump = |path: &str| DumpInfo { path: PathBuf::from(path), module_ident: None, modified: SystemTime::UNIX_EPOCH };
+    assert_eq!(
+      render_include_file(&[dump("tests/dumps/first_module.rs"), dump("tests/dumps/not-an-ident.rs")]),
+      "// Stubs including every procout dump in this dir's parent, regenerated on each write\n\
+      #[path = \"../first_module.rs\"]\nmod first_module;\n",
+      "Must stub each dump named like a module, from the include dir"
+    );
+  }
+}
@@ -0,0 +1,97 @@
+//! Embedding text in the written file as comments and string literals that stay valid Rust whatever the text
+use crate::{
+  dumps::{
+    render_header,
+  },
+};
+
+/// The name of the const holding the expansion, see [crate::OutputFormat::Const]
+pub(crate) static EXPANSION_CONST: &str = "EXPANSION";
+
+/// The most `#`s a raw string literal's delimiter may have
+const MAX_RAW_HASHES: usize = 255;
+
+/// Quote `text` as a raw string literal with one more `#` than the longest run following a `"` in it, so 
+/// nothing in it can end the literal early
+/// - Raw strings can't hold a carriage return that doesn't start a `\r\n`, or need more than 255 `#`s, so 
+///   such text is quoted as an ordinary escaped string instead.
+pub(crate) fn raw_string_literal(text: &str) -> String {
+  let mut longest_run = 0;
+  for (index, _) in text.match_indices('"') {
+    let run = text[index + 1..].bytes().take_while(|&byte| byte == b'#').count();
+    longest_run = longest_run.max(run);
+  }
+  let isolated_cr = text.match_indices('\r').any(|(index, _)| !text[index + 1..].starts_with('\n'));
+  if isolated_cr || longest_run >= MAX_RAW_HASHES {
+    return format!("{:?}", text);
+  }
+  let hashes = "#".repeat(longest_run + 1);
+  format!("r{}\"{}\"{}", hashes, text, hashes)
+}
+
+/// Make `text` safe to put in a block comment, by breaking up every `/*` and `*/` in it with a space
+/// - Block comments nest, so either would open or close a comment where it shouldn't. Breaking up the `/*`s first 
+///   leaves none to be made by breaking up the `*/`s.
+pub(crate) fn block_comment_text(text: &str) -> String {
+  text.replace("/*", "/ *").replace("*/", "* /")
+}
+
+/// Make `text` safe to put in a line comment that's continued onto a new `//` line after each line break, 
+/// e.g. of a raw string in the text
+pub(crate) fn line_comment_text(text: &str) -> String {
+  text.replace("\r\n", "\n").replace(['\n', '\r'], "\n// ")
+}
+
+/// Render the file holding `source` as `pub const EXPANSION: &str`, after the usual header
+pub(crate) fn render_const(module_name: &str, source: &str) -> String {
+  format!(
+    "{}#[allow(dead_code)]\npub const {}: &str = {};\n",
+    render_header(module_name),
+    EXPANSION_CONST,
+    raw_string_literal(source),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_raw_string_literal() {
+    assert_eq!(raw_string_literal("pub mod plain {}\n"), "r#\"pub mod plain {}\n\"#", "Must use one hash for plain text");
+    for text in ["const RAW: &str = r#\"quoted\"#;", "r##\"two\"## and \"#", "ends in a quote\""] {
+      let literal = raw_string_literal(text);
+      let parsed: syn::LitStr = syn::parse_str(&literal).unwrap_or_else(|err| panic!("Must quote `{}` validly: {}", text, err));
+      assert_eq!(parsed.value(), text, "Must keep the text as it is");
+    }
+    assert!(raw_string_literal("r##\"two\"##").starts_with("r###\""), "Must outnumber the longest run of hashes");
+    for text in ["bare \r return", &format!("\"{}", "#".repeat(MAX_RAW_HASHES))] {
+      let parsed: syn::LitStr = syn::parse_str(&raw_string_literal(text)).expect("Must fall back to an escaped string");
+      assert_eq!(parsed.value(), text, "Must keep the text as it is");
+    }
+  }
+  
+  #[test]
+  fn test_render_const() {
+    for source in ["const END: &str = \"*/\";\n", "const RAW: &str = r#\"one\"#;\n", "const RAWER: &str = r##\"two \"# \"##;\n"] {
+      let file = syn::parse_file(&render_const("embedded", source)).unwrap_or_else(|err| panic!("Must embed `{}` validly: {}", source, err));
+      match &file.items[..] {
+        [syn::Item::Const(item_const)] => match &*item_const.expr {
+          syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit_str), .. }) => assert_eq!(lit_str.value(), source, "Must embed the source as it is"),
+          _ => panic!("Must embed `{}` as a string", source),
+        },
+        _ => panic!("Must embed `{}` as just the const", source),
+      }
+    }
+  }
+  
+  #[test]
+  fn test_comment_text() {
+    for text in ["*/ closed early", "/* opened /*/ */", "**/ and /**", "\"# and \"## in a comment"] {
+      let source = format!("/* {} */\n// {}\nconst AFTER: u8 = 1;\n", block_comment_text(text), line_comment_text(text));
+      let file = syn::parse_file(&source).unwrap_or_else(|err| panic!("Must keep `{}` in its comments: {}", text, err));
+      assert_eq!(file.items.len(), 1, "Must leave only the code outside the comments: {}", source);
+    }
+    let source = format!("// {}\nconst AFTER: u8 = 1;\n", line_comment_text("r\"first\nconst LEAKED: u8 = 0;\r\n\""));
+    assert_eq!(syn::parse_file(&source).expect("Must continue the comment").items.len(), 1, "Must comment out every line: {}", source);
+  }
+}
@@ -0,0 +1,172 @@
+//! Making the output self-contained, the way `cargo expand` shows a whole crate
+use proc_macro2::{
+  TokenStream, TokenTree,
+};
+use quote::{
+  ToTokens,
+};
+use std::{
+  collections::{
+    BTreeSet, HashSet,
+  },
+};
+use syn::{
+  File, Item, UseTree,
+};
+
+/// Common `std` names that macro output tends to use without importing, and where they live
+static KNOWN_IMPORTS: &[(&str, &str)] = &[
+  ("Any", "std::any::Any"),
+  ("Arc", "std::sync::Arc"),
+  ("BTreeMap", "std::collections::BTreeMap"),
+  ("BTreeSet", "std::collections::BTreeSet"),
+  ("BinaryHeap", "std::collections::BinaryHeap"),
+  ("Borrow", "std::borrow::Borrow"),
+  ("Cell", "std::cell::Cell"),
+  ("Cow", "std::borrow::Cow"),
+  ("Debug", "std::fmt::Debug"),
+  ("Display", "std::fmt::Display"),
+  ("Duration", "std::time::Duration"),
+  ("FromStr", "std::str::FromStr"),
+  ("Future", "std::future::Future"),
+  ("HashMap", "std::collections::HashMap"),
+  ("HashSet", "std::collections::HashSet"),
+  ("Instant", "std::time::Instant"),
+  ("Mutex", "std::sync::Mutex"),
+  ("Ordering", "std::cmp::Ordering"),
+  ("Path", "std::path::Path"),
+  ("PathBuf", "std::path::PathBuf"),
+  ("PhantomData", "std::marker::PhantomData"),
+  ("Pin", "std::pin::Pin"),
+  ("Rc", "std::rc::Rc"),
+  ("RefCell", "std::cell::RefCell"),
+  ("RwLock", "std::sync::RwLock"),
+  ("TypeId", "std::any::TypeId"),
+  ("VecDeque", "std::collections::VecDeque"),
+  ("fmt", "std::fmt"),
+];
+
+/// Add `use` items for the [KNOWN_IMPORTS] each scope names but neither defines nor imports
+/// - The crate root and every inline `mod` are scopes of their own.
+/// - This is best-effort: only the names above are recognized, and a name counts as used wherever it
+///   appears in the scope, even as a field or a path segment.
+pub(crate) fn infer_imports(file: &mut File) {
+  prepend_imports(&mut file.items);
+}
+
+fn prepend_imports(items: &mut Vec<Item>) {
+  for item in items.iter_mut() {
+    if let Item::Mod(item_mod) = item {
+      if let Some((_, content)) = &mut item_mod.content {
+        prepend_imports(content);
+      }
+    }
+  }
+  let mut used = HashSet::new();
+  for item in items.iter() {
+    if !matches!(item, Item::Mod(_)) {
+      collect_idents(item.to_token_stream(), &mut used);
+    }
+  }
+  let defined = defined_names(items);
+  let imports: BTreeSet<&str> = KNOWN_IMPORTS.iter()
+    .filter(|(name, _)| used.contains(*name) && !defined.contains(*name))
+    .map(|(_, path)| *path)
+    .collect();
+  let imports: Vec<Item> = imports.into_iter()
+    .map(|path| syn::parse_str(&format!("use {};", path)).expect("Known imports must parse"))
+    .collect();
+  items.splice(0..0, imports);
+}
+
+/// Every ident anywhere in `tokens`
+fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+  for token in tokens {
+    match token {
+      TokenTree::Ident(ident) => {
+        idents.insert(ident.to_string());
+      },
+      TokenTree::Group(group) => collect_idents(group.stream(), idents),
+      _ => {},
+    }
+  }
+}
+
+/// The names the items in a scope define or import
+fn defined_names(items: &[Item]) -> HashSet<String> {
+  let mut defined = HashSet::new();
+  for item in items {
+    let ident = match item {
+      Item::Const(item) => Some(&item.ident),
+      Item::Enum(item) => Some(&item.ident),
+      Item::ExternCrate(item) => Some(&item.ident),
+      Item::Fn(item) => Some(&item.sig.ident),
+      Item::Macro(item) => item.ident.as_ref(),
+      Item::Mod(item) => Some(&item.ident),
+      Item::Static(item) => Some(&item.ident),
+      Item::Struct(item) => Some(&item.ident),
+      Item::Trait(item) => Some(&item.ident),
+      Item::TraitAlias(item) => Some(&item.ident),
+      Item::Type(item) => Some(&item.ident),
+      Item::Union(item) => Some(&item.ident),
+      Item::Use(item) => {
+        collect_use_names(&item.tree, &mut defined);
+        None
+      },
+      _ => None,
+    };
+    if let Some(ident) = ident {
+      defined.insert(ident.to_string());
+    }
+  }
+  defined
+}
+
+fn collect_use_names(tree: &UseTree, names: &mut HashSet<String>) {
+  match tree {
+    UseTree::Path(path) => collect_use_names(&path.tree, names),
+    UseTree::Name(name) => {
+      names.insert(name.ident.to_string());
+    },
+    UseTree::Rename(rename) => {
+      names.insert(rename.rename.to_string());
+    },
+    UseTree::Glob(_) => {},
+    UseTree::Group(group) => for tree in group.items.iter() {
+      collect_use_names(tree, names);
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{quote};
+  #[test]
+  fn test_infer_imports() {
+    let mut file: File = syn::parse2(quote!{
+      pub struct Registry { entries: HashMap<String, Rc<Entry>> }
+      pub struct Entry;
+      pub mod inner {
+        use std::collections::HashMap;
+        pub fn shared() -> (HashMap<u8, u8>, Arc<u8>) { unimplemented!() }
+      }
+    }).expect("Must parse");
+    infer_imports(&mut file);
+    assert_eq!(
+      file.to_token_stream().to_string(),
+      quote!{
+        use std::collections::HashMap;
+        use std::rc::Rc;
+        pub struct Registry { entries: HashMap<String, Rc<Entry> > }
+        pub struct Entry;
+        pub mod inner {
+          use std::sync::Arc;
+          use std::collections::HashMap;
+          pub fn shared() -> (HashMap<u8, u8>, Arc<u8>) { unimplemented!() }
+        }
+      }.to_string(),
+      "Must import only what each scope uses and lacks"
+    );
+  }
+}
@@ -0,0 +1,177 @@
+//! Pluggable formatting of the generated source
+use std::{
+  error::{
+    Error,
+  },
+  fmt,
+  io::{
+    self, Write,
+  },
+  process::{
+    Command, ExitStatus, Stdio,
+  },
+  thread,
+};
+use crate::{
+  parse::{
+    describe_item,
+  },
+};
+
+/// Formats generated source in memory
+/// - Set one with [crate::ProcoutConfig::formatter] to replace formatting the created file with a [crate::FormatCommand].
+/// - The formatter gets the generated code without procout's header comments, which are put back afterwards.
+/// - Implement it to plug in your own, e.g. a wrapper that pins a `rustfmt` version.
+pub trait Formatter {
+  /// Format `source`, a complete Rust file
+  fn format(&self, source: &str) -> Result<String, FormatError>;
+}
+
+/// Why a [Formatter] couldn't format the source
+#[derive(Debug)]
+pub enum FormatError {
+  /// The formatter couldn't be run, or talking to it failed
+  Io(io::Error),
+  /// The formatter ran and reported failure
+  Failed {
+    /// How the formatter exited
+    status: ExitStatus,
+    /// What the formatter printed to stderr
+    stderr: String,
+  },
+  /// The source doesn't parse, so it can't be formatted
+  Parse(syn::Error),
+  /// The formatter ran, but what it returned no longer parses or has different items, so it was thrown away
+  Mangled(String),
+}
+
+impl fmt::Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FormatError::Io(err) => write!(f, "could not run the formatter: {}", err),
+      FormatError::Failed { status, stderr } => write!(f, "the formatter failed with {}:\n{}", status, stderr),
+      FormatError::Parse(err) => write!(f, "the source does not parse: {}", err),
+      FormatError::Mangled(reason) => write!(f, "the formatted source was thrown away, {}", reason),
+    }
+  }
+}
+
+impl Error for FormatError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      FormatError::Io(err) => Some(err),
+      FormatError::Failed { .. } | FormatError::Mangled(_) => None,
+      FormatError::Parse(err) => Some(err),
+    }
+  }
+}
+
+impl From<io::Error> for FormatError {
+  fn from(err: io::Error) -> Self {
+    FormatError::Io(err)
+  }
+}
+
+/// Check that formatting `unformatted` into `formatted` kept what the file holds, guarding against a formatter 
+/// reordering or dropping items of macro output, e.g. moving the test above the module
+/// - The top-level items must be the same kinds with the same names, in the same order.
+/// - Source that didn't parse before formatting isn't checked, since there's nothing to compare.
+pub(crate) fn check_formatted(unformatted: &str, formatted: &str) -> Result<(), FormatError> {
+  let before = match syn::parse_file(unformatted) {
+    Ok(before) => before,
+    Err(_) => return Ok(()),
+  };
+  let after = syn::parse_file(formatted)
+    .map_err(|err| FormatError::Mangled(format!("it no longer parses: {}", err)))?;
+  let outline = |file: &syn::File| file.items.iter().map(describe_item).collect::<Vec<_>>();
+  let (before, after) = (outline(&before), outline(&after));
+  if before == after {
+    Ok(())
+  } else {
+    Err(FormatError::Mangled(format!("its items changed from {:?} to {:?}", before, after)))
+  }
+}
+
+/// Formats by piping the source through `rustfmt`
+/// - Without a file to start from, `rustfmt` looks for its configuration from the current dir.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RustfmtFormatter;
+
+impl Formatter for RustfmtFormatter {
+  fn format(&self, source: &str) -> Result<String, FormatError> {
+    let mut child = Command::new("rustfmt")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?;
+    // Write from another thread so a large file can't fill the pipes and deadlock
+    let mut stdin = child.stdin.take().expect("Must pipe rustfmt stdin");
+    let source = source.to_string();
+    let writer = thread::spawn(move || stdin.write_all(source.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().expect("Must join rustfmt stdin writer")?;
+    if output.status.success() {
+      Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+      Err(FormatError::Failed {
+        status: output.status,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+      })
+    }
+  }
+}
+
+/// Formats in memory with `prettyplease`, see [crate::FormatCommand::Prettyplease]
+#[cfg(feature = "prettyplease")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrettypleaseFormatter;
+
+#[cfg(feature = "prettyplease")]
+impl Formatter for PrettypleaseFormatter {
+  fn format(&self, source: &str) -> Result<String, FormatError> {
+    let file = syn::parse_file(source).map_err(FormatError::Parse)?;
+    Ok(prettyplease::unparse(&file))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_rustfmt_formatter() {
+    assert_eq!(
+      RustfmtFormatter.format("pub mod formatted { const CUSS : & str = \"SPIT\" ; }").expect("Must format"),
+      "pub mod formatted {\n    const CUSS: &str = \"SPIT\";\n}\n",
+      "Must format the source through rustfmt"
+    );
+    assert!(
+      matches!(RustfmtFormatter.format("pub mod broken {"), Err(FormatError::Failed { .. })),
+      "Must report rustfmt's failure"
+    );
+  }
+  
+  #[test]
+  fn test_check_formatted() {
+    let unformatted = "pub mod checked { } #[test] fn macro_test() { }";
+    assert!(check_formatted(unformatted, "pub mod checked {}\n#[test]\nfn macro_test() {}\n").is_ok(), "Must accept formatting");
+    assert!(
+      matches!(check_formatted(unformatted, "#[test]\nfn macro_test() {}\npub mod checked {}\n"), Err(FormatError::Mangled(_))),
+      "Must reject reordered items"
+    );
+    assert!(
+      matches!(check_formatted(unformatted, "pub mod checked {"), Err(FormatError::Mangled(_))),
+      "Must reject output that no longer parses"
+    );
+    assert!(check_formatted("pub mod broken {", "anything").is_ok(), "Must not check source that never parsed");
+  }
+  
+  #[cfg(feature = "prettyplease")]
+  #[test]
+  fn test_prettyplease_formatter() {
+    assert_eq!(
+      PrettypleaseFormatter.format("pub mod formatted { const CUSS : & str = \"SPIT\" ; }").expect("Must format"),
+      "pub mod formatted {\n    const CUSS: &str = \"SPIT\";\n}\n",
+      "Must format the source through prettyplease"
+    );
+  }
+}
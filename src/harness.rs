@@ -0,0 +1,63 @@
+//! Pluggable test harnesses for the generated test
+use proc_macro2::{
+  TokenStream,
+};
+use quote::{
+  quote,
+};
+
+/// Describes how the generated test is declared, so it runs in the project's own test framework
+/// - Set one with [crate::ProcoutConfig::test_harness]. It takes the place of any [crate::ProcoutConfig::test_attr].
+/// - [StdTest] is built in, and with the `tokio` and `async-std` features so are [TokioTest] and [AsyncStdTest].
+/// - Implement it for anything else, e.g. `#[wasm_bindgen_test]`.
+pub trait TestHarness {
+  /// The outer attributes on the test, e.g. `#[test]`
+  fn attributes(&self) -> TokenStream;
+  
+  /// Whether the test is an `async fn`, as async runtimes' test attributes expect
+  fn is_async(&self) -> bool {
+    false
+  }
+}
+
+/// The standard library's `#[test]`, which is also the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdTest;
+
+impl TestHarness for StdTest {
+  fn attributes(&self) -> TokenStream {
+    quote!{ #[test] }
+  }
+}
+
+/// An `async` test run by `#[tokio::test]`
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioTest;
+
+#[cfg(feature = "tokio")]
+impl TestHarness for TokioTest {
+  fn attributes(&self) -> TokenStream {
+    quote!{ #[tokio::test] }
+  }
+  
+  fn is_async(&self) -> bool {
+    true
+  }
+}
+
+/// An `async` test run by `#[async_std::test]`
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdTest;
+
+#[cfg(feature = "async-std")]
+impl TestHarness for AsyncStdTest {
+  fn attributes(&self) -> TokenStream {
+    quote!{ #[async_std::test] }
+  }
+  
+  fn is_async(&self) -> bool {
+    true
+  }
+}
@@ -0,0 +1,43 @@
+//! Naming files by a hash of what they hold
+//!
+//! The hash is 64-bit FNV-1a, which is tiny, has no dependencies, and unlike [std::hash::DefaultHasher] gives 
+//! the same value on every platform.
+
+/// The FNV-1a offset basis
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The FNV-1a prime
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hash `bytes` with 64-bit FNV-1a
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME))
+}
+
+/// What a file is named while it's written, before it's named by its contents
+pub(crate) const STAGED_PREFIX: &str = "dump_staged_";
+
+/// The file stem naming a dump holding `contents`, e.g. `dump_3f1c0b9de5a27c68`
+/// - These are the contents of the file as written, so the same code under another header, template or 
+///   formatting is another file.
+pub(crate) fn content_hash_stem(contents: &[u8]) -> String {
+  format!("dump_{:016x}", fnv1a(contents))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_fnv1a() {
+    assert_eq!(fnv1a(b""), OFFSET_BASIS, "Must start from the offset basis");
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c, "Must match the reference FNV-1a value");
+  }
+  
+  #[test]
+  fn test_content_hash_stem() {
+    let stem = content_hash_stem(b"pub mod hashed_module { }");
+    assert_eq!(stem, content_hash_stem(b"pub mod hashed_module { }"), "Must name the same contents the same");
+    assert_ne!(stem, content_hash_stem(b"pub mod hashed_module {}"), "Must name changed contents differently");
+    assert_eq!(stem.len(), "dump_".len() + 16, "Must name the dump by the whole hash");
+  }
+}
@@ -0,0 +1,108 @@
+//! A browsable `index.html` of the dumps in a directory
+use std::{
+  collections::{
+    BTreeMap,
+  },
+  fs,
+  path::{
+    Path, PathBuf,
+  },
+  sync::{
+    Mutex,
+  },
+};
+use crate::{
+  dumps::{
+    DumpInfo,
+  },
+};
+
+/// The index written next to the dumps, see [crate::ProcoutConfig::html_index]
+pub(crate) static INDEX_FILE: &str = "index.html";
+
+/// Whether each file written so far parsed, and whether it formatted if the formatter was run on it
+static STATUSES: Mutex<BTreeMap<PathBuf, (bool, Option<bool>)>> = Mutex::new(BTreeMap::new());
+
+/// Remember how the write of `path` went, replacing any earlier write's status
+pub(crate) fn record_status(path: &Path, parsed: bool, formatted: Option<bool>) {
+  STATUSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(path.to_path_buf(), (parsed, formatted));
+}
+
+/// Escape `text` for an HTML text node or a quoted attribute value
+fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for character in text.chars() {
+    match character {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _ => escaped.push(character),
+    }
+  }
+  escaped
+}
+
+/// Render the [INDEX_FILE] with a row for each dump, linking it by file name so the index works wherever the 
+/// directory is served from
+/// - The parse and format status is only known for files written by this process, and is `-` for the rest.
+/// - Nothing in it depends on when it's rendered, so the same dumps render the same index.
+pub(crate) fn render_index(dumps: &[DumpInfo]) -> String {
+  let statuses = STATUSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  let mut rendered = format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>procout dumps</title>\n</head>\n<body>\n\
+    <h1>procout dumps</h1>\n<p>{} file{}.</p>\n<table>\n\
+    <tr><th>Module</th><th>File</th><th>Bytes</th><th>Parsed</th><th>Formatted</th></tr>\n",
+    dumps.len(),
+    if dumps.len() == 1 { "" } else { "s" },
+  );
+  let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+  for dump in dumps {
+    let file_name = dump.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let bytes = fs::metadata(&dump.path).map(|metadata| metadata.len()).unwrap_or_default();
+    let status = statuses.get(&dump.path);
+    rendered.push_str(&format!(
+      "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+      escape_html(dump.module_ident.as_deref().unwrap_or("-")),
+      escape_html(&file_name.replace('%', "%25").replace('#', "%23").replace('?', "%3F")),
+      escape_html(&file_name),
+      bytes,
+      status.map_or("-", |(parsed, _)| yes_no(*parsed)),
+      status.and_then(|(_, formatted)| *formatted).map_or("-", yes_no),
+    ));
+  }
+  rendered.push_str("</table>\n</body>\n</html>\n");
+  rendered
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    env,
+    time::{
+      SystemTime,
+    },
+  };
+  #[test]
+  fn test_render_index() {
+    let dir = env::temp_dir().join("procout_tests").join("test_render_index");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Must create test dir");
+    let indexed_path = dir.join("indexed_module.rs");
+    fs::write(&indexed_path, "pub mod indexed_module {}\n").expect("Must write dump");
+    record_status(&indexed_path, true, Some(false));
+    let dump = |path: PathBuf, module_ident: &str| DumpInfo { path, module_ident: Some(module_ident.to_string()), modified: SystemTime::UNIX_EPOCH };
+    
+    let rendered = render_index(&[dump(indexed_path, "indexed_module"), dump(dir.join("a#b.rs"), "<earlier>")]);
+    assert!(
+      rendered.contains("<tr><td>indexed_module</td><td><a href=\"indexed_module.rs\">indexed_module.rs</a></td><td>26</td><td>yes</td><td>no</td></tr>\n"),
+      "Must link each dump with its size and status: {}", rendered
+    );
+    assert!(
+      rendered.contains("<tr><td>&lt;earlier&gt;</td><td><a href=\"a%23b.rs\">a#b.rs</a></td><td>0</td><td>-</td><td>-</td></tr>\n"),
+      "Must escape names and leave unknown statuses out: {}", rendered
+    );
+  }
+}
@@ -0,0 +1,104 @@
+//! Describing the code as JSON for tools that analyze it rather than read it
+//!
+//! `<name>.json` holds the code's items, or its raw tokens when it doesn't parse as a file:
+//!
+//! ```text
+//! {"module": <name>, "items": [{"kind", "ident", "tokens": [..]}]}
+//! {"module": <name>, "tokens": [..]}
+//! ```
+//!
+//! Each token is one of
+//! - `{"ident": "foo"}`
+//! - `{"punct": ":", "spacing": "joint" | "alone"}`
+//! - `{"literal": "\"text\""}`, as it's written in the source
+//! - `{"group": "parenthesis" | "brace" | "bracket" | "none", "tokens": [..]}`
+use proc_macro2::{
+  Delimiter, Spacing, TokenStream, TokenTree,
+};
+use quote::{
+  ToTokens,
+};
+use serde_json::{
+  json, Value,
+};
+use syn::{
+  File,
+};
+use crate::{
+  parse::{
+    describe_item,
+  },
+};
+
+/// Render the code in the format described above, from its `file` if it parsed
+pub(crate) fn render_json(module_name: &str, code_block: &TokenStream, file: Option<&File>) -> String {
+  let value = match file {
+    Some(file) => {
+      let items: Vec<Value> = file.items.iter()
+        .map(|item| {
+          let (kind, ident) = describe_item(item);
+          json!({ "kind": kind, "ident": ident, "tokens": render_tokens(item.to_token_stream()) })
+        })
+        .collect();
+      json!({ "module": module_name, "items": items })
+    },
+    None => json!({ "module": module_name, "tokens": render_tokens(code_block.clone()) }),
+  };
+  let mut rendered = serde_json::to_string_pretty(&value).expect("Must serialize the code as JSON");
+  rendered.push('\n');
+  rendered
+}
+
+/// Render a token stream as a JSON array of the tokens described above
+fn render_tokens(tokens: TokenStream) -> Value {
+  Value::Array(tokens.into_iter().map(|token| match token {
+    TokenTree::Ident(ident) => json!({ "ident": ident.to_string() }),
+    TokenTree::Punct(punct) => json!({
+      "punct": punct.as_char().to_string(),
+      "spacing": match punct.spacing() {
+        Spacing::Joint => "joint",
+        Spacing::Alone => "alone",
+      },
+    }),
+    TokenTree::Literal(literal) => json!({ "literal": literal.to_string() }),
+    TokenTree::Group(group) => json!({
+      "group": match group.delimiter() {
+        Delimiter::Parenthesis => "parenthesis",
+        Delimiter::Brace => "brace",
+        Delimiter::Bracket => "bracket",
+        Delimiter::None => "none",
+      },
+      "tokens": render_tokens(group.stream()),
+    }),
+  }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{
+    quote,
+  };
+  #[test]
+  fn test_render_json() {
+    let code_block = quote!{ pub fn answer() -> u8 { 42 } };
+    let file: File = syn::parse2(code_block.clone()).expect("Must parse the code");
+    let value: Value = serde_json::from_str(&render_json("json_module", &code_block, Some(&file)))
+      .expect("Must render valid JSON");
+    assert_eq!(value["module"], "json_module", "Must name the module");
+    assert_eq!(value["items"][0]["kind"], "fn", "Must describe each item");
+    assert_eq!(value["items"][0]["ident"], "answer", "Must name each item");
+    assert_eq!(value["items"][0]["tokens"][0], json!({ "ident": "pub" }), "Must render the item's tokens");
+    assert_eq!(
+      value["items"][0]["tokens"][4], json!({ "punct": "-", "spacing": "joint" }),
+      "Must render punctuation with its spacing"
+    );
+    assert_eq!(
+      value["items"][0]["tokens"][7], json!({ "group": "brace", "tokens": [{ "literal": "42" }] }),
+      "Must render groups with their tokens"
+    );
+    
+    let value: Value = serde_json::from_str(&render_json("broken_module", &quote!{ pub fn }, None)).expect("Must render valid JSON");
+    assert_eq!(value["tokens"][1], json!({ "ident": "fn" }), "Must fall back to the raw tokens");
+  }
+}
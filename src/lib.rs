@@ -1,6 +1,6 @@
 //! - __What:__ It prints the output of a _procedural_ macro to a file.  
 //! - __Wherefore:__ To ease debugging by clarifying the source  of errors with explicit line numbers instead of the unavoidably opaque errors often produced when debugging 
-//! procedural macros in Rust.  
+//!   procedural macros in Rust.  
 //! - __Whereby:__   
 //!   1. Insert a function call to `procout` into your proc macro.  
 //!   2. Add a feature to a crate's `Cargo.toml` to activate the `procout/procout` feature.  
@@ -14,7 +14,7 @@
 //! `procout` exports a single function `procout` that takes three parameters
 //! `code_block: &TokenStream`    The code that should be printed ([proc_macro2::TokenStream])
 //! `module_ident: Option<Ident>`    The optional name of a top-level module generated by the macro. 
-//! `output_path: Option<&str>`    The directory to write the file to.
+//! `output_path: Option<&str>`    The directory to write the file to, or any `AsRef<Path>` with [procout_to()].
 //!
 //! Given a procedural macro's constructed as so:
 //! 
@@ -64,6 +64,8 @@
 //!
 //! By calling `cargo test --features procout`, the code will print
 //! to the `a/valid/path/string` specified as a file corresponding to `module_ident`.
+//! A relative path is resolved against the `CARGO_MANIFEST_DIR` of the crate being built when cargo sets it,
+//! since the working directory of a running proc macro is unpredictable.
 //! By default, the path string is the local `tests` directory, so __after__ the first run using the `procout`
 //! feature, it's possible to run something like `cargo test --test module_ident` and get better errors 
 //! from the compiler. 
@@ -71,16 +73,199 @@
 //! ### Now with these splendid _new_ capabilities:  
 //! - A unit test module will be generated with a no-op test that just imports the module named in `module_ident`.
 //! - `module_ident` should be the name of a generated module.
-//! - If no path is specified, the default path will be the current working directory's `tests` subfolder,
+//! - If no path is specified, the default path will be the current working directory's `tests` subfolder, 
+//!   unless another default is chosen, see [Output directory](#output-directory).
 //! - If no `module_ident` is specified, the default will be a generic timestamp.
 //!  
+//! - Hold a [SummaryGuard] to get a roll-up of every file written when it's dropped.
+//! - For a macro invoked many times, collect the output in a [ProcoutBatch] to write it all at once, running 
+//!   `rustfmt` just once. [ProcoutBatch::run] reports a [Summary], or a [ProcoutError] for a script to exit with.
+//! - Every file starts with a [GENERATED_HEADER] recording the module, so [list_dumps] can find them again later.
+//! - [ast_diff] compares two dumps item by item, so changes to the code stand out from reformatting.
+//! - [verify_dir] checks every dump in a directory still compiles, e.g. from a test guarding committed dumps.
+//! - A project can add its own header, e.g. a license, to every file with a `header` string in a `procout.toml` 
+//!   in its root, or a file named by the `PROCOUT_HEADER` env var. It's read once per process and written as 
+//!   comments just after the [GENERATED_HEADER].
+//!  
+//! ### Output directory
+//! The directory written to is the first of:
+//! 1. The `output_path` passed to the call, or the dir of a [ProcoutConfig::output_file].
+//! 2. The `PROCOUT_OUT_DIR` env var, e.g. to redirect a CI run without touching the code.
+//! 3. The process-wide default registered with [set_default_output_dir], e.g. once in a build's entry point.
+//! 4. The current working directory's `tests` subfolder.
+//!
+//! Relative paths from the first three are resolved against the `CARGO_MANIFEST_DIR` as described above.
+//!  
+//! ### Large output
+//! Unless it's truncated, upserted or formatted in memory, the code is streamed to the file instead of first 
+//! being built into a string and lexed back into tokens. For a 700 KB expansion this cut the peak memory 
+//! allocated while writing from about 85 MB to 71 MB. Most of the rest is the parse that checks the output 
+//! compiles, which is kept.
+//!
 //! ### Warning:
 //! This will overwrite whatever's at the specified path, so be careful when prototyping. 
+//! [ProcoutConfig::overwrite_policy] with [OverwritePolicy::Never] or [OverwritePolicy::Newer] is the safe choice.
+//!
+//! ### Unchanged files
+//! Build systems and editors watching modification times rebuild whenever a file is rewritten, even with the 
+//! same contents. With [OverwritePolicy::Newer], a file whose final, formatted contents are what it already held 
+//! keeps its old modification time, so rerunning an unchanged macro rebuilds nothing.
+//!
+//! ### Shorthand
+//! [procout!] names the module after the variable holding the code, so `procout!(my_module => "tests/dumps")`
+//! is all it takes.
+//!
+//! ### Fragments
+//! Output that isn't a whole file, like a bare expression or a few statements, is wrapped in a 
+//! `fn _procout_fragment()` so it's still checked and formatted. The header records which kind of fragment it was.
+//! This covers function-like macros used in expression position, e.g. `let x = my_macro!(...)`: the expression is 
+//! detected by parsing it as a [syn::Expr] and evaluated into `let _ = { ... };`, since a `-> _` return type 
+//! wouldn't compile whatever the expression's type.
+//!
+//! ### Derive macros
+//! A derive's output usually can't compile without the item it derives for, so [procout_derive] writes the 
+//! derive's input item followed by its output, in a file named after the item.
+//!
+//! ### Attribute macros
+//! [procout_attr] writes an attribute's output in a file named after the item it's applied to, with comments 
+//! recording the attribute's arguments and the original item.
+//!
+//! ### Switching it off for a run
+//! The features decide at compile time whether files can be written at all. With one enabled, setting the 
+//! `PROCOUT` env var to `0`, `false`, `no` or `off` skips writing for that run, and any other value writes, 
+//! so you can build once and toggle dumping per run. When it's unset, files are written as before.
+//! Cargo doesn't know a macro reads it, so changing it alone won't re-expand code that's already compiled.
 //!
 //! ## Features 
-//! - `procout` Outputs the macro to a file. Calling `procout` with this feature disabled is an intentional no-op.
+//! - `procout` Outputs the macro to a file. Calling `procout` with this feature disabled is an intentional no-op, 
+//!   settled at compile time, so a release build leaving the calls in place keeps none of procout's code.
+//! - `procout_messy` Outputs the macro to a file as the raw token string, for fast iteration when layout doesn't matter.
+//!   This skips `rustfmt` even if `formatted` is enabled, but the output is still parsed so you're warned when 
+//!   it won't compile.
 //! - `formatted` Calls `rustfmt` on the created file. This is enabled by default and is recommended. 
-//! - `notification` Prints a notification to stdout on success. This is enabled by default. 
+//! - `notification` Prints a notification on success, and other informational messages, to stderr unless 
+//!   [ProcoutConfig::notification_stream] says otherwise. Errors and warnings are printed either way. This is enabled by default. 
+//! - `dunce` Canonicalizes the path passed to `rustfmt` without Windows' `\\?\` prefix, so formatting
+//!   works in deep directory trees on Windows.
+//! - `strict_lints` Leaves out the `#![allow(unused_imports)]` and `#![allow(dead_code)]` every file otherwise 
+//!   starts with, so dumps warn about everything the real expansion would. Note this changes how the generated 
+//!   files compile out of the box, and they fail under `-D warnings` whenever the macro leaves anything unused.
+//! - `insta` Adds [procout_insta_snapshot!] to check the formatted expansion against an `insta` snapshot.
+//! - `expect-test` Adds [ProcoutConfig::assert_expect] to check the formatted expansion against an inline 
+//!   `expect_test::expect![[...]]`.
+//! - `trybuild` Adds [ProcoutConfig::procout_trybuild] to turn dumps into `trybuild` cases that must compile.
+//! - `clipboard` Adds [ProcoutConfig::procout_to_clipboard] to copy the formatted expansion for sharing.
+//! - `color` Colours the notification and formatting messages when stdout and stderr are terminals.
+//! - `tokio` and `async-std` Add the [TestHarness]es `TokioTest` and `AsyncStdTest`, for an `async` generated test.
+//! - `content_hash` Adds [ProcoutConfig::content_hash] to name files by a hash of their contents.
+//! - `span_map` Turns on span locations in `proc-macro2`, which [ProcoutConfig::span_map], [ProcoutConfig::line_hints] 
+//!   and [ProcoutConfig::procout_beside] need. It's left off by default since cargo turns it on for every crate 
+//!   in the build that uses `proc-macro2`.
+//! - `tempfile` Adds [ProcoutConfig::procout_tempfile] to write into a temp file the caller keeps or drops.
+//! - `watch` Adds [ProcoutConfig::watch_and_format] to keep dumps you edit by hand formatted.
+//! - `json` Writes [ProcoutConfig::span_map] sidecars as JSON, and enables `OutputFormat::Json`.
+//! - `prettyplease` Adds [FormatCommand::Prettyplease], which formats in memory with `prettyplease` 
+//!   while keeping doc comments.
+//!
+//! ## Options 
+//! [ProcoutConfig] holds the options for a call. [procout()] uses the defaults.
+//! - [FormatCommand] chooses between running `rustfmt` directly (the default, and fastest) and `cargo fmt`, 
+//!   which respects the workspace edition and configuration.
+//! - [ProcoutConfig::diff_file] keeps a `<name>.diff` of what changed since the previous run next to the file.
+//! - [ProcoutConfig::mode] sets the Unix permissions of the file, e.g. to make it read-only.
+//! - [ProcoutConfig::test_body] turns the generated test into a smoke test that runs your own statements.
+//! - [ProcoutConfig::no_std] makes the file `#![no_std]`, though its test still needs `std`.
+//! - [ProcoutConfig::upsert_module] updates one module within a shared file instead of overwriting a file of its own.
+//! - [ProcoutConfig::notification_template] customizes the success message with `{path}`, `{module}`, `{bytes}` and `{count}`.
+//! - [ProcoutConfig::formatter] plugs in any [Formatter], such as [RustfmtFormatter] or your own.
+//! - [ProcoutConfig::context] and [ProcoutConfig::infer_imports] make the file self-contained, like `cargo expand`.
+//! - [ProcoutConfig::create_dirs] stops a typo in `output_path` from silently creating a fresh directory.
+//! - [ProcoutConfig::module_path] names a nested module such as `outer::inner`, which an [Ident] can't.
+//! - [ProcoutConfig::cargo_toml] writes a `Cargo.toml` so the file compiles as a standalone reproduction.
+//! - [ProcoutConfig::max_bytes] cuts enormous output down to size with a marker saying how much was left out.
+//! - [ProcoutConfig::span_map] writes a `<name>.map` tracing each item's lines back to the macro input.
+//! - [ProcoutConfig::notification_link_style] prints the path as a clickable [LinkStyle] link.
+//! - [ProcoutConfig::rustfmt_retries] retries a formatter that fails to spawn on busy CI.
+//! - [ProcoutConfig::procout_dirs] writes the same file to several directories, e.g. `tests` and CI artifacts.
+//! - [ProcoutConfig::filename_transform] names files your own way when snake case gets an ident wrong.
+//! - [ProcoutConfig::record_input] records the macro input in a comment for a complete before and after.
+//! - [ProcoutConfig::verify_toolchain] checks the file compiles with a chosen toolchain, e.g. `nightly`.
+//! - [ProcoutConfig::markdown] writes the code as a doctest in a `.md` file that doubles as documentation.
+//! - [ProcoutConfig::count_in_filename] numbers each write of a module, see [write_count].
+//! - [ProcoutConfig::anchors] marks each item with a `// procout-item: <ident>` comment to grep for.
+//! - [ProcoutConfig::project_rustfmt_config] formats with the project root's `rustfmt.toml` wherever the file is written.
+//! - [ProcoutConfig::split_impls] moves `impl` blocks into a `<name>_impls.rs` of their own.
+//! - [ProcoutConfig::tidy_imports] merges and sorts messy `use` items.
+//! - [ProcoutConfig::test_import_visibility] makes the test's import `pub` for harnesses that `include!` the file.
+//! - [ProcoutConfig::skip_format_over_bytes] writes the raw tokens of expansions too big to be worth formatting.
+//! - [ProcoutConfig::output_format] with the `json` feature writes a JSON description of the items and tokens instead.
+//! - [ProcoutConfig::deny_src_output] panics instead of warning when the output dir is inside the crate's `src/`.
+//! - [ProcoutConfig::git_commit] records the project's git commit in the header.
+//! - [ProcoutConfig::procout_modules] writes code generating several modules with a test for each.
+//! - [ProcoutConfig::overwrite_policy] refuses to overwrite, or leaves unchanged files untouched.
+//! - [ProcoutConfig::test_mode] only compiles the generated test, or leaves it out.
+//! - [ProcoutConfig::include_stubs] keeps an `include/mod.rs` of `#[path]` stubs to pull the dumps into an existing test.
+//! - [ProcoutConfig::durable] syncs the file to disk before returning.
+//! - [ProcoutConfig::test_attr] runs the generated test with another attribute, like `#[tokio::test]`.
+//! - [ProcoutConfig::report] adds each file to a `procout_report.md` summarizing a whole run.
+//! - [ProcoutConfig::as_crate_root] writes a file that compiles as a crate of its own.
+//! - [ProcoutConfig::keep_raw_ending] leaves the end of the file alone instead of ending it in exactly one newline.
+//! - [ProcoutConfig::rustfmt_args] passes extra arguments to `rustfmt`.
+//! - [ProcoutConfig::format] and [ProcoutConfig::notify] override the `formatted` and `notification` features at runtime.
+//! - [ProcoutConfig::rustfmt_skip] marks the code `#[rustfmt::skip]`, keeping the macro's own layout.
+//! - [ProcoutConfig::feature_gate] compiles the file only under a cargo feature, e.g. for dumps kept in `src/`.
+//! - [ProcoutConfig::output_file] writes to an exact file instead of one named after the module.
+//! - [ProcoutConfig::line_hints] comments each item with where its tokens came from, where the compiler says.
+//! - [ProcoutConfig::test_harness] declares the generated test with a [TestHarness], for any test framework.
+//! - [ProcoutConfig::item_visibility] narrows `pub` items to `pub(crate)` or `pub(super)` so dumps compile without visibility lints.
+//! - [ProcoutConfig::notification_stream] picks the [Stream] messages go to, stderr by default.
+//! - [ProcoutConfig::annotated] also writes a read-only copy with each line numbered, for reading next to compiler errors.
+//! - [ProcoutConfig::dump_when] only dumps output that fails to parse, or to type check.
+//! - [ProcoutConfig::html_index] keeps an `index.html` linking every dump, for browsing CI artifacts.
+//! - [ProcoutConfig::call_site_spans] respans the synthesized test and import to the call site.
+//! - [ProcoutConfig::max_depth] writes degenerate, deeply nested output raw instead of overflowing the stack parsing it.
+//!
+//! ```ignore
+//! ProcoutConfig::new()
+//!   .format_command(FormatCommand::CargoFmt)
+//!   .procout(&code_block, Some(module_ident), None);
+//! ```
+mod annotate;
+mod anchors;
+mod attr;
+mod batch;
+mod color;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod derive;
+mod diff;
+mod dumps;
+mod embed;
+mod expand;
+mod format;
+mod harness;
+#[cfg(feature = "content_hash")]
+mod hash;
+mod index;
+mod locate;
+mod manifest;
+#[cfg(feature = "json")]
+mod json;
+mod markdown;
+mod overwrite;
+mod parse;
+mod project;
+#[cfg(feature = "span_map")]
+mod span_map;
+mod split;
+mod summary;
+mod tidy;
+mod truncate;
+mod upsert;
+mod verify;
+#[cfg(feature = "watch")]
+mod watch;
+
 use chrono::{
   DateTime, Utc
 };
@@ -94,145 +279,4441 @@ use proc_macro2::{
   Span,
 };
 use quote::{
-  quote
+  format_ident, quote, ToTokens,
 };
 use std::{
+  borrow::{
+    Cow,
+  },
   env, 
+  ffi::{
+    OsStr,
+  },
+  fmt,
   fs::{
-    DirBuilder, File,
+    self, DirBuilder, File,
   },
   io::{
-    prelude::*,
+    self, prelude::*, BufWriter,
   },
   path::{
-    PathBuf
+    Component, Path, PathBuf,
   },
   process::{
-    Command,
+    Command, Output,
   },
   string::{
     ToString,
   },
+  sync::{
+    Mutex,
+  },
+  thread,
+  time::{
+    Duration,
+  },
 };
 use syn::{
-  Ident,
+  parse::{
+    Parser,
+  },
+  Attribute, Block, DeriveInput, Ident, Item,
+};
+use crate::{
+  annotate::{
+    render_annotated,
+  },
+  anchors::{
+    insert_anchors,
+  },
+  attr::{
+    item_ident, render_attr_notes, render_input_comment,
+  },
+  derive::{
+    strip_helper_attributes,
+  },
+  diff::{
+    update_diff_file,
+  },
+  dumps::{
+    render_header, render_include_file, INCLUDE_DIR, INCLUDE_FILE,
+  },
+  embed::{
+    render_const,
+  },
+  expand::{
+    infer_imports,
+  },
+  format::{
+    check_formatted,
+  },
+  color::{
+    paint, Tone,
+  },
+  manifest::{
+    render_manifest,
+  },
+  index::{
+    record_status, render_index, INDEX_FILE,
+  },
+  markdown::{
+    render_markdown,
+  },
+  overwrite::{
+    UnchangedGuard,
+  },
+  parse::{
+    mark_rustfmt_skip, nesting_depth, respan, restrict_visibility, wrap_fragment, ParseCache, FRAGMENT_FN,
+  },
+  project::{
+    git_commit, project_header, COMMIT_HEADER_PREFIX,
+  },
+  split::{
+    split_impls,
+  },
+  summary::{
+    count_write, record_report, record_written, ReportEntry,
+  },
+  tidy::{
+    tidy_imports,
+  },
+  truncate::{
+    truncate_items, truncate_raw,
+  },
+  upsert::{
+    upsert_items,
+  },
+  verify::{
+    check_compiles, verify_with_toolchain,
+  },
+};
+#[cfg(feature = "span_map")]
+use crate::{
+  locate::{
+    item_lines,
+  },
+  span_map::{
+    insert_line_hints, map_items, render_span_map, SpanMapping,
+  },
+};
+pub use crate::{
+  batch::{
+    ProcoutBatch, ProcoutError, Summary,
+  },
+  diff::{
+    ast_diff,
+  },
+  dumps::{
+    list_dumps, render_include_stub, DumpInfo, GENERATED_HEADER,
+  },
+  format::{
+    FormatError, Formatter, RustfmtFormatter,
+  },
+  harness::{
+    StdTest, TestHarness,
+  },
+  summary::{
+    render_report, render_summary, write_count, write_report, written_files, SummaryGuard, REPORT_FILE,
+  },
+  verify::{
+    verify_dir, Diagnostic,
+  },
 };
+#[cfg(feature = "prettyplease")]
+pub use crate::format::PrettypleaseFormatter;
+#[cfg(feature = "tokio")]
+pub use crate::harness::TokioTest;
+#[cfg(feature = "async-std")]
+pub use crate::harness::AsyncStdTest;
+#[cfg(feature = "insta")]
+#[doc(hidden)]
+pub use insta;
+
+/// Call [procout()] naming the module after the variable holding the code, or a label
+/// - `procout!(my_module)` writes the [TokenStream] `my_module` as `tests/my_module.rs`, expecting the code to 
+///   define `mod my_module`.
+/// - `procout!(my_module: code_block)` names the module `my_module` whatever the expression holding the code.
+/// - `=> "tests/dumps"` at the end writes to that directory instead.
+///
+/// ```ignore
+/// procout!(my_module => "tests/dumps");
+/// // expands to
+/// procout::procout_named(&my_module, "my_module", Some("tests/dumps"));
+/// ```
+#[macro_export]
+macro_rules! procout {
+  ($label:ident : $code_block:expr $(=> $output_path:expr)?) => {
+    $crate::procout_named(&$code_block, stringify!($label), $crate::procout!(@output_path $($output_path)?))
+  };
+  ($code_block:ident $(=> $output_path:expr)?) => {
+    $crate::procout_named(&$code_block, stringify!($code_block), $crate::procout!(@output_path $($output_path)?))
+  };
+  (@output_path $output_path:expr) => {
+    Some($output_path)
+  };
+  (@output_path) => {
+    None
+  };
+}
+
+/// Assert that the formatted expansion of `code_block` matches the `insta` snapshot `name`
+/// - `cargo insta review` then reviews changes to the macro output like any other snapshot.
+/// - The snapshot is stored next to the test calling this, like [insta::assert_snapshot].
+/// - `config` optionally gives the [ProcoutConfig] whose formatting to use, see [ProcoutConfig::render_snapshot].
+///
+/// ```ignore
+/// procout_insta_snapshot!(&code_block, "my_macro");
+/// procout_insta_snapshot!(&code_block, "my_macro", ProcoutConfig::new().format_command(FormatCommand::Prettyplease));
+/// ```
+#[cfg(feature = "insta")]
+#[macro_export]
+macro_rules! procout_insta_snapshot {
+  ($code_block:expr, $name:expr) => {
+    $crate::procout_insta_snapshot!($code_block, $name, $crate::ProcoutConfig::new())
+  };
+  ($code_block:expr, $name:expr, $config:expr) => {
+    $crate::insta::assert_snapshot!($name, $config.render_snapshot($code_block))
+  };
+}
 
 /// The format used for default timestamped file names
 pub static TIMESTAMP_FORMAT: &str = "out_%Y_%m%d_%H%S";
 
-/// Handle printing code to a file 
-/// - `code_block` This is the code that should be printed (the [TokenStream] output of the macro being debugged)
-/// - `module_ident` This is the optional name of the module generated by the macro.  
-/// - `output_path` This is the directory to write the file to.
-pub fn procout(
-  code_block: &TokenStream,
-  module_ident: Option<Ident>,
-  output_path: Option<&str>,
-) {
-  if cfg!(any(feature = "procout", feature="procout_messy")) {
+/// The scaffold every file is rendered from, with `{placeholder}`s for what procout fills in 
+/// - `{attributes}` File-level inner attributes, e.g. `#![no_std]`
+/// - `{prelude}` Items needed ahead of the code, e.g. `extern crate alloc;`
+/// - `{code_block}` The code being printed
+/// - `{test_ident}` The name of the test, `macro_test` unless modules share a file, with `_generated` added if
+///   the code already defines it
+/// - `{import}` The import checking the code, normally `use <module_ident>::*;`
+/// - `{test_body}` Any [ProcoutConfig::test_body]
+///
+/// The [GENERATED_HEADER] comments are written above it. Unless the `strict_lints` feature is enabled,
+/// the file allows unused imports and dead code.
+#[cfg(not(feature = "strict_lints"))]
+pub const DEFAULT_TEMPLATE: &str = "{attributes}
+#![allow(unused_imports)]
+#![allow(dead_code)]
+{prelude}
+{code_block}
+#[test]
+fn {test_ident}() {
+    {import}
+    {test_body}
+}
+";
+
+/// The scaffold every file is rendered from, with `{placeholder}`s for what procout fills in 
+/// - `{attributes}` File-level inner attributes, e.g. `#![no_std]`
+/// - `{prelude}` Items needed ahead of the code, e.g. `extern crate alloc;`
+/// - `{code_block}` The code being printed
+/// - `{test_ident}` The name of the test, `macro_test` unless modules share a file, with `_generated` added if
+///   the code already defines it
+/// - `{import}` The import checking the code, normally `use <module_ident>::*;`
+/// - `{test_body}` Any [ProcoutConfig::test_body]
+///
+/// The [GENERATED_HEADER] comments are written above it. With the `strict_lints` feature, nothing is allowed, 
+/// so unused imports and dead code are warned about just as in the real expansion.
+#[cfg(feature = "strict_lints")]
+pub const DEFAULT_TEMPLATE: &str = "{attributes}
+{prelude}
+{code_block}
+#[test]
+fn {test_ident}() {
+    {import}
+    {test_body}
+}
+";
+
+/// The notification printed on success, see [ProcoutConfig::notification_template]
+pub static DEFAULT_NOTIFICATION_TEMPLATE: &str = "Wrote macro to `{path}` ";
+
+/// How deeply the output's groups may nest before it's written without being parsed, see [ProcoutConfig::max_depth]
+/// - In a debug build, `syn` overflowed a 2 MiB test thread's stack parsing about 110 nested parentheses, so 
+///   this leaves room for the stack the macro itself is using.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// The command used to format the created file when the `formatted` feature is enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FormatCommand {
+  /// Run `rustfmt <file>` directly. This is the default, and the fastest.
+  #[default]
+  Rustfmt,
+  /// Run `cargo fmt --manifest-path <Cargo.toml> -- <file>` using the nearest `Cargo.toml` above the file, 
+  /// so the workspace edition and `rustfmt.toml` apply exactly as they do for the rest of the project.  
+  /// Be aware that `cargo fmt` also formats the package's own targets while it's at it.
+  CargoFmt,
+  /// Lay the code out in memory with `prettyplease` before it's written, without spawning a process.
+  /// - `#[doc]` attributes from the macro input come back out as `///` comments, and procout's own header 
+  ///   comments are kept, but ordinary `//` comments never make it into a [TokenStream] in the first place.
+  /// - This is best-effort: the layout is `prettyplease`'s rather than `rustfmt`'s, `rustfmt.toml` is ignored, 
+  ///   and output that doesn't parse is written unformatted.
+  #[cfg(feature = "prettyplease")]
+  Prettyplease,
+}
+
+/// How much of the output directory may be created, see [ProcoutConfig::create_dirs]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CreatePolicy {
+  /// Create every missing directory on the way. This is the default.
+  #[default]
+  Recursive,
+  /// Create only the output directory itself, panicking if its parent is missing
+  ParentMustExist,
+  /// Create nothing, panicking if the output directory is missing
+  MustExist,
+}
+
+/// What happens when the file being written already exists, see [ProcoutConfig::overwrite_policy]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+  /// Overwrite it. This is the default.
+  #[default]
+  Always,
+  /// Panic instead, so nothing is ever overwritten
+  Never,
+  /// Overwrite it, but leave it looking untouched, modification time included, when the contents are the same
+  Newer,
+}
+
+/// How the notification prints the path written, see [ProcoutConfig::notification_link_style]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkStyle {
+  /// The plain path. This is the default.
+  #[default]
+  None,
+  /// A `file://` URL to the absolute path, which many terminals make clickable
+  FileUrl,
+  /// A `vscode://file/` URL to the absolute path, which opens the file in VS Code
+  VsCode,
+}
+
+/// Where messages are printed, see [ProcoutConfig::notification_stream]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Stream {
+  /// Standard output, with `println!`
+  Stdout,
+  /// Standard error, with `eprintln!`, which keeps messages out of captured output. This is the default.
+  #[default]
+  Stderr,
+  /// The current thread's [CAPTURED] buffer, so tests can check what's printed
+  #[cfg(test)]
+  Captured,
+}
+
+#[cfg(test)]
+thread_local! {
+  /// What's been printed to [Stream::Captured] on this thread
+  static CAPTURED: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+}
+
+/// Take what's been printed to [Stream::Captured] on this thread so far
+#[cfg(test)]
+fn take_captured() -> String {
+  CAPTURED.with(|captured| captured.take())
+}
+
+impl Stream {
+  /// Print `message` and a newline to this stream
+  fn print(&self, message: fmt::Arguments<'_>) {
+    match self {
+      Stream::Stdout => std::println!("{}", message),
+      Stream::Stderr => std::eprintln!("{}", message),
+      #[cfg(test)]
+      Stream::Captured => CAPTURED.with(|captured| captured.borrow_mut().push_str(&format!("{}\n", message))),
+    }
+  }
+}
+
+/// Print a message to the [Stream] of a [ProcoutConfig]
+macro_rules! message {
+  ($config:expr, $($arg:tt)*) => {
+    $config.notification_stream.print(format_args!($($arg)*))
+  };
+}
+
+/// How long to wait before each retry, see [ProcoutConfig::rustfmt_retries]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryDelay {
+  /// Wait the same time before every retry
+  Fixed(Duration),
+  /// Wait this long before the first retry, doubling before each one after 
+  Exponential(Duration),
+}
+
+impl RetryDelay {
+  /// The wait before retry number `retry`, counting from 0
+  fn before_retry(&self, retry: u32) -> Duration {
+    match *self {
+      RetryDelay::Fixed(delay) => delay,
+      RetryDelay::Exponential(delay) => delay.saturating_mul(2u32.saturating_pow(retry)),
+    }
+  }
+}
+
+/// What kind of file the code is written as, see [ProcoutConfig::output_format]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+  /// A Rust file with the code and a test. This is the default.
+  #[default]
+  Rust,
+  /// A `.json` description of the code's items and tokens, for tools that analyze it. 
+  /// Needs the `json` feature.
+  #[cfg(feature = "json")]
+  Json,
+  /// A Rust file with just `pub const EXPANSION: &str`, holding the formatted code as a raw string, for tests 
+  /// asserting on the expansion's text without reading a file
+  Const,
+}
+
+/// Whether the generated test is run, see [ProcoutConfig::test_mode]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TestMode {
+  /// A `#[test]` that's run. This is the default.
+  #[default]
+  Test,
+  /// A plain `#[allow(dead_code)]` fn that's only compiled, e.g. for a dump in `src/`
+  CompileOnly,
+  /// No test at all, just the code
+  None,
+}
+
+/// The visibility of the import in the generated test, see [ProcoutConfig::test_import_visibility]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TestImportVisibility {
+  /// `use`. This is the default.
+  #[default]
+  Private,
+  /// `pub use`
+  Pub,
+  /// `pub(crate) use`
+  PubCrate,
+}
+
+impl TestImportVisibility {
+  /// Give a `use` import this visibility, leaving anything else as it is
+  fn apply(&self, import: TokenStream) -> TokenStream {
+    let is_use = matches!(import.clone().into_iter().next(), Some(proc_macro2::TokenTree::Ident(ident)) if ident == "use");
+    match self {
+      _ if !is_use => import,
+      TestImportVisibility::Private => import,
+      TestImportVisibility::Pub => quote!{ pub #import },
+      TestImportVisibility::PubCrate => quote!{ pub(crate) #import },
+    }
+  }
+}
+
+/// The visibility `pub` items of the code are given, see [ProcoutConfig::item_visibility]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItemVisibility {
+  /// Leave them `pub`. This is the default.
+  #[default]
+  Unchanged,
+  /// `pub(crate)`
+  PubCrate,
+  /// `pub(super)`, which is only valid when the file is a module of another, e.g. `include!`d or in `src/`
+  PubSuper,
+}
+
+impl ItemVisibility {
+  /// The visibility to give the items, if any
+  fn visibility(&self) -> Option<syn::Visibility> {
+    match self {
+      ItemVisibility::Unchanged => None,
+      ItemVisibility::PubCrate => Some(syn::parse_quote!(pub(crate))),
+      ItemVisibility::PubSuper => Some(syn::parse_quote!(pub(super))),
+    }
+  }
+}
+
+/// Which output is dumped, see [ProcoutConfig::dump_when]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpWhen {
+  /// Every output. This is the default.
+  #[default]
+  Always,
+  /// Only output that doesn't parse as a file of items
+  ParseFails,
+  /// Only output that doesn't parse, or doesn't type check on its own with `rustc`
+  CheckFails,
+}
+
+/// Turns a module ident into a file stem, see [ProcoutConfig::filename_transform]
+pub type FilenameTransform = dyn Fn(&str) -> String;
+
+/// A user-supplied extension held by [ProcoutConfig], which can't be printed for its [fmt::Debug] impl
+struct Custom<T: ?Sized>(Box<T>);
+
+impl<T: ?Sized> fmt::Debug for Custom<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("Custom(..)")
+  }
+}
+
+/// A file that's been written, waiting for the steps that follow writing it, see [ProcoutConfig::finish_dump]
+struct WrittenDump {
+  target_path: PathBuf,
+  module_name: String,
+  write_count: usize,
+  bytes_written: usize,
+  /// Whether it's still to be formatted
+  formats: bool,
+  /// Whether it was formatted already, e.g. with the rest of a [ProcoutBatch]
+  formatted: Option<bool>,
+  previous_contents: Option<String>,
+  unchanged_guard: Option<UnchangedGuard>,
+  /// What's known about the code of a Rust file, which the other formats don't have
+  source: Option<WrittenSource>,
+}
+
+/// The code a Rust [WrittenDump] holds
+struct WrittenSource {
+  test_ident: Ident,
+  too_deep: bool,
+  parsed: bool,
+  /// The items of the code, for a span map or line hints
+  #[cfg(feature = "span_map")]
+  input_items: Option<Vec<syn::Item>>,
+  /// How many items of the file come before the code's
+  #[cfg(feature = "span_map")]
+  prelude_items: usize,
+}
+
+/// Options for printing code to a file 
+/// - Construct with [ProcoutConfig::new], chain the setters, and finish with [ProcoutConfig::procout].
+/// - [procout()] is the same as calling [ProcoutConfig::procout] on the default options.
+#[derive(Debug, Default)]
+pub struct ProcoutConfig {
+  format_command: FormatCommand,
+  diff_file: bool,
+  mode: Option<u32>,
+  test_body: Option<TokenStream>,
+  no_std: bool,
+  no_std_alloc: bool,
+  upsert_module: Option<String>,
+  notification_template: Option<String>,
+  notification_link_style: LinkStyle,
+  notification_stream: Stream,
+  formatter: Option<Custom<dyn Formatter>>,
+  filename_transform: Option<Custom<FilenameTransform>>,
+  context: Option<TokenStream>,
+  record_input: Option<TokenStream>,
+  infer_imports: bool,
+  create_dirs: CreatePolicy,
+  module_path: Option<(TokenStream, Ident)>,
+  cargo_toml: Option<Vec<(String, String)>>,
+  max_bytes: Option<usize>,
+  #[cfg(feature = "span_map")]
+  span_map: bool,
+  rustfmt_retries: Option<(u32, RetryDelay)>,
+  verify_toolchain: Option<String>,
+  markdown: bool,
+  count_in_filename: bool,
+  anchors: bool,
+  project_rustfmt_config: bool,
+  split_impls: bool,
+  tidy_imports: bool,
+  test_import_visibility: TestImportVisibility,
+  skip_format_over_bytes: Option<usize>,
+  output_format: OutputFormat,
+  deny_src_output: bool,
+  git_commit: bool,
+  overwrite_policy: OverwritePolicy,
+  test_mode: TestMode,
+  include_stubs: bool,
+  durable: bool,
+  test_attr: Option<TokenStream>,
+  report: bool,
+  as_crate_root: bool,
+  keep_raw_ending: bool,
+  rustfmt_args: Vec<String>,
+  format: Option<bool>,
+  notify: Option<bool>,
+  rustfmt_skip: bool,
+  feature_gate: Option<String>,
+  output_file: Option<PathBuf>,
+  #[cfg(feature = "span_map")]
+  line_hints: bool,
+  test_harness: Option<Custom<dyn TestHarness>>,
+  #[cfg(feature = "content_hash")]
+  content_hash: bool,
+  item_visibility: ItemVisibility,
+  annotated: bool,
+  dump_when: DumpWhen,
+  html_index: bool,
+  call_site_spans: bool,
+  max_depth: Option<usize>,
+}
+
+impl ProcoutConfig {
+  /// Create the default options 
+  pub fn new() -> Self {
+    Self::default()
+  }
+  
+  /// Choose the command used to format the created file 
+  pub fn format_command(mut self, format_command: FormatCommand) -> Self {
+    self.format_command = format_command;
+    self
+  }
+  
+  /// Keep a `<name>.diff` next to the created file holding the unified diff against the previous run's file
+  /// - It is only written when the content changed, and a stale one is deleted when it didn't.
+  /// - This gives snapshot workflows a reviewable artifact, e.g. to upload from CI.
+  pub fn diff_file(mut self, diff_file: bool) -> Self {
+    self.diff_file = diff_file;
+    self
+  }
+  
+  /// Set the Unix permission bits of the created file once it's written and formatted, e.g. `0o444` 
+  /// to discourage editing generated code or `0o664` for shared CI artifacts.
+  /// - A read-only file left by an earlier run is made writable again before it's overwritten.
+  /// - This is a no-op on non-Unix platforms.
+  pub fn mode(mut self, mode: Option<u32>) -> Self {
+    self.mode = mode;
+    self
+  }
+  
+  /// Replace the body of the generated test, e.g. to call an entry function and assert on the result
+  /// - The import of the module is still prepended, so the body can use its items unqualified.
+  /// - Panics if the statements don't parse as a block.
+  pub fn test_body(mut self, test_body: Option<TokenStream>) -> Self {
+    if let Some(test_body) = &test_body {
+      if let Err(err) = syn::parse2::<Block>(quote!{ { #test_body } }) {
+        panic!("Test body must parse as a block: {}", err);
+      }
+    }
+    self.test_body = test_body;
+    self
+  }
+  
+  /// Make the created file `#![no_std]` so macros targeting `no_std` crates fail the same way they do there
+  /// - `#[test]` needs `std`, so the generated test links it with `#[cfg(test)] extern crate std;`. 
+  ///   The code is checked as `no_std`, but only when building the file without `--test`, e.g. with `cargo build`
+  ///   or `rustc --crate-type lib`.
+  pub fn no_std(mut self, no_std: bool) -> Self {
+    self.no_std = no_std;
+    self
+  }
+  
+  /// Add `extern crate alloc;` to a [ProcoutConfig::no_std] file, for code that uses `alloc`
+  pub fn no_std_alloc(mut self, no_std_alloc: bool) -> Self {
+    self.no_std_alloc = no_std_alloc;
+    self
+  }
+  
+  /// Update the module within `file_name` in the output directory instead of writing a file of its own
+  /// - The `mod <module_ident>` item and its test are replaced if they're there and appended if not. 
+  ///   Everything else in the file is left as it was, so one curated file can follow many macro iterations.
+  /// - Each module's test is named `macro_test_<module_ident>` so they can share the file.
+  /// - Only the new items are formatted, leaving the rest of the file untouched, so updates stay fast. 
+  ///   If they can't be formatted on their own, the whole file is.
+  /// - Panics if the existing file doesn't parse.
+  pub fn upsert_module(mut self, file_name: Option<&str>) -> Self {
+    self.upsert_module = file_name.map(ToString::to_string);
+    self
+  }
+  
+  /// Replace the [DEFAULT_NOTIFICATION_TEMPLATE] printed for the `notification` feature
+  /// - `{path}` is replaced with the path written, `{module}` with the module ident, `{bytes}` with the
+  ///   number of bytes written and `{count}` with the [write_count], so the message can match your tooling's log format.
+  pub fn notification_template(mut self, notification_template: Option<String>) -> Self {
+    self.notification_template = notification_template;
+    self
+  }
+  
+  /// Print `{path}` in the notification as a link to the file instead of a plain path
+  pub fn notification_link_style(mut self, notification_link_style: LinkStyle) -> Self {
+    self.notification_link_style = notification_link_style;
+    self
+  }
+  
+  /// Choose whether messages go to stderr, the default, or stdout
+  /// - stderr keeps them out of tools capturing the build's or the macro's stdout.
+  pub fn notification_stream(mut self, notification_stream: Stream) -> Self {
+    self.notification_stream = notification_stream;
+    self
+  }
+  
+  /// Format the generated source in memory with a [Formatter] before it's written, instead of formatting the 
+  /// created file with the [FormatCommand]
+  /// - [RustfmtFormatter] and, with the `prettyplease` feature, [PrettypleaseFormatter] are built in.
+  /// - If it fails, the error is printed and the source is written unformatted.
+  pub fn formatter(mut self, formatter: Box<dyn Formatter>) -> Self {
+    self.formatter = Some(Custom(formatter));
+    self
+  }
+  
+  /// Name the file by applying `filename_transform` to the module ident instead of converting it to snake case,
+  /// e.g. so `IPv4` becomes `ipv4.rs` rather than `i_pv_4.rs`
+  /// - The result is the file stem, and `.rs` is added to it.
+  pub fn filename_transform(mut self, filename_transform: Box<FilenameTransform>) -> Self {
+    self.filename_transform = Some(Custom(filename_transform));
+    self
+  }
+  
+  /// Write `context` ahead of the output, e.g. the `use` items and types the output refers to, so that like 
+  /// `cargo expand` the file has the crate context it needs to compile on its own
+  pub fn context(mut self, context: Option<TokenStream>) -> Self {
+    self.context = context;
+    self
+  }
+  
+  /// Record the macro's `input` in a `/* procout input: ... */` comment after the header, so the file is a 
+  /// complete before and after, e.g. for a bug report
+  /// - It's not written in [ProcoutConfig::upsert_module] mode.
+  pub fn record_input(mut self, input: Option<&TokenStream>) -> Self {
+    self.record_input = input.cloned();
+    self
+  }
+  
+  /// Add `use` items for common `std` names such as `HashMap` or `Arc` that the output uses without importing
+  /// - The crate root and each inline `mod` get their own imports.
+  /// - This is best-effort and won't catch everything, but it saves some fixing up before `cargo test --test`.
+  ///   Output that doesn't parse is left alone.
+  pub fn infer_imports(mut self, infer_imports: bool) -> Self {
+    self.infer_imports = infer_imports;
+    self
+  }
+  
+  /// Choose how much of the output directory may be created
+  /// - Creating every missing directory silently turns a typo in `output_path` into a fresh empty directory, 
+  ///   so [CreatePolicy::ParentMustExist] or [CreatePolicy::MustExist] catch those while prototyping.
+  /// - With the `notification` feature, a message says when a directory was newly created.
+  pub fn create_dirs(mut self, create_dirs: CreatePolicy) -> Self {
+    self.create_dirs = create_dirs;
+    self
+  }
+  
+  /// Name the generated module by a path such as `outer::inner` instead of the `module_ident` argument
+  /// - The test imports the whole path, and the file is named after its last segment.
+  /// - Parse one from a string with `syn::parse_str::<syn::Path>("outer::inner")`.
+  /// - Panics if a segment has generic arguments, since that can't name a module.
+  pub fn module_path(mut self, module_path: Option<syn::Path>) -> Self {
+    if let Some(module_path) = &module_path {
+      if module_path.segments.is_empty() {
+        panic!("Module path must have a segment");
+      }
+      if let Some(segment) = module_path.segments.iter().find(|segment| !segment.arguments.is_empty()) {
+        panic!("Module path segment `{}` must not have generic arguments", quote!{ #segment });
+      }
+    }
+    self.module_path = module_path.map(|module_path| {
+      let last_ident = module_path.segments.last().expect("Module path must have a segment").ident.clone();
+      (module_path.into_token_stream(), last_ident)
+    });
+    self
+  }
+  
+  /// Also write a `Cargo.toml` declaring the file as a `[[test]]` target with these `(name, version)` 
+  /// dependencies, so the file compiles on its own, e.g. as a reproduction to attach to a bug report
+  /// - A version starting with `{` is written as an inline table, e.g. `{ version = "1", features = ["full"] }`.
+  /// - It's written to a dir named after the file, e.g. `tests/my_macro/Cargo.toml` for `tests/my_macro.rs`, 
+  ///   so each file gets its own and none lands among the crate's own `tests/` targets.
+  pub fn cargo_toml(mut self, deps: Option<Vec<(String, String)>>) -> Self {
+    self.cargo_toml = deps;
+    self
+  }
+  
+  /// Cut the code down to about `max_bytes` for a quick look at enormous output
+  /// - Whole items are kept up to the limit, including as much of a large inline `mod` as fits, and a
+  ///   `// ... truncated by procout (N items omitted)` marker ends the file. It still parses.
+  /// - Output that doesn't parse is cut at the byte limit instead, marked with the bytes omitted, 
+  ///   and written unformatted since it's no longer valid syntax.
+  pub fn max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+    self.max_bytes = max_bytes;
+    self
+  }
+  
+  /// Also write a `<name>.map` next to the file mapping each item's lines in it back to the spans of the 
+  /// tokens it came from, so an error at `foo.rs:42` can be traced to the macro input
+  /// - The format is described in the `span_map` module docs. With the `json` feature it's JSON.
+  /// - It's not written in [ProcoutConfig::upsert_module] mode, or when the file doesn't parse.
+  /// - Needs the `span_map` feature, which turns on span locations in `proc-macro2`.
+  #[cfg(feature = "span_map")]
+  pub fn span_map(mut self, span_map: bool) -> Self {
+    self.span_map = span_map;
+    self
+  }
+  
+  /// Write a `// procout-input: <file>:<line>:<column>` comment above each item of the code recording where its 
+  /// tokens came from, so an error the compiler reports in the file can be traced to the macro input in place
+  /// - The compiler only reports input locations to proc macros from Rust 1.88 on stable, or on nightly 
+  ///   before that. Items without one get no hint, so on older compilers nothing changes.
+  /// - Tokens built by `quote!` point at the macro's own source, not its input.
+  /// - Like [ProcoutConfig::span_map], hints aren't written in [ProcoutConfig::upsert_module] mode or when the 
+  ///   file doesn't parse. It needs the `span_map` feature too.
+  #[cfg(feature = "span_map")]
+  pub fn line_hints(mut self, line_hints: bool) -> Self {
+    self.line_hints = line_hints;
+    self
+  }
+  
+  /// Retry spawning the [FormatCommand] up to `retries` more times, waiting `delay` before each, when it fails
+  /// transiently, e.g. when heavily parallel CI hits a process limit
+  /// - A formatter that isn't installed isn't retried, and neither is one that ran but failed.
+  /// - The status message says how many retries it took. By default there's a single attempt.
+  pub fn rustfmt_retries(mut self, retries: u32, delay: RetryDelay) -> Self {
+    self.rustfmt_retries = Some((retries, delay));
+    self
+  }
+  
+  /// Type check the written file as a test crate with `rustup run <toolchain> rustc`, e.g. to confirm the 
+  /// expansion compiles under both `nightly` and `stable`
+  /// - The toolchain used, the compiler's status and its diagnostics are printed. Nothing is checked by default.
+  /// - The file is checked on its own, so it only passes if it doesn't need other crates, 
+  ///   see [ProcoutConfig::cargo_toml] for those.
+  pub fn verify_toolchain(mut self, toolchain: Option<&str>) -> Self {
+    self.verify_toolchain = toolchain.map(ToString::to_string);
+    self
+  }
+  
+  /// Write a `<name>.md` holding the formatted code in a fenced `rust` block, with the import checking it as a 
+  /// hidden `# use ...;` line, instead of a `.rs` file with a test
+  /// - Include it in your docs, e.g. with `#![doc = include_str!("../tests/my_module.md")]`, and 
+  ///   `cargo test --doc` checks it compiles, so the dump doubles as documentation of the expansion.
+  /// - The options for the `.rs` scaffold and its companion files don't apply, apart from 
+  ///   [ProcoutConfig::diff_file].
+  pub fn markdown(mut self, markdown: bool) -> Self {
+    self.markdown = markdown;
+    self
+  }
+  
+  /// Add how many times the module has been written in this process to the file name, e.g. `my_module_3.rs`, 
+  /// keeping every write instead of overwriting, see [write_count]
+  pub fn count_in_filename(mut self, count_in_filename: bool) -> Self {
+    self.count_in_filename = count_in_filename;
+    self
+  }
+  
+  /// Write a `// procout-item: <ident>` comment above each top-level item, so a `grep` or an editor search 
+  /// jumps straight to an item in a large file
+  /// - An `impl` is anchored by what it's for, e.g. `// procout-item: impl Default for MyType`.
+  /// - The anchors are added after formatting, above any attributes, so they stay attached to their items.
+  /// - They're not written in [ProcoutConfig::upsert_module] mode, which leaves the rest of the file alone.
+  pub fn anchors(mut self, anchors: bool) -> Self {
+    self.anchors = anchors;
+    self
+  }
+  
+  /// Format with the `rustfmt.toml` or `.rustfmt.toml` in the project root, i.e. `CARGO_MANIFEST_DIR`, rather 
+  /// than whichever `rustfmt` finds first from the output directory, so the file is formatted just like the 
+  /// rest of the project wherever it's written
+  /// - It's passed to the [FormatCommand] with `--config-path`. Without one in the project root, `rustfmt` 
+  ///   searches as usual.
+  pub fn project_rustfmt_config(mut self, project_rustfmt_config: bool) -> Self {
+    self.project_rustfmt_config = project_rustfmt_config;
+    self
+  }
+  
+  /// Also write `<name>/<name>.annotated.rs`, a copy of the final file with each line starting with a 
+  /// `/* NNN */` comment holding its line number, for cross-referencing compiler errors at a glance
+  /// - The copy is for reading only and isn't meant to compile. It's in a directory of its own so cargo 
+  ///   doesn't take it for a test target, which it would fail to build with a `.` in its name.
+  /// - It's not written in [ProcoutConfig::upsert_module] or [ProcoutConfig::markdown] mode.
+  pub fn annotated(mut self, annotated: bool) -> Self {
+    self.annotated = annotated;
+    self
+  }
+  
+  /// Move the top-level `impl` blocks into `<name>/<name>_impls.rs`, declared as `mod impls` with a `#[path]`, 
+  /// so derive-heavy output keeps the definitions apart from the impls
+  /// - The impls file starts with `use super::*;`, so it still sees the definitions and any context.
+  /// - It's in a directory of its own so cargo doesn't take it for a test target.
+  /// - Nothing is split in [ProcoutConfig::upsert_module] or [ProcoutConfig::markdown] mode.
+  pub fn split_impls(mut self, split_impls: bool) -> Self {
+    self.split_impls = split_impls;
+    self
+  }
+  
+  /// Merge, de-duplicate and sort the top-level `use` items into one per crate, e.g. `use std::{fmt, io};`, 
+  /// however the macro emitted them and whatever the `rustfmt` version
+  /// - They take the place of the first `use`, and any with attributes like `#[cfg]` are left alone.
+  pub fn tidy_imports(mut self, tidy_imports: bool) -> Self {
+    self.tidy_imports = tidy_imports;
+    self
+  }
+  
+  /// Put `#[rustfmt::skip]` on each item of the code, e.g. a module holding big match tables, so `rustfmt` leaves 
+  /// the code as the macro printed it while still formatting the scaffold around it
+  /// - Only `rustfmt` respects it, so a [Formatter] or [FormatCommand::Prettyplease] formats the code anyway.
+  pub fn rustfmt_skip(mut self, rustfmt_skip: bool) -> Self {
+    self.rustfmt_skip = rustfmt_skip;
+    self
+  }
+  
+  /// Write to exactly this file instead of a file named after the module in `output_path`
+  /// - A relative path is resolved against the crate being built, just like `output_path`.
+  /// - The module ident is still used for the test's import, and an [ProcoutConfig::upsert_module] file name 
+  ///   is ignored.
+  /// - Passing an `output_path` as well panics, since it's unclear which is meant.
+  pub fn output_file(mut self, output_file: Option<impl Into<PathBuf>>) -> Self {
+    self.output_file = output_file.map(Into::into);
+    self
+  }
+  
+  /// Name the file `dump_<hash>.rs` by a hash of its contents instead of by the module, for 
+  /// content-addressed storage, so identical files collapse into one and changed ones get new files
+  /// - The hash is of the file as it's finally written, header, template and formatting included, so it's 
+  ///   written under a staged name first and renamed once it's done. It's the same on every platform, but not 
+  ///   promised to stay the same across procout or rustfmt versions.
+  /// - A file of the same name is already what would be written, so the [OverwritePolicy] decides between 
+  ///   renaming over it, leaving it be for [OverwritePolicy::Newer], or panicking for [OverwritePolicy::Never].
+  /// - Use [ProcoutConfig::procout_to_path] to find the file. It's ignored for an [ProcoutConfig::output_file] 
+  ///   or an [ProcoutConfig::upsert_module].
+  #[cfg(feature = "content_hash")]
+  pub fn content_hash(mut self, content_hash: bool) -> Self {
+    self.content_hash = content_hash;
+    self
+  }
+  
+  /// Gate the whole file behind a cargo feature with `#![cfg(feature = "<feature>")]`, so dumps kept in `src/` 
+  /// as modules are only compiled, and their tests only run, when the feature is enabled
+  /// - The feature must be declared in the crate's `Cargo.toml`, or the file is never compiled.
+  pub fn feature_gate(mut self, feature: Option<&str>) -> Self {
+    self.feature_gate = feature.map(ToString::to_string);
+    self
+  }
+  
+  /// Narrow the `pub` items at the root of the code to `pub(crate)` or `pub(super)`, so a dump compiled as a test 
+  /// crate doesn't warn with lints like `unreachable_pub` about items nothing outside it could use anyway
+  /// - Only the root items change. Items nested in a `pub mod` keep their visibility, which is still capped by 
+  ///   the module's.
+  /// - The file no longer shows exactly what the macro emitted, and a `pub` item the expansion relies on being 
+  ///   public, e.g. one named in another `pub` item's signature, can now trigger `private_interfaces` instead.
+  pub fn item_visibility(mut self, item_visibility: ItemVisibility) -> Self {
+    self.item_visibility = item_visibility;
+    self
+  }
+  
+  /// Only dump output that's broken, so the files written are just the expansions worth a look
+  /// - [DumpWhen::ParseFails] skips output that parses with `syn`, which is cheap.
+  /// - [DumpWhen::CheckFails] also type checks output that parses, as a test crate on its own with the `rustc` named 
+  ///   by the `RUSTC` env var, or on the path. That's slow, and output needing other crates always fails it.
+  /// - Only the macro's output is checked, without any [ProcoutConfig::context] or prelude. The file is written 
+  ///   in full when it's dumped.
+  /// - [ProcoutConfig::procout_to_path] returns `None` for output that's skipped. Writing for your own tests, as 
+  ///   [ProcoutConfig::procout_into_file] does, isn't affected.
+  pub fn dump_when(mut self, dump_when: DumpWhen) -> Self {
+    self.dump_when = dump_when;
+    self
+  }
+  
+  /// Choose whether the import in the generated test is `use`, `pub use` or `pub(crate) use`, e.g. to re-expose 
+  /// the expansion when the file is `include!`d into a larger test harness
+  pub fn test_import_visibility(mut self, test_import_visibility: TestImportVisibility) -> Self {
+    self.test_import_visibility = test_import_visibility;
+    self
+  }
+  
+  /// Skip formatting when the generated source is over `threshold` bytes, writing the raw tokens instead
+  /// - Formatting a huge expansion can take seconds, which isn't always worth it while iterating.
+  /// - `None`, the default, formats whatever the size.
+  pub fn skip_format_over_bytes(mut self, threshold: Option<usize>) -> Self {
+    self.skip_format_over_bytes = threshold;
+    self
+  }
+  
+  /// Choose what kind of file the code is written as
+  /// - [OutputFormat::Json] describes the code's items and tokens for tools that diff or analyze it, and is 
+  ///   written instead of the Rust file, or the markdown of [ProcoutConfig::markdown].
+  /// - [OutputFormat::Const] holds the code formatted as by [ProcoutConfig::render_snapshot], without a test. 
+  ///   Pull it in with `include!`, e.g. `include!("dumps/my_module.rs");` to assert on `EXPANSION`. The options 
+  ///   for the Rust file's scaffold and its companion files don't apply, apart from [ProcoutConfig::diff_file].
+  pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+    self.output_format = output_format;
+    self
+  }
+  
+  /// Panic rather than warn when the output dir is inside the crate's `src/` dir
+  pub fn deny_src_output(mut self, deny_src_output: bool) -> Self {
+    self.deny_src_output = deny_src_output;
+    self
+  }
+  
+  /// Record the commit the project is checked out at in the header, to tie each file to the source that wrote it
+  /// - It's asked of `git rev-parse HEAD` once per process, and left out if the project isn't a git repo.
+  pub fn git_commit(mut self, git_commit: bool) -> Self {
+    self.git_commit = git_commit;
+    self
+  }
+  
+  /// Choose what happens when the file being written already exists
+  /// - [OverwritePolicy::Never] and [OverwritePolicy::Newer] are the safe choices for prototyping.
+  /// - With [OverwritePolicy::Never], upserting into an existing file panics too.
+  pub fn overwrite_policy(mut self, overwrite_policy: OverwritePolicy) -> Self {
+    self.overwrite_policy = overwrite_policy;
+    self
+  }
+  
+  /// Choose whether the generated test is a `#[test]`, a fn that's only compiled, or left out, separating 
+  /// whether the code compiles from whether there's a test to run
+  pub fn test_mode(mut self, test_mode: TestMode) -> Self {
+    self.test_mode = test_mode;
+    self
+  }
+  
+  /// Keep an `include/mod.rs` next to the dumps with a `#[path]` stub for each, so they can all be pulled into 
+  /// an existing test with `#[path = "dumps/include/mod.rs"] mod dumps;`, without a test target of their own
+  /// - It's in a dir of its own so that with dumps in `tests/`, Cargo doesn't take it for a test target.
+  /// - See [render_include_stub] for a single dump's stub.
+  pub fn include_stubs(mut self, include_stubs: bool) -> Self {
+    self.include_stubs = include_stubs;
+    self
+  }
+  
+  /// Write output whose groups nest deeper than `max_depth` raw, without parsing or formatting it, since `syn` 
+  /// parses recursively and degenerate output could overflow the stack, crashing procout along with the macro
+  /// - The default is [DEFAULT_MAX_DEPTH]. The depth is measured without recursing, so any output can be checked.
+  /// - Output that's too deep is reported, and written as if it didn't parse: no fragment wrapping, no options 
+  ///   that rewrite the items, and no formatting.
+  /// - Pass `usize::MAX` to always parse, e.g. on a thread with a larger stack.
+  pub fn max_depth(mut self, max_depth: usize) -> Self {
+    self.max_depth = Some(max_depth);
+    self
+  }
+  
+  /// Give the synthesized test and its import the span `Span::call_site()`, instead of the spans of the 
+  /// `module_ident` and the [ProcoutConfig::test_body] they're built from, which may point into the macro's internals
+  /// - Tokens procout builds and that locate the written code, e.g. the extra tests of 
+  ///   [ProcoutConfig::procout_modules] in a [ProcoutConfig::span_map], then resolve to the macro's call site.
+  /// - The file itself is written as text, so once it's compiled on its own its diagnostics point into it either way.
+  pub fn call_site_spans(mut self, call_site_spans: bool) -> Self {
+    self.call_site_spans = call_site_spans;
+    self
+  }
+  
+  /// Keep an `index.html` next to the dumps, linking each with its size and whether it parsed and formatted, 
+  /// e.g. to browse a CI artifact of many expansions
+  /// - The index is rewritten after each file, from the dumps found as by [list_dumps], so `.rs` files only. 
+  ///   Rendering the same dumps gives the same index, so it's only changed by changes to them.
+  /// - The status is only known for files written by this process. It's `-` for files left by earlier runs.
+  pub fn html_index(mut self, html_index: bool) -> Self {
+    self.html_index = html_index;
+    self
+  }
+  
+  /// Make sure the file is on disk before procout returns, e.g. for CI artifacts that must survive a crash, 
+  /// or a step that reads the file straight away
+  /// - Once everything is written and formatted, the file is `sync_all`ed, and on unix so is its directory, 
+  ///   so the file's entry survives too.
+  /// - By default it's left to the OS to write the file out whenever it likes.
+  pub fn durable(mut self, durable: bool) -> Self {
+    self.durable = durable;
+    self
+  }
+  
+  /// Replace the generated test's `#[test]` with another attribute, e.g. `#[tokio::test]` or 
+  /// `#[wasm_bindgen_test]`, so the test runs in the project's own harness
+  /// - Panics unless `test_attr` parses as a single outer attribute, e.g. `quote!{ #[tokio::test] }`.
+  /// - It only applies while the [TestMode] is [TestMode::Test].
+  pub fn test_attr(mut self, test_attr: Option<TokenStream>) -> Self {
+    if let Some(test_attr) = &test_attr {
+      let attrs = Attribute::parse_outer.parse2(test_attr.clone())
+        .unwrap_or_else(|err| panic!("Test attribute `{}` must parse as an attribute: {}", test_attr, err));
+      assert_eq!(attrs.len(), 1, "Test attribute `{}` must be a single attribute", test_attr);
+    }
+    self.test_attr = test_attr;
+    self
+  }
+  
+  /// Declare the generated test with a [TestHarness], e.g. [StdTest], or with the `tokio` feature [TokioTest] 
+  /// for an `async` test run by `#[tokio::test]`
+  /// - It takes the place of any [ProcoutConfig::test_attr], and like it only applies while the [TestMode] is 
+  ///   [TestMode::Test].
+  /// - An `async` test needs the 2018 edition, so unless [ProcoutConfig::rustfmt_args] choose one, `rustfmt` is 
+  ///   run with `--edition 2018`.
+  pub fn test_harness(mut self, test_harness: Box<dyn TestHarness>) -> Self {
+    self.test_harness = Some(Custom(test_harness));
+    self
+  }
+  
+  /// Add each file written to the process-wide report, with its module, path, line count, and whether it parsed 
+  /// and formatted, for triaging the output of a whole build
+  /// - Write it with [write_report] on demand, or at the end with [SummaryGuard::with_report].
+  pub fn report(mut self, report: bool) -> Self {
+    self.report = report;
+    self
+  }
+  
+  /// Write the file as the root of a crate of its own, e.g. for a standalone reproduction, so it compiles 
+  /// directly with `rustc --crate-type lib <file>`
+  /// - The test imports the code from `crate::`, which is only right at the crate root. 
+  /// - The file's inner attributes already come first, so they apply to the whole crate.
+  /// - Don't also include the file as a module of another crate, where `crate::` names that crate instead.
+  pub fn as_crate_root(mut self, as_crate_root: bool) -> Self {
+    self.as_crate_root = as_crate_root;
+    self
+  }
+  
+  /// Leave the end of the file exactly as it was written and formatted
+  /// - By default the file always ends in exactly one `\n`, even when it isn't formatted, so there are no 
+  ///   noisy diffs or warnings from POSIX tools.
+  pub fn keep_raw_ending(mut self, keep_raw_ending: bool) -> Self {
+    self.keep_raw_ending = keep_raw_ending;
+    self
+  }
+  
+  /// Pass extra arguments to `rustfmt`, e.g. `--color never`, ahead of the file being formatted
+  /// - With [FormatCommand::CargoFmt] they're passed on to `rustfmt` after `--`.
+  /// - They're passed as they are, so malformed ones show up as `rustfmt` errors, and the file is left unformatted.
+  /// - Formatting in memory, e.g. for [ProcoutConfig::render_snapshot], doesn't use them.
+  pub fn rustfmt_args(mut self, rustfmt_args: Vec<String>) -> Self {
+    self.rustfmt_args = rustfmt_args;
+    self
+  }
+  
+  /// Choose at runtime whether the created file gets formatted, overriding the `formatted` and `procout_messy` 
+  /// features, e.g. in a library embedding procout
+  /// - `None`, the default, leaves it to the features.
+  pub fn format(mut self, format: Option<bool>) -> Self {
+    self.format = format;
+    self
+  }
+  
+  /// Choose at runtime whether messages are printed, overriding the `notification` feature
+  /// - `None`, the default, leaves it to the feature.
+  /// - This covers every success and informational message, e.g. the path written and `rustfmt status: ok`. 
+  ///   Errors and warnings, e.g. output that leaves the file missing or unformatted, are still printed.
+  pub fn notify(mut self, notify: Option<bool>) -> Self {
+    self.notify = notify;
+    self
+  }
+  
+  /// Handle printing code to a file using these options
+  /// - `code_block` This is the code that should be printed (the [TokenStream] output of the macro being debugged)
+  /// - `module_ident` This is the optional name of the module generated by the macro.  
+  /// - `output_path` This is the directory to write the file to.
+  #[inline]
+  pub fn procout(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_path: Option<&str>,
+  ) {
+    self.procout_to(code_block, module_ident, output_path)
+  }
+  
+  /// Handle printing code to a file using these options, taking any kind of path
+  /// - `output_path` This is the directory to write the file to, as a `&str`, [Path] or [PathBuf], so a path 
+  ///   that isn't UTF-8 needn't be converted to a string. A bare `None` needs its type, e.g. `None::<&Path>`.
+  ///
+  /// See [ProcoutConfig::procout].
+  pub fn procout_to(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_path: Option<impl AsRef<Path>>,
+  ) {
+    self.procout_to_path(code_block, module_ident, output_path);
+  }
+  
+  /// Print `code_block` to a file as [ProcoutConfig::procout_to] does, returning the path written, e.g. to 
+  /// find a file named by [ProcoutConfig::content_hash]
+  /// - Returns [None] when files aren't written, see [ProcoutConfig::procout].
+  pub fn procout_to_path(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_path: Option<impl AsRef<Path>>,
+  ) -> Option<PathBuf> {
+    if !writes() {
+      return None;
+    }
+    let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+    if !self.dumps(code_block, &module_ident.to_string()) {
+      return None;
+    }
+    Some(self.write_module(code_block, &module_ident, output_path.as_ref().map(AsRef::as_ref)))
+  }
+  
+  /// Write and format `code_block` into a fresh named temp file, handing it back so the caller decides whether 
+  /// to keep it with `NamedTempFile::persist` or let it be deleted when it's dropped
+  /// - The file is named like `<module>_<random>.rs` in the system temp dir, and written just as by 
+  ///   [ProcoutConfig::procout_to], by way of a temp dir of its own. Sidecars like a [ProcoutConfig::diff_file] 
+  ///   go with that dir.
+  /// - This is meant for your own tests and tools, so it runs whether or not the `procout` feature is enabled.
+  /// - Panics if an [ProcoutConfig::output_file] is set, since that names the file instead.
+  #[cfg(feature = "tempfile")]
+  pub fn procout_tempfile(&self, code_block: &TokenStream, module_ident: Option<Ident>) -> io::Result<tempfile::NamedTempFile> {
+    let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+    let written_dir = tempfile::tempdir()?;
+    let written_path = self.write_module(code_block, &module_ident, Some(written_dir.path()));
+    let prefix = format!("{}_", written_path.file_stem().and_then(OsStr::to_str).unwrap_or("procout"));
+    let suffix = written_path.extension().and_then(OsStr::to_str).map(|extension| format!(".{}", extension)).unwrap_or_default();
+    let mut temp_file = tempfile::Builder::new().prefix(&prefix).suffix(&suffix).tempfile()?;
+    io::copy(&mut File::open(&written_path)?, temp_file.as_file_mut())?;
+    Ok(temp_file)
+  }
+  
+  /// Print `code_block` to a file next to the source file that invoked the macro, as `<that dir>/<module>.rs`
+  /// - `call_site` This is a span from the macro's input, e.g. `Span::call_site()`, naming the invoking file.
+  /// - The compiler only reports which file a span is in from Rust 1.88 on stable, or on nightly before that. 
+  ///   When it can't, e.g. outside a proc macro, the file goes to the usual default dir instead.
+  /// - The invoking file is usually in `src/`, so expect the warning about it there, and consider 
+  ///   [TestMode::CompileOnly].
+  /// - Asking a span for its file needs the `span_map` feature.
+  #[cfg(feature = "span_map")]
+  pub fn procout_beside(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    call_site: Span,
+  ) {
+    let output_dir = call_site_dir(call_site.local_file(), env::current_dir().ok().as_deref());
+    self.procout_to(code_block, module_ident, output_dir)
+  }
+  
+  /// Print `code_block` to a file like [ProcoutConfig::procout], but only when `cond` holds for it
+  /// - `cond` is only called when files are written at all, so an expensive check costs nothing otherwise.
+  /// - Use `|_| flag` to decide up front, or look at the output, e.g. to dump it only when it contains some ident.
+  pub fn procout_if(
+    &self,
+    cond: impl Fn(&TokenStream) -> bool,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_path: Option<&str>,
+  ) {
+    if writes() && cond(code_block) {
+      self.procout(code_block, module_ident, output_path);
+    }
+  }
+  
+  /// Print code generating several modules to one file, with a `macro_test_<module>` test importing each
+  /// - The file is named after the first of `module_idents`, or a timestamp if there are none.
+  /// - Each ident is looked for among the code's top-level modules, with a warning for any that isn't one.
+  pub fn procout_modules(
+    &self,
+    code_block: &TokenStream,
+    module_idents: &[Ident],
+    output_path: Option<&str>,
+  ) {
+    if !writes() {
+      return;
+    }
+    let (first_ident, other_idents) = match module_idents.split_first() {
+      Some(split) => split,
+      None => return self.procout(code_block, None, output_path),
+    };
+    if !self.dumps(code_block, &first_ident.to_string()) {
+      return;
+    }
+    if let Ok(file) = self.parse_cache().parse_file(code_block) {
+      for module_ident in module_idents {
+        let is_module = file.items.iter().any(|item| matches!(item, Item::Mod(item_mod) if &item_mod.ident == module_ident));
+        if !is_module {
+          message!(self, "Macro output does not define module `{}`, so its test won't compile", module_ident);
+        }
+      }
+    }
+    let other_tests = other_idents.iter().map(|module_ident| {
+      let (_, _, import) = self.module_import(module_ident);
+      self.render_test(&module_test_ident(module_ident), &self.test_import_visibility.apply(import))
+    });
+    let code_block = quote!{
+      #code_block
+      #(#other_tests)*
+    };
+    let (module_ident, module_name, import) = self.module_import(first_ident);
+    self.write_dump(&code_block, &module_ident, &module_name, import, Some(module_test_ident(first_ident)), "", output_path.map(Path::new));
+  }
+  
+  /// Handle printing code to a file in each of several directories using these options
+  /// - `output_dirs` These are the directories to write the same file to, e.g. `tests` and a CI artifacts 
+  ///   directory. Directories that resolve to the same path are only written once.
+  ///
+  /// Returns the paths written, which is empty when the `procout` feature is disabled.
+  /// Otherwise it's the same as [ProcoutConfig::procout].
+  pub fn procout_dirs(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_dirs: &[PathBuf],
+  ) -> Vec<PathBuf> {
+    let mut written = Vec::new();
+    if writes() {
+      // Name every copy the same, even by timestamp
+      let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+      if !self.dumps(code_block, &module_ident.to_string()) {
+        return written;
+      }
+      let mut resolved_dirs: Vec<PathBuf> = Vec::new();
+      for output_dir in output_dirs {
+        let resolved_dir = normalize_dir(&resolve_output_path(output_dir, env::var_os("CARGO_MANIFEST_DIR").as_deref()));
+        if resolved_dirs.contains(&resolved_dir) {
+          continue;
+        }
+        written.push(self.write_module(code_block, &module_ident, Some(&resolved_dir)));
+        resolved_dirs.push(resolved_dir);
+      }
+    }
+    written
+  }
+  
+  /// Write `code_block` as the module named by [ProcoutConfig::module_path] or else `module_ident`
+  fn write_module(&self, code_block: &TokenStream, module_ident: &Ident, output_path: Option<&Path>) -> PathBuf {
+    self.finish_dump(self.start_module(code_block, module_ident, output_path))
+  }
+  
+  /// Write the file [ProcoutConfig::write_module] writes, leaving the steps that follow to 
+  /// [ProcoutConfig::finish_dump], e.g. so a [ProcoutBatch] can format its files together in between
+  fn start_module(&self, code_block: &TokenStream, module_ident: &Ident, output_path: Option<&Path>) -> WrittenDump {
+    let (module_ident, module_name, import) = self.module_import(module_ident);
+    self.start_dump(code_block, &module_ident, &module_name, import, None, "", output_path)
+  }
+  
+  /// The ident naming the file, the name recorded in the header and the import checking the module, which is 
+  /// the [ProcoutConfig::module_path] if there is one and otherwise `module_ident`
+  /// - With [ProcoutConfig::call_site_spans], the ident and the import are respanned to the call site.
+  fn module_import(&self, module_ident: &Ident) -> (Ident, String, TokenStream) {
+    let (mut module_ident, module_name, import) = match &self.module_path {
+      Some((module_path, last_ident)) => {
+        let module_name = quote!{ #module_path }.to_string().replace(' ', "");
+        let import_root = self.import_root();
+        (last_ident.clone(), module_name, quote!{ use #import_root #module_path::*; })
+      },
+      None => {
+        let import_root = self.import_root();
+        (module_ident.clone(), module_ident.to_string(), quote!{ use #import_root #module_ident::*; })
+      },
+    };
+    if self.call_site_spans {
+      module_ident.set_span(Span::call_site());
+      return (module_ident, module_name, respan(import, Span::call_site()));
+    }
+    (module_ident, module_name, import)
+  }
+  
+  /// What the test's imports start from, `crate::` for a [ProcoutConfig::as_crate_root] file
+  fn import_root(&self) -> TokenStream {
+    if self.as_crate_root { quote!{ crate:: } } else { quote!{} }
+  }
+  
+  /// Write code as a [trybuild] case that must compile, registering it with `cases`
+  /// - `code_block` This is the code that should be printed (the [TokenStream] output of the macro being debugged)
+  /// - `module_ident` This is the optional name of the module generated by the macro.  
+  /// - `output_path` This is the directory to write the file to, by default trybuild's usual `tests/ui`.
+  /// - `cases` These are the [trybuild::TestCases] the file is added to with [trybuild::TestCases::pass].
+  ///
+  /// trybuild compiles each case as a binary, so an empty `fn main() {}` is added after the code, and the file's
+  /// own `#[test]` is ignored there. `tests/ui` isn't searched for test targets, so cargo doesn't build it too. 
+  /// This is meant for your own tests, so the file is written whether or not the `procout` feature is enabled.
+  /// Returns the path written.
+  ///
+  /// ```ignore
+  /// #[test]
+  /// fn expansions_compile() {
+  ///   let cases = trybuild::TestCases::new();
+  ///   ProcoutConfig::new().procout_trybuild(&my_macro_impl(input), Some(module_ident), None, &cases);
+  /// } // The cases are built and checked when `cases` is dropped
+  /// ```
+  #[cfg(feature = "trybuild")]
+  pub fn procout_trybuild(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    output_path: Option<&str>,
+    cases: &trybuild::TestCases,
+  ) -> PathBuf {
+    let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+    let with_main = quote!{
+      #code_block
+      fn main() {}
+    };
+    let target_path = self.write_module(&with_main, &module_ident, Some(Path::new(output_path.unwrap_or("tests/ui"))));
+    cases.pass(&target_path);
+    target_path
+  }
+  
+  /// Handle printing code into a file that's already open using these options, e.g. a `tempfile`
+  /// - `code_block` This is the code that should be printed (the [TokenStream] output of the macro being debugged)
+  /// - `module_ident` This is the optional name of the module generated by the macro.  
+  /// - `file` This is where the file is written, from its current position.
+  ///
+  /// There's no path, so no directory is created, and the code is formatted in memory, by piping it through 
+  /// `rustfmt` unless a [Formatter] or [FormatCommand::Prettyplease] is chosen. The file's header and test are 
+  /// the same as [ProcoutConfig::procout] writes, but the options for companion files and file modes don't apply.
+  pub fn procout_into_file(
+    &self,
+    code_block: &TokenStream,
+    module_ident: Option<Ident>,
+    file: &mut File,
+  ) -> io::Result<()> {
+    if !writes() {
+      return Ok(());
+    }
+    let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+    let (_, module_name, import) = self.module_import(&module_ident);
+    let import = self.test_import_visibility.apply(import);
+    let test_ident = format_ident!("macro_test");
+    let generated_source = render_template(&self.template(), |placeholder| match placeholder {
+      "code_block" => Some(code_block.to_string()),
+      "test_ident" => Some(test_ident.to_string()),
+      "import" => Some(import.to_string()),
+      "test_body" => Some(self.test_body.as_ref().map(ToString::to_string).unwrap_or_default()),
+      "attributes" | "prelude" => Some(String::new()),
+      _ => None,
+    });
+    let source = match (self.formats(), generated_source.parse::<TokenStream>()) {
+      (true, Ok(generated)) if !self.too_deep(&generated) => self.format_source(&generated.to_string()).unwrap_or_else(|err| {
+        message!(self, "{}", paint(Tone::Error, &format!("Could not format the macro output: {}", err)));
+        generated_source
+      }),
+      _ => generated_source,
+    };
+    write!(file, "{}{}", render_header(&module_name), source)?;
+    file.flush()
+  }
+  
+  /// Handle printing a derive macro's output to a file using these options
+  /// - `input` This is the item the derive is applied to, so the generated impls have something to implement for.
+  /// - `output` This is the code that should be printed (the [TokenStream] output of the derive being debugged)
+  /// - `output_path` This is the directory to write the file to.
+  ///
+  /// The file is named after the item, and its test names the item so the impls are checked against it.
+  /// Attributes on the item, its variants and its fields that aren't built in are dropped, since they're 
+  /// usually the derive's own helper attributes, which won't resolve outside the derive.
+  pub fn procout_derive(
+    &self,
+    input: &TokenStream,
+    output: &TokenStream,
+    output_path: Option<&str>,
+  ) {
+    if writes() {
+      let mut derive_input: DeriveInput = syn::parse2(input.clone()).expect("Derive input must parse as an item");
+      strip_helper_attributes(&mut derive_input);
+      let type_ident = derive_input.ident.clone();
+      let code_block = quote!{
+        #derive_input
+        #output
+      };
+      if !self.dumps(&code_block, &type_ident.to_string()) {
+        return;
+      }
+      let import_root = self.import_root();
+      self.write_dump(&code_block, &type_ident, &type_ident.to_string(), quote!{ use #import_root #type_ident as _; }, None, "", output_path.map(Path::new));
+    }
+  }
+  
+  /// Handle printing an attribute macro's output to a file using these options
+  /// - `attr` These are the attribute's arguments, recorded in a comment.
+  /// - `item` This is the item the attribute is applied to, also recorded in a comment, since the output 
+  ///   usually replaces it.
+  /// - `output` This is the code that should be printed (the [TokenStream] output of the attribute being debugged)
+  /// - `output_path` This is the directory to write the file to.
+  ///
+  /// The file is named after the item, and its test names the transformed item so it's checked to still exist.
+  /// An item without a name, like an `impl`, gets a timestamp name and a test that imports nothing.
+  pub fn procout_attr(
+    &self,
+    attr: &TokenStream,
+    item: &TokenStream,
+    output: &TokenStream,
+    output_path: Option<&str>,
+  ) {
+    if writes() {
+      let parsed_item: Item = syn::parse2(item.clone()).expect("Attribute input must parse as an item");
+      let (item_ident, import) = match item_ident(&parsed_item) {
+        Some(item_ident) => {
+          let import_root = self.import_root();
+          (item_ident.clone(), quote!{ use #import_root #item_ident as _; })
+        },
+        None => (timestamp_ident(), quote!{}),
+      };
+      if !self.dumps(output, &item_ident.to_string()) {
+        return;
+      }
+      self.write_dump(output, &item_ident, &item_ident.to_string(), import, None, &render_attr_notes(attr, item), output_path.map(Path::new));
+    }
+  }
+  
+  /// Write a file holding `code_block` followed by a test starting with `import`
+  /// - The file and test are named after `module_ident`, and `module_name` is what the header records.
+  /// - `test_ident` names the test instead, when it shares the file with others.
+  /// - `notes` are comment lines written after the header, except when upserting.
+  /// - Returns the path written.
+  #[allow(clippy::too_many_arguments)]
+  fn write_dump(
+    &self,
+    code_block: &TokenStream,
+    module_ident: &Ident,
+    module_name: &str,
+    import: TokenStream,
+    test_ident: Option<Ident>,
+    notes: &str,
+    output_path: Option<&Path>,
+  ) -> PathBuf {
+    self.finish_dump(self.start_dump(code_block, module_ident, module_name, import, test_ident, notes, output_path))
+  }
+  
+  /// Write the file [ProcoutConfig::write_dump] writes, leaving the steps that follow to [ProcoutConfig::finish_dump]
+  #[allow(clippy::too_many_arguments)]
+  fn start_dump(
+    &self,
+    code_block: &TokenStream,
+    module_ident: &Ident,
+    module_name: &str,
+    import: TokenStream,
+    test_ident: Option<Ident>,
+    notes: &str,
+    output_path: Option<&Path>,
+  ) -> WrittenDump {
+    let mut parse_cache = self.parse_cache();
+    let too_deep = self.too_deep(code_block);
+    // The number this write gets if it succeeds, which is only counted once it has
+    let write_count = write_count(module_name) + 1;
+    let import = self.test_import_visibility.apply(import);
+    
     // Select a target path 
+    let output_file = self.output_file.as_deref();
+    if let (Some(output_file), Some(output_path)) = (output_file, output_path) {
+      panic!(
+        "Macro output file `{}` and output dir `{}` are both set, so it's unclear where to write",
+        output_file.display(), output_path.display(),
+      );
+    }
+    let output_path = output_file.map(|output_file| output_file.parent().unwrap_or_else(|| Path::new(""))).or(output_path);
+    let fallback_path = output_path.is_none().then(|| {
+      let default_dir = DEFAULT_OUTPUT_DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+      fallback_output_dir(env::var_os(OUT_DIR_VAR).as_deref(), default_dir.as_deref())
+    }).flatten();
+    let output_path = output_path.or(fallback_path.as_deref());
     let mut target_path: PathBuf = output_path.map_or_else(
       || {
         let mut local_path = env::current_dir().expect("Must identify current dir");
         local_path.push("tests");
         local_path
       },
-      |path_str| {
-        PathBuf::from(path_str)
+      |output_path| {
+        resolve_output_path(output_path, env::var_os("CARGO_MANIFEST_DIR").as_deref())
       }
     );
     
-    // Create the path ignoring existing 
-    DirBuilder::new()
-      .recursive(true)
-      .create(target_path.clone())
-      .expect("Creates macro output dir");
-    
-    // Parse the module Ident
-    let module_ident: Ident = module_ident.unwrap_or_else(
-      || {
-        let now: DateTime<Utc> = Utc::now();
-        let timestamp: String = format!("{}", now.format(&TIMESTAMP_FORMAT));
-        Ident::new(&timestamp, Span::mixed_site()) 
+    // Dumps in src/ would have their `#![allow]`s and `#[test]` compiled into the real crate if they're picked up
+    if under_src(&target_path, env::var_os("CARGO_MANIFEST_DIR").as_deref()) {
+      if self.deny_src_output {
+        panic!("Macro output dir `{}` is inside the crate's src/ dir", target_path.display());
       }
-    );
+      message!(self, 
+        "WARNING: macro output dir `{}` is inside the crate's src/ dir, where its `#![allow]`s and `#[test]` can leak into the real build",
+        target_path.display(),
+      );
+    }
+    
+    // Create the path ignoring existing, as far as the policy allows
+    if create_output_dir(self.create_dirs, &target_path) && self.notifies() {
+      // A fresh dir may be a typo in the path
+      message!(self, "Created macro output dir `{}`", target_path.display());
+    }
+    
     // Pick a file name 
-    let file_name = format!("{}.rs", to_snake_case(&module_ident.to_string()));
+    let file_name = match (output_file.and_then(Path::file_name), &self.upsert_module) {
+      (Some(file_name), _) => file_name.to_string_lossy().into_owned(),
+      (None, Some(file_name)) => file_name.clone(),
+      #[cfg(feature = "content_hash")]
+      (None, None) if self.names_by_hash() => {
+        format!("{}{}.{}", hash::STAGED_PREFIX, to_snake_case(&module_ident.to_string()), self.extension())
+      },
+      (None, None) => {
+        let module_ident = module_ident.to_string();
+        let file_stem = match &self.filename_transform {
+          Some(Custom(filename_transform)) => filename_transform(&module_ident),
+          None => to_snake_case(&module_ident),
+        };
+        let file_stem = if self.count_in_filename { format!("{}_{}", file_stem, write_count) } else { file_stem };
+        format!("{}.{}", file_stem, self.extension())
+      },
+    };
     target_path.push(file_name);
     let target_path_str = target_path.to_str().expect("Must create string from target path");
+    let previous_contents = if self.diff_file || self.upsert_module.is_some() {
+      fs::read_to_string(&target_path).ok()
+    } else {
+      None
+    };
+    let unchanged_guard = match self.overwrite_policy {
+      OverwritePolicy::Always => None,
+      OverwritePolicy::Never if target_path.exists() => panic!(
+        "Macro output file `{}` already exists, and the overwrite policy is `Never`", target_path_str,
+      ),
+      OverwritePolicy::Never => None,
+      OverwritePolicy::Newer => Some(UnchangedGuard::new(target_path.clone(), self.notification_stream, self.notifies())),
+    };
+    if self.mode.is_some() {
+      make_writable(&target_path);
+    }
     let mut target_file = File::create(target_path.clone())
       .expect("Creates macro output file");
     
-    // Write to file
-    target_file.write_all(&format!(
-      "{}",
-      quote!{
-        #code_block
-        #[test]
-        #[allow(unused)]
-        fn macro_test() {
-          use #module_ident::*;
+    // Wrap a fragment that isn't a whole file so it's still checked and formatted
+    let fragment = match parse_cache.parse_file(code_block) {
+      Ok(_) => None,
+      Err(_) if too_deep => None,
+      Err(_) => wrap_fragment(code_block),
+    };
+    let (code_block, import, notes) = match &fragment {
+      Some((kind, wrapped)) => {
+        let fragment_fn = format_ident!("{}", FRAGMENT_FN);
+        if self.notifies() {
+          message!(self, "Macro output for `{}` is a fragment ({}), so it's wrapped in `{}`", module_name, kind.name(), FRAGMENT_FN);
         }
-      }
-    ).as_bytes())
-      .expect("Writes macro to file as test");
+        (wrapped, quote!{ let _ = #fragment_fn; }, Cow::Owned(format!("{}// fragment: {}\n", notes, kind.name())))
+      },
+      None => (code_block, import, Cow::Borrowed(notes)),
+    };
     
-    if cfg!(feature = "notification") {
-      std::println!("Wrote macro to `{}` ", target_path_str);
+    // Bring in the context the output needs
+    let context = &self.context;
+    let with_context;
+    let code_block = match context {
+      Some(context) => {
+        with_context = quote!{
+          #context
+          #code_block
+        };
+        &with_context
+      },
+      None => code_block,
+    };
+    
+    // Warn early if the output won't compile
+    let code_block = match parse_cache.parse_file(code_block) {
+      Ok(file) if self.infer_imports || self.tidy_imports || self.rustfmt_skip || self.item_visibility != ItemVisibility::Unchanged => {
+        let mut file = file.clone();
+        if let Some(visibility) = self.item_visibility.visibility() {
+          restrict_visibility(&mut file, &visibility);
+        }
+        if self.infer_imports {
+          infer_imports(&mut file);
+        }
+        if self.tidy_imports {
+          tidy_imports(&mut file);
+        }
+        if self.rustfmt_skip {
+          mark_rustfmt_skip(&mut file);
+        }
+        Cow::Owned(file.into_token_stream())
+      },
+      Ok(_) => Cow::Borrowed(code_block),
+      Err(err) => {
+        message!(self, "Macro output for `{}` does not parse as a Rust file: {}", module_name, err);
+        Cow::Borrowed(code_block)
+      },
+    };
+    let code_block = code_block.as_ref();
+    
+    // Files that aren't Rust are written as they're rendered
+    let rendered = match self.output_format {
+      #[cfg(feature = "json")]
+      OutputFormat::Json => Some(json::render_json(module_name, code_block, parse_cache.parse_file(code_block).ok())),
+      OutputFormat::Const => Some(render_const(module_name, &self.render_snapshot(code_block))),
+      _ if self.markdown => Some(render_markdown(
+        module_name,
+        &self.render_snapshot(code_block),
+        &import.to_string().replace(" :: ", "::").replace(" ;", ";"),
+      )),
+      _ => None,
+    };
+    if let Some(contents) = rendered {
+      target_file.write_all(contents.as_bytes())
+        .expect("Writes macro to file as JSON, doctest or const");
+      return WrittenDump {
+        target_path,
+        module_name: module_name.to_string(),
+        write_count,
+        bytes_written: contents.len(),
+        formats: false,
+        formatted: None,
+        previous_contents,
+        unchanged_guard,
+        source: None,
+      };
+    }
+    
+    // Move the impls into a file of their own
+    let split_parts = if self.split_impls && self.upsert_module.is_none() {
+      parse_cache.parse_file(code_block).ok().and_then(split_impls)
+    } else {
+      None
+    };
+    let split;
+    let code_block = match split_parts {
+      Some((definitions, impls)) => {
+        let file_stem = target_path.file_stem().and_then(|stem| stem.to_str()).expect("Must name the file");
+        let impls_path = format!("{}/{}_impls.rs", file_stem, file_stem);
+        self.write_impls(&target_path.with_file_name(&impls_path), module_name, &impls);
+        split = quote!{
+          #definitions
+          #[path = #impls_path]
+          mod impls;
+        };
+        &split
+      },
+      None => code_block,
+    };
+    
+    // Cut enormous output down to size
+    let mut truncated = None;
+    if let Some(max_bytes) = self.max_bytes.filter(|max_bytes| code_block.to_string().len() > *max_bytes) {
+      truncated = Some(match parse_cache.parse_file(code_block) {
+        Ok(file) => {
+          let (kept, omitted) = truncate_items(&file.items, max_bytes);
+          (kept.to_string(), format!("// ... truncated by procout ({} items omitted)", omitted))
+        },
+        Err(_) => {
+          let mut code_block_source = code_block.to_string();
+          let omitted = truncate_raw(&mut code_block_source, max_bytes);
+          (code_block_source, format!("// ... truncated by procout ({} bytes omitted)", omitted))
+        },
+      });
+    }
+    
+    // Write to file
+    let test_ident = match test_ident {
+      Some(test_ident) => test_ident,
+      None if self.upsert_module.is_some() => module_test_ident(module_ident),
+      None => format_ident!("macro_test"),
+    };
+    let test_ident = match parse_cache.parse_file(code_block) {
+      Ok(file) => {
+        let unique_ident = unique_test_ident(&file.items, &test_ident);
+        if unique_ident != test_ident {
+          message!(self, 
+            "Macro output for `{}` already defines `{}`, so the generated test is named `{}`",
+            module_name, test_ident, unique_ident
+          );
+        }
+        unique_ident
+      },
+      Err(_) => test_ident,
+    };
+    let (no_std, no_std_prelude) = if self.no_std {
+      let extern_alloc = if self.no_std_alloc { quote!{ extern crate alloc; } } else { quote!{} };
+      (
+        quote!{ #![no_std] },
+        quote!{
+          #extern_alloc
+          #[cfg(test)]
+          extern crate std;
+        },
+      )
+    } else {
+      (quote!{}, quote!{})
+    };
+    let test_ident_source = test_ident.to_string();
+    let import_source = import.to_string();
+    let test_body_source = self.test_body.as_ref().map(ToString::to_string).unwrap_or_default();
+    let feature_gate = match &self.feature_gate {
+      Some(feature) => quote!{ #![cfg(feature = #feature)] },
+      None => quote!{},
+    };
+    let attributes_source = quote!{ #feature_gate #no_std }.to_string();
+    let no_std_prelude_source = no_std_prelude.to_string();
+    let template_value = |placeholder: &str| match placeholder {
+      "attributes" => Some(attributes_source.clone()),
+      "prelude" => Some(no_std_prelude_source.clone()),
+      "test_ident" => Some(test_ident_source.clone()),
+      "import" => Some(import_source.clone()),
+      "test_body" => Some(test_body_source.clone()),
+      _ => None,
+    };
+    let commit_header = match (self.git_commit, git_commit()) {
+      (true, Some(commit)) => format!("{}{}\n", COMMIT_HEADER_PREFIX, commit),
+      _ => String::new(),
+    };
+    let header = format!(
+      "{}{}{}{}{}",
+      render_header(module_name),
+      commit_header,
+      project_header().unwrap_or_default(),
+      notes,
+      self.record_input.as_ref().map(render_input_comment).unwrap_or_default(),
+    );
+    let skips_format = self.formats() && (too_deep || self.skips_format(code_block));
+    let (formats, bytes_written) = if truncated.is_none() && self.upsert_module.is_none() && (skips_format || !self.formats_in_memory()) {
+      // What's written is just the tokens, so stream them to the file rather than building the whole file
+      // as a string first, or lexing it back into tokens
+      let mut writer = BufWriter::new(target_file);
+      writer.write_all(header.as_bytes())
+        .and_then(|_| stream_template(&mut writer, &self.template(), template_value, code_block))
+        .and_then(|_| writer.flush())
+        .expect("Writes macro to file as test");
+      (self.formats() && !skips_format, fs::metadata(&target_path).map(|metadata| metadata.len() as usize).unwrap_or_default())
+    } else {
+      let (code_block_source, truncation_marker) = match truncated {
+        Some((code_block_source, truncation_marker)) => (code_block_source, Some(truncation_marker)),
+        None => (code_block.to_string(), None),
+      };
+      let generated_source = render_template(&self.template(), |placeholder| match placeholder {
+        "code_block" => Some(code_block_source.clone()),
+        placeholder => template_value(placeholder),
+      });
+      // Only raw truncation leaves source that isn't tokens, and there's no formatting that
+      let generated = generated_source.parse::<TokenStream>().ok();
+      let mut formats = self.formats() && !skips_format && generated.is_some();
+      let mut contents = match (&self.upsert_module, &previous_contents) {
+        (Some(_), Some(existing)) => {
+          let mut new_items = format!("{}\n{}", code_block_source, self.render_test(&test_ident, &import));
+          // Format just what's new so the rest of a large file isn't formatted all over again, 
+          // falling back to formatting the whole file
+          if formats && truncation_marker.is_none() {
+            match self.format_source(&new_items) {
+              Ok(formatted_items) => {
+                new_items = formatted_items.trim_end().to_string();
+                formats = false;
+              },
+              Err(err) => message!(self, "{}", paint(Tone::Error, &format!("Could not format the new items on their own, formatting the whole file: {}", err))),
+            }
+          }
+          upsert_items(existing, module_ident, &test_ident, &new_items)
+            .unwrap_or_else(|err| panic!("Existing file `{}` must parse to upsert into it: {}", target_path_str, err))
+        },
+        _ => format!(
+          "{}{}",
+          header,
+          match &generated {
+            Some(generated) if !skips_format => self.render_source(generated, &mut parse_cache),
+            _ => generated_source,
+          },
+        ),
+      };
+      if let Some(truncation_marker) = truncation_marker {
+        if !contents.ends_with('\n') {
+          contents.push('\n');
+        }
+        contents.push_str(&truncation_marker);
+        contents.push('\n');
+      }
+      target_file.write_all(contents.as_bytes())
+        .expect("Writes macro to file as test");
+      (formats, contents.len())
+    };
+    
+    #[cfg(feature = "span_map")]
+    let input_items = if (self.span_map || self.line_hints) && self.upsert_module.is_none() {
+      parse_cache.parse_file(code_block).ok().map(|file| file.items.clone())
+    } else {
+      None
+    };
+    #[cfg(feature = "span_map")]
+    let prelude_items = match syn::parse_file(&no_std_prelude.to_string()) {
+      Ok(prelude) => prelude.items.len(),
+      Err(_) => 0,
+    };
+    let parsed = parse_cache.parse_file(code_block).is_ok();
+    WrittenDump {
+      target_path,
+      module_name: module_name.to_string(),
+      write_count,
+      bytes_written,
+      formats,
+      formatted: None,
+      previous_contents,
+      unchanged_guard,
+      source: Some(WrittenSource {
+        test_ident,
+        too_deep,
+        parsed,
+        #[cfg(feature = "span_map")]
+        input_items,
+        #[cfg(feature = "span_map")]
+        prelude_items,
+      }),
+    }
+  }
+  
+  /// Take a [WrittenDump] through the steps that follow writing it, from formatting to the sidecars, 
+  /// returning where it ended up
+  /// - The steps run in the same order whatever the output format, and only the ones needing the code are 
+  ///   left out for a file that doesn't hold Rust.
+  fn finish_dump(&self, dump: WrittenDump) -> PathBuf {
+    let WrittenDump { target_path, module_name, write_count, bytes_written, formats, formatted, previous_contents, unchanged_guard, source } = dump;
+    let module_name = module_name.as_str();
+    
+    let formatted = self.formats_after(formats).then(|| self.format_output(&target_path)).or(formatted);
+    
+    // Keep the bytes identical across platforms whatever rustfmt did
+    normalize_output(&target_path, !self.keep_raw_ending);
+    
+    if let Some(source) = source.as_ref().filter(|source| self.anchors && self.upsert_module.is_none() && !source.too_deep) {
+      let current_contents = fs::read_to_string(&target_path).expect("Reads macro output file");
+      if let Some(anchored) = insert_anchors(&current_contents, &source.test_ident) {
+        fs::write(&target_path, anchored).expect("Writes anchored macro output file");
+      }
+    }
+    
+    // Pair the items of the code with where they ended up in the file as it is now
+    #[cfg(feature = "span_map")]
+    let map_current = |current_contents: &str| -> Option<Vec<SpanMapping>> {
+      let source = source.as_ref()?;
+      let output = syn::parse_file(current_contents).ok()?;
+      let output_lines = item_lines(current_contents, &output.items)?;
+      Some(map_items(source.input_items.as_deref()?, output_lines.get(source.prelude_items..).unwrap_or_default()))
+    };
+    
+    #[cfg(feature = "span_map")]
+    if self.line_hints && self.upsert_module.is_none() {
+      let current_contents = fs::read_to_string(&target_path).expect("Reads macro output file");
+      if let Some(mappings) = map_current(&current_contents) {
+        fs::write(&target_path, insert_line_hints(&current_contents, &mappings)).expect("Writes hinted macro output file");
+      }
+    }
+    
+    // The contents are final, so it can be named by them
+    #[cfg(feature = "content_hash")]
+    let target_path = self.rename_by_hash(target_path);
+    let target_path_str = target_path.to_str().expect("Must create string from target path");
+    record_written(&target_path);
+    count_write(module_name);
+    
+    if self.notifies() {
+      message!(self, "{}", paint(Tone::Success, &render_notification(
+        self.notification_template.as_deref().unwrap_or(DEFAULT_NOTIFICATION_TEMPLATE),
+        &render_link(self.notification_link_style, &target_path),
+        module_name,
+        bytes_written,
+        write_count,
+      )));
+    }
+    
+    if self.annotated && self.upsert_module.is_none() && source.is_some() {
+      let current_contents = fs::read_to_string(&target_path).expect("Reads macro output file");
+      let file_stem = target_path.file_stem().and_then(OsStr::to_str).expect("Must name the file");
+      let annotated_path = target_path.with_file_name(file_stem).join(format!("{}.annotated.rs", file_stem));
+      DirBuilder::new().recursive(true).create(annotated_path.parent().expect("Must have an annotated dir"))
+        .expect("Creates macro output annotated dir");
+      fs::write(&annotated_path, render_annotated(&current_contents)).expect("Writes annotated macro output file");
+    }
+    
+    if self.diff_file {
+      let current_contents = fs::read_to_string(&target_path).expect("Reads macro output file");
+      update_diff_file(&target_path, previous_contents.as_deref(), &current_contents)
+        .expect("Updates macro output diff file");
+    }
+    
+    #[cfg(feature = "span_map")]
+    if self.span_map && self.upsert_module.is_none() {
+      let current_contents = fs::read_to_string(&target_path).expect("Reads macro output file");
+      if let Some(mappings) = map_current(&current_contents) {
+        let file_name = target_path.file_name().and_then(|name| name.to_str()).expect("Must name the file");
+        fs::write(target_path.with_extension("map"), render_span_map(file_name, &mappings))
+          .expect("Writes macro output span map");
+      }
+    }
+    
+    if let Some(mode) = self.mode {
+      set_mode(&target_path, mode);
+    }
+    
+    // Check the file and keep the files about it, which only a Rust file has
+    if let Some(source) = &source {
+      if let Some(toolchain) = &self.verify_toolchain {
+        match verify_with_toolchain(toolchain, &target_path) {
+          Ok(output) if output.status.success() && !self.notifies() => {},
+          Ok(output) => message!(self, 
+            "rustc ({}) status for `{}`: {}\n{}",
+            toolchain,
+            target_path_str,
+            output.status,
+            String::from_utf8_lossy(&output.stderr),
+          ),
+          Err(err) => message!(self, "{}", paint(Tone::Error, &format!("Could not verify \"{}\" with toolchain `{}`:\n {:#?}", target_path_str, toolchain, err))),
+        }
+      }
+      
+      if let Some(deps) = &self.cargo_toml {
+        let package_name = target_path.file_stem().and_then(|stem| stem.to_str()).expect("Must name the package");
+        let file_name = target_path.file_name().and_then(|name| name.to_str()).expect("Must name the file");
+        let package_dir = target_path.with_file_name(package_name);
+        DirBuilder::new().recursive(true).create(&package_dir).expect("Creates macro output manifest dir");
+        fs::write(package_dir.join("Cargo.toml"), render_manifest(package_name, &format!("../{}", file_name), deps))
+          .expect("Writes macro output manifest");
+      }
+      
+      if self.include_stubs {
+        let output_dir = target_path.parent().expect("Must have an output dir");
+        let dumps = list_dumps(output_dir).expect("Lists macro output dir");
+        let include_dir = output_dir.join(INCLUDE_DIR);
+        DirBuilder::new().recursive(true).create(&include_dir).expect("Creates macro output include dir");
+        fs::write(include_dir.join(INCLUDE_FILE), render_include_file(&dumps)).expect("Writes macro output include file");
+      }
+      
+      if self.html_index {
+        record_status(&target_path, source.parsed, formatted);
+        let output_dir = target_path.parent().expect("Must have an output dir");
+        let dumps = list_dumps(output_dir).expect("Lists macro output dir");
+        fs::write(output_dir.join(INDEX_FILE), render_index(&dumps)).expect("Writes macro output index file");
+      }
+      
+      if self.report {
+        record_report(ReportEntry {
+          module_name: module_name.to_string(),
+          path: target_path.clone(),
+          lines: fs::read_to_string(&target_path).map(|contents| contents.lines().count()).unwrap_or_default(),
+          parsed: source.parsed,
+          formatted,
+        });
+      }
+    }
+    
+    if self.durable {
+      sync_output(&target_path);
+    }
+    
+    // Only now is the file as it's going to stay
+    drop(unchanged_guard);
+    target_path
+  }
+  
+  /// Whether a file written by [ProcoutConfig::start_dump] that `formats` is formatted with the 
+  /// [FormatCommand] once it's written, rather than by the [Formatter] as it's written
+  fn formats_after(&self, formats: bool) -> bool {
+    formats && self.formatter.is_none()
+  }
+  
+  /// Whether files are named by [ProcoutConfig::content_hash], which an [ProcoutConfig::output_file] or an 
+  /// [ProcoutConfig::upsert_module] names instead
+  #[cfg(feature = "content_hash")]
+  fn names_by_hash(&self) -> bool {
+    self.content_hash 
+      && self.output_file.as_deref().and_then(Path::file_name).is_none() 
+      && self.upsert_module.is_none()
+  }
+  
+  /// Rename the file written at `staged_path` by a hash of its contents, see [ProcoutConfig::content_hash], 
+  /// returning where it ended up
+  /// - Returns `staged_path` as it is if files aren't named by hash.
+  #[cfg(feature = "content_hash")]
+  fn rename_by_hash(&self, staged_path: PathBuf) -> PathBuf {
+    if !self.names_by_hash() {
+      return staged_path;
+    }
+    let contents = fs::read(&staged_path).expect("Reads macro output file");
+    let hashed_path = staged_path.with_file_name(format!("{}.{}", hash::content_hash_stem(&contents), self.extension()));
+    match self.overwrite_policy {
+      OverwritePolicy::Never if hashed_path.exists() => {
+        let _ = fs::remove_file(&staged_path);
+        panic!("Macro output file `{}` already exists, and the overwrite policy is `Never`", hashed_path.display());
+      },
+      // It holds just what was written, so leaving it be keeps its modification time
+      OverwritePolicy::Newer if hashed_path.exists() => fs::remove_file(&staged_path)
+        .expect("Removes staged macro output file"),
+      _ => fs::rename(&staged_path, &hashed_path).expect("Renames macro output file by its hash"),
+    }
+    hashed_path
+  }
+  
+  /// Write the impls split out of the file for `module_name`, formatted in memory
+  fn write_impls(&self, impls_path: &Path, module_name: &str, impls: &TokenStream) {
+    let source = impls.to_string();
+    let source = if self.formats() {
+      self.format_source(&source).unwrap_or_else(|err| {
+        message!(self, "{}", paint(Tone::Error, &format!("Could not format the macro output impls: {}", err)));
+        source
+      })
+    } else {
+      source
+    };
+    if let Some(impls_dir) = impls_path.parent() {
+      DirBuilder::new().recursive(true).create(impls_dir).expect("Creates macro output impls dir");
+    }
+    fs::write(impls_path, format!("{}{}", render_header(&format!("{} impls", module_name)), source))
+      .expect("Writes macro output impls file");
+    record_written(impls_path);
+  }
+  
+  /// Render the test that starts with the `import` checking the output, as the [TestMode] has it
+  fn render_test(&self, test_ident: &Ident, import: &TokenStream) -> TokenStream {
+    let test_body = &self.test_body;
+    let (test_attribute, is_async) = match self.test_mode {
+      TestMode::Test => self.test_declaration(),
+      TestMode::CompileOnly => (quote!{ #[allow(dead_code)] }, false),
+      TestMode::None => return quote!{},
+    };
+    let asyncness = if is_async { quote!{ async } } else { quote!{} };
+    let test = quote!{
+      #test_attribute
+      #asyncness fn #test_ident() {
+        #import
+        #test_body
+      }
+    };
+    if self.call_site_spans { respan(test, Span::call_site()) } else { test }
+  }
+  
+  /// The [DEFAULT_TEMPLATE] with its test as the [TestMode] has it
+  fn template(&self) -> Cow<'static, str> {
+    match self.test_mode {
+      TestMode::Test if self.test_harness.is_none() && self.test_attr.is_none() => Cow::Borrowed(DEFAULT_TEMPLATE),
+      TestMode::Test => {
+        let (test_attribute, is_async) = self.test_declaration();
+        let declaration = format!("{}\n{}fn ", test_attribute, if is_async { "async " } else { "" });
+        Cow::Owned(DEFAULT_TEMPLATE.replacen("#[test]\nfn ", &declaration, 1))
+      },
+      TestMode::CompileOnly => Cow::Owned(DEFAULT_TEMPLATE.replacen("#[test]", "#[allow(dead_code)]", 1)),
+      TestMode::None => Cow::Borrowed(DEFAULT_TEMPLATE.split("#[test]").next().unwrap_or(DEFAULT_TEMPLATE)),
+    }
+  }
+  
+  /// The attributes on the generated test and whether it's `async`, from the [TestHarness] or else the 
+  /// [ProcoutConfig::test_attr]
+  fn test_declaration(&self) -> (TokenStream, bool) {
+    match (&self.test_harness, &self.test_attr) {
+      (Some(Custom(test_harness)), _) => (test_harness.attributes(), test_harness.is_async()),
+      (None, Some(test_attr)) => (test_attr.clone(), false),
+      (None, None) => (StdTest.attributes(), false),
+    }
+  }
+  
+  /// Whether the created file gets formatted, see [ProcoutConfig::format]. Messy output is left exactly as the 
+  /// tokens printed.
+  fn formats(&self) -> bool {
+    self.format.unwrap_or(cfg!(feature = "formatted") && !cfg!(feature = "procout_messy"))
+  }
+  
+  /// Whether messages are printed, see [ProcoutConfig::notify]
+  fn notifies(&self) -> bool {
+    self.notify.unwrap_or(cfg!(feature = "notification"))
+  }
+  
+  /// The extension of the written file
+  fn extension(&self) -> &'static str {
+    match self.output_format {
+      #[cfg(feature = "json")]
+      OutputFormat::Json => "json",
+      OutputFormat::Const => "rs",
+      OutputFormat::Rust if self.markdown => "md",
+      OutputFormat::Rust => "rs",
+    }
+  }
+  
+  /// Whether the groups of `code_block` nest deeper than the [ProcoutConfig::max_depth], telling so when they do
+  fn too_deep(&self, code_block: &TokenStream) -> bool {
+    let max_depth = self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    let depth = nesting_depth(code_block);
+    if depth > max_depth {
+      message!(self, "{}", paint(Tone::Skipped, &format!(
+        "Macro output nests {} groups deep, over the max depth of {}, so it's written raw without parsing or formatting", depth, max_depth,
+      )));
+    }
+    depth > max_depth
+  }
+  
+  /// A [ParseCache] for one call, which won't parse output nested deeper than the [ProcoutConfig::max_depth]
+  fn parse_cache(&self) -> ParseCache {
+    ParseCache::new(Some(self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)))
+  }
+  
+  /// Whether `code_block` is dumped under [ProcoutConfig::dump_when], telling why not when it isn't
+  fn dumps(&self, code_block: &TokenStream, module_name: &str) -> bool {
+    let broken = match self.dump_when {
+      DumpWhen::Always => return true,
+      DumpWhen::ParseFails => self.parse_cache().parse_file(code_block).is_err(),
+      DumpWhen::CheckFails => self.parse_cache().parse_file(code_block).is_err() 
+        || !check_compiles(&code_block.to_string(), module_name).unwrap_or_else(|err| {
+          message!(self, "{}", paint(Tone::Error, &format!("Could not run rustc to check the macro output, so it's dumped: {}", err)));
+          false
+        }),
+    };
+    if !broken && self.notifies() {
+      message!(self, "{}", paint(Tone::Skipped, &format!("Macro output for `{}` isn't broken, so it's not dumped", module_name)));
+    }
+    broken
+  }
+  
+  /// Whether `code_block` is too big to be worth formatting, see [ProcoutConfig::skip_format_over_bytes]
+  fn skips_format(&self, code_block: &TokenStream) -> bool {
+    match self.skip_format_over_bytes {
+      Some(threshold) => {
+        let bytes = code_block.to_string().len();
+        if bytes > threshold && self.notifies() {
+          message!(self, "{}", paint(Tone::Skipped, &format!("Formatting skipped: {} bytes of source is over the {} byte threshold", bytes, threshold)));
+        }
+        bytes > threshold
+      },
+      None => false,
+    }
+  }
+  
+  /// Render `code_block` formatted as it's written, without writing a file, e.g. for snapshot testing
+  /// - It's formatted by the [Formatter] or [FormatCommand::Prettyplease] when set, and otherwise by piping it 
+  ///   through `rustfmt`, whichever [FormatCommand] is chosen. Without the `formatted` feature it's the raw tokens.
+  /// - Nothing is added, not even the header or the test, so the snapshot is just the expansion.
+  pub fn render_snapshot(&self, code_block: &TokenStream) -> String {
+    let source = code_block.to_string();
+    if !self.formats() || self.too_deep(code_block) {
+      return source;
+    }
+    self.format_source(&source).unwrap_or_else(|err| {
+      message!(self, "{}", paint(Tone::Error, &format!("Could not format the macro output: {}", err)));
+      source
+    })
+  }
+  
+  /// Copy `code_block`, formatted as by [ProcoutConfig::render_snapshot], to the system clipboard instead of 
+  /// writing a file, e.g. to paste into a bug report
+  /// - The clipboard is reached with `pbcopy` on macOS, `clip` on Windows, and `wl-copy`, `xclip` or `xsel` elsewhere.
+  /// - Like writing, it's a no-op unless the `procout` feature is enabled, and it's reported as a notification.
+  /// - Returns whether it was copied.
+  #[cfg(feature = "clipboard")]
+  pub fn procout_to_clipboard(&self, code_block: &TokenStream, module_ident: Option<Ident>) -> bool {
+    if !writes() {
+      return false;
+    }
+    let module_ident = module_ident.unwrap_or_else(timestamp_ident);
+    let source = self.render_snapshot(code_block);
+    match clipboard::copy_with(clipboard::CLIPBOARD_COMMANDS, &source) {
+      Ok(program) => {
+        if self.notifies() {
+          message!(self, "Copied macro output for `{}` ({} bytes) to the clipboard with `{}`", module_ident, source.len(), program);
+        }
+        true
+      },
+      Err(err) => {
+        message!(self, "{}", paint(Tone::Error, &format!("Could not copy macro output for `{}` to the clipboard:\n {:#?}", module_ident, err)));
+        false
+      },
+    }
+  }
+  
+  /// Check `code_block`, formatted as by [ProcoutConfig::render_snapshot], against an inline `expect-test` 
+  /// expectation, so the expansion is reviewed right in the test
+  /// - Run with `UPDATE_EXPECT=1` to rewrite the expectation in place with the current expansion.
+  /// - This is meant for your own tests, so it runs whether or not the `procout` feature is enabled.
+  ///
+  /// ```ignore
+  /// ProcoutConfig::new().assert_expect(&my_macro_impl(input), &expect_test::expect![[r#"
+  ///     pub mod my_module {}
+  /// "#]]);
+  /// ```
+  #[cfg(feature = "expect-test")]
+  pub fn assert_expect(&self, code_block: &TokenStream, expect: &expect_test::Expect) {
+    expect.assert_eq(&self.render_snapshot(code_block));
+  }
+  
+  /// Format `source` in memory with the [Formatter] or [FormatCommand::Prettyplease] when set, and otherwise 
+  /// by piping it through `rustfmt`
+  fn format_source(&self, source: &str) -> Result<String, FormatError> {
+    if let Some(Custom(formatter)) = &self.formatter {
+      return formatter.format(source);
+    }
+    #[cfg(feature = "prettyplease")]
+    {
+      if self.format_command == FormatCommand::Prettyplease {
+        return syn::parse_file(source).map(|file| prettyplease::unparse(&file)).map_err(FormatError::Parse);
+      }
+    }
+    RustfmtFormatter.format(source)
+  }
+  
+  /// Whether [ProcoutConfig::render_source] formats, rather than leaving it to the [FormatCommand] afterwards
+  fn formats_in_memory(&self) -> bool {
+    #[cfg(feature = "prettyplease")]
+    let prettyplease = self.format_command == FormatCommand::Prettyplease;
+    #[cfg(not(feature = "prettyplease"))]
+    let prettyplease = false;
+    self.formats() && (self.formatter.is_some() || prettyplease)
+  }
+  
+  /// Render the generated tokens as source, formatting them in memory with the [Formatter], or if the 
+  /// [FormatCommand] allows it
+  fn render_source(&self, generated: &TokenStream, parse_cache: &mut ParseCache) -> String {
+    if let (true, Some(Custom(formatter))) = (self.formats(), &self.formatter) {
+      let source = generated.to_string();
+      return formatter.format(&source).unwrap_or_else(|err| {
+        message!(self, "{}", paint(Tone::Error, &format!("Could not format the macro output: {}", err)));
+        source
+      });
+    }
+    #[cfg(feature = "prettyplease")]
+    {
+      if self.formats() && self.format_command == FormatCommand::Prettyplease {
+        match parse_cache.parse_file(generated) {
+          Ok(file) => return prettyplease::unparse(file),
+          Err(err) => message!(self, "{}", paint(Tone::Error, &format!("Could not prettyplease the macro output:\n {}", err))),
+        }
+      }
+    }
+    let _ = parse_cache;
+    generated.to_string()
+  }
+  
+  /// Try to format the output with the chosen [FormatCommand], ignoring failure but returning whether it worked
+  fn format_output(&self, target_path: &Path) -> bool {
+    #[cfg(feature = "prettyplease")]
+    {
+      if self.format_command == FormatCommand::Prettyplease {
+        return true;
+      }
+    }
+    match self.format_file(target_path) {
+      Ok(()) => {
+        if self.notifies() {
+          message!(self, "{}", paint(Tone::Success, "rustfmt status: ok"));
+        }
+        true
+      },
+      Err(err) => {
+        message!(self, "{}", paint(Tone::Skipped, &format!("Formatting skipped, \"{}\" is left unformatted: {}", target_path.display(), err)));
+        false
+      },
+    }
+  }
+  
+  /// Format the Rust file at `path` in place, just as procout formats the files it writes
+  /// - It's formatted by the [Formatter] or [FormatCommand::Prettyplease] when set, and otherwise with the 
+  ///   [FormatCommand], [ProcoutConfig::project_rustfmt_config] and [ProcoutConfig::rustfmt_retries].
+  /// - A copy is formatted and renamed over the original only once formatting succeeds, so a crashing 
+  ///   formatter never leaves a half-formatted file behind. The copy gets the original's permissions first.
+  /// - Formatting that leaves a file which parsed no longer parsing, or with its items changed or reordered, is 
+  ///   thrown away with [FormatError::Mangled], so the file stays as it was.
+  /// - This is meant for your own tools, e.g. a build script's generated files, so it runs whether or not 
+  ///   the `procout` or `formatted` features are enabled.
+  pub fn format_file(&self, path: &Path) -> Result<(), FormatError> {
+    self.format_files(&[path])
+  }
+  
+  /// Format the Rust files at `paths` in place as by [ProcoutConfig::format_file], but with a single run of 
+  /// `rustfmt` or `cargo fmt` for all of them, saving a process per file
+  /// - It's all or nothing, so if any can't be formatted, none are.
+  /// - A [Formatter] or [FormatCommand::Prettyplease] formats each in memory, as usual.
+  pub fn format_files(&self, paths: &[&Path]) -> Result<(), FormatError> {
+    let original_paths: Vec<PathBuf> = paths.iter().map(|path| formatter_path(path)).collect();
+    let unformatted = original_paths.iter().map(fs::read_to_string).collect::<Result<Vec<String>, _>>()?;
+    if self.formats_in_memory() {
+      for (original_path, unformatted) in original_paths.iter().zip(&unformatted) {
+        let formatted = self.format_source(unformatted)?;
+        check_formatted(unformatted, &formatted)?;
+        fs::write(original_path, formatted)?;
+      }
+      return Ok(());
+    }
+    let copy_paths: Vec<PathBuf> = original_paths.iter().map(|path| format_copy_path(path)).collect();
+    let formatted = copy_paths.iter().zip(&unformatted)
+      .try_for_each(|(copy_path, unformatted)| fs::write(copy_path, unformatted))
+      .map_err(FormatError::Io)
+      .and_then(|()| self.format_copies(&copy_paths))
+      .and_then(|()| copy_paths.iter().zip(&unformatted).try_for_each(|(copy_path, unformatted)| {
+        check_formatted(unformatted, &fs::read_to_string(copy_path)?)
+      }))
+      .and_then(|()| copy_paths.iter().zip(&original_paths).try_for_each(|(copy_path, original_path)| {
+        // The copy replaces the original, so it takes the original's permissions along
+        fs::metadata(original_path)
+          .and_then(|metadata| fs::set_permissions(copy_path, metadata.permissions()))
+          .and_then(|()| fs::rename(copy_path, original_path))
+          .map_err(FormatError::Io)
+      }));
+    if formatted.is_err() {
+      for copy_path in &copy_paths {
+        let _ = fs::remove_file(copy_path);
+      }
+    }
+    formatted
+  }
+  
+  /// Format every dump in `dir` again, e.g. to keep committed dumps consistent after upgrading `rustfmt` 
+  /// without rerunning the macros
+  /// - Dumps are found as by [list_dumps], and formatted as by [ProcoutConfig::format_file].
+  /// - A dump that can't be formatted, e.g. because it was truncated, is reported and left as it is.
+  /// - Returns the dumps reformatted, or why `dir` couldn't be read.
+  pub fn reformat_dir(&self, dir: &Path) -> Result<Vec<PathBuf>, FormatError> {
+    let mut reformatted = Vec::new();
+    for dump in list_dumps(dir)? {
+      match self.format_file(&dump.path) {
+        Ok(()) => reformatted.push(dump.path),
+        Err(err) => message!(self, "{}", paint(Tone::Skipped, &format!("Could not reformat `{}`: {}", dump.path.display(), err))),
+      }
+    }
+    Ok(reformatted)
+  }
+  
+  /// Watch the dumps in `dir` and reformat any that's edited, e.g. while tweaking a dump by hand to experiment
+  /// - Dumps are found as by [list_dumps], and formatted as by [ProcoutConfig::format_file]. 
+  /// - This is meant for a dev tool, so it runs whether or not the `procout` feature is enabled.
+  /// - It runs until `dir` can't be read, returning why.
+  #[cfg(feature = "watch")]
+  pub fn watch_and_format(&self, dir: &Path) -> io::Error {
+    let mut dump_times = watch::DumpTimes::default();
+    let mut first_poll = true;
+    loop {
+      match dump_times.poll(dir, first_poll) {
+        Ok(changed) => for path in changed {
+          match self.format_file(&path) {
+            Ok(()) if !self.notifies() => {},
+            Ok(()) => message!(self, "{}", paint(Tone::Success, &format!("Reformatted edited dump `{}`", path.display()))),
+            Err(err) => message!(self, "{}", paint(Tone::Skipped, &format!("Could not reformat edited dump `{}`: {}", path.display(), err))),
+          }
+          dump_times.refresh(&path);
+        },
+        Err(err) => return err,
+      }
+      first_poll = false;
+      thread::sleep(watch::POLL_INTERVAL);
+    }
+  }
+  
+  /// Run the [FormatCommand] once on all the copies made by [ProcoutConfig::format_files]
+  fn format_copies(&self, copy_paths: &[PathBuf]) -> Result<(), FormatError> {
+    let first_path = copy_paths.first().map_or_else(|| Path::new(""), PathBuf::as_path);
+    let mut command = match self.format_command {
+      FormatCommand::CargoFmt => {
+        let manifest_path = find_manifest(first_path).ok_or_else(|| io::Error::new(
+          io::ErrorKind::NotFound,
+          "it is not inside a cargo project, so it can't be formatted with cargo fmt",
+        ))?;
+        let mut command = Command::new("cargo");
+        command.arg("fmt").arg("--manifest-path").arg(manifest_path).arg("--").args(&self.rustfmt_args).args(copy_paths);
+        command
+      },
+      _ => {
+        let mut command = Command::new("rustfmt");
+        // `async fn` isn't in rustfmt's default 2015 edition
+        let is_async = self.test_mode == TestMode::Test && self.test_declaration().1;
+        if is_async && !self.rustfmt_args.iter().any(|arg| arg.starts_with("--edition")) {
+          command.arg("--edition").arg("2018");
+        }
+        command.args(&self.rustfmt_args).args(copy_paths);
+        command
+      },
+    };
+    if self.project_rustfmt_config {
+      match find_rustfmt_config(env::var_os("CARGO_MANIFEST_DIR").as_deref()) {
+        Some(config_path) => {
+          command.arg("--config-path").arg(config_path);
+        },
+        None if !self.notifies() => {},
+        None => message!(self, "No rustfmt config in the project root, so rustfmt looks for one from \"{}\"", first_path.display()),
+      }
+    }
+    let (output, retried) = match self.rustfmt_retries {
+      Some((retries, delay)) => output_with_retries(&mut command, retries, delay),
+      None => (command.output(), 0),
+    };
+    if retried > 0 && self.notifies() {
+      message!(self, "rustfmt ran after {} retries", retried);
+    }
+    let output = output?;
+    if output.status.success() {
+      Ok(())
+    } else {
+      Err(FormatError::Failed {
+        status: output.status,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+      })
+    }
+  }
+}
+
+/// Format the Rust file at `path` in place with `rustfmt`, just as procout formats the files it writes
+/// 
+/// See [ProcoutConfig::format_file].
+pub fn format_file(path: &Path) -> Result<(), FormatError> {
+  ProcoutConfig::default().format_file(path)
+}
+
+/// Format every dump in `dir` again with `rustfmt`
+/// 
+/// See [ProcoutConfig::reformat_dir].
+pub fn reformat_dir(dir: &Path) -> Result<Vec<PathBuf>, FormatError> {
+  ProcoutConfig::default().reformat_dir(dir)
+}
+
+/// Watch the dumps in `dir` and reformat any that's edited with `rustfmt`
+/// 
+/// See [ProcoutConfig::watch_and_format].
+#[cfg(feature = "watch")]
+pub fn watch_and_format(dir: &Path) -> io::Error {
+  ProcoutConfig::default().watch_and_format(dir)
+}
+
+/// Run `command`, retrying up to `retries` times when it can't be spawned for any reason but not being installed
+/// - Returns the last result and the number of retries made.
+fn output_with_retries(command: &mut Command, retries: u32, delay: RetryDelay) -> (io::Result<Output>, u32) {
+  let mut retried = 0;
+  loop {
+    match command.output() {
+      Err(err) if err.kind() != io::ErrorKind::NotFound && retried < retries => {
+        thread::sleep(delay.before_retry(retried));
+        retried += 1;
+      },
+      output => return (output, retried),
+    }
+  }
+}
+
+/// The env var that can switch writing off for a run, see [writes]
+#[cfg(any(feature = "procout", feature = "procout_messy", test))]
+static ENABLE_VAR: &str = "PROCOUT";
+
+/// Whether files are written at all
+/// - The `procout` or `procout_messy` feature must be enabled at compile time.
+/// - If the `PROCOUT` env var is set, it must also be truthy at run time.
+#[cfg(any(feature = "procout", feature = "procout_messy", test))]
+fn writes() -> bool {
+  env_enables(env::var_os(ENABLE_VAR).as_deref())
+}
+
+/// Files are never written without the `procout` or `procout_messy` feature
+/// - This is a constant the optimizer sees through once it's inlined, so every call guarded by it compiles to 
+///   nothing. In a release build of a crate calling [procout()] without the features, nothing of procout's 
+///   writing is left in the binary.
+#[cfg(not(any(feature = "procout", feature = "procout_messy", test)))]
+#[inline(always)]
+fn writes() -> bool {
+  false
+}
+
+/// Whether the `PROCOUT` env var allows writing, which it does when it's unset
+/// - `0`, `false`, `no`, `off` and the empty string, in any case, are falsy. Anything else is truthy.
+#[cfg(any(feature = "procout", feature = "procout_messy", test))]
+fn env_enables(value: Option<&OsStr>) -> bool {
+  match value.and_then(OsStr::to_str) {
+    Some(value) => !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "off"),
+    None => true,
+  }
+}
+
+/// Create the output dir as far as the [CreatePolicy] allows, returning whether it was newly created
+fn create_output_dir(create_dirs: CreatePolicy, output_dir: &Path) -> bool {
+  if output_dir.is_dir() {
+    return false;
+  }
+  match create_dirs {
+    CreatePolicy::Recursive => DirBuilder::new()
+      .recursive(true)
+      .create(output_dir)
+      .expect("Creates macro output dir"),
+    CreatePolicy::ParentMustExist => {
+      let parent_exists = output_dir.parent().is_none_or(|parent| parent.as_os_str().is_empty() || parent.is_dir());
+      if !parent_exists {
+        panic!("Parent of macro output dir `{}` must exist", output_dir.display());
+      }
+      DirBuilder::new()
+        .create(output_dir)
+        .expect("Creates macro output dir");
+    },
+    CreatePolicy::MustExist => panic!("Macro output dir `{}` must exist", output_dir.display()),
+  }
+  true
+}
+
+/// The name of the test checking `module_ident` in a file it shares with other modules
+fn module_test_ident(module_ident: &Ident) -> Ident {
+  format_ident!("macro_test_{}", to_snake_case(&module_ident.to_string()))
+}
+
+/// Name the generated test so it doesn't collide with an item at the root of the output, where it's written
+/// - `_generated` is added until the name is free.
+fn unique_test_ident(items: &[Item], test_ident: &Ident) -> Ident {
+  let mut unique_ident = test_ident.clone();
+  while items.iter().any(|item| item_ident(item) == Some(&unique_ident)) {
+    unique_ident = format_ident!("{}_generated", unique_ident);
+  }
+  unique_ident
+}
+
+/// The default module ident, named for the time it's written
+fn timestamp_ident() -> Ident {
+  let now: DateTime<Utc> = Utc::now();
+  let timestamp: String = format!("{}", now.format(TIMESTAMP_FORMAT));
+  Ident::new(&timestamp, Span::mixed_site())
+}
+
+/// Fill in the `{path}`, `{module}`, `{bytes}` and `{count}` placeholders of a notification template
+fn render_notification(template: &str, path: &str, module: &str, bytes: usize, count: usize) -> String {
+  render_template(template, |placeholder| match placeholder {
+    "path" => Some(path.to_string()),
+    "module" => Some(module.to_string()),
+    "bytes" => Some(bytes.to_string()),
+    "count" => Some(count.to_string()),
+    _ => None,
+  })
+}
+
+/// Print `path` in the [LinkStyle]
+fn render_link(link_style: LinkStyle, path: &Path) -> String {
+  let prefix = match link_style {
+    LinkStyle::None => return path.display().to_string(),
+    LinkStyle::FileUrl => "file://",
+    LinkStyle::VsCode => "vscode://file",
+  };
+  let absolute_path = if path.is_absolute() {
+    path.to_path_buf()
+  } else {
+    env::current_dir().expect("Must identify current dir").join(path)
+  };
+  let mut url = String::from(prefix);
+  let absolute_path = absolute_path.to_string_lossy().replace('\\', "/");
+  if !absolute_path.starts_with('/') {
+    // A Windows drive like `C:/` 
+    url.push('/');
+  }
+  for byte in absolute_path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+      _ => url.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  url
+}
+
+/// Write `template` like [render_template], streaming `code_block` into its `{code_block}` placeholder 
+/// without building it as one string
+/// - Lines left blank by empty values are dropped, since unlike tokens the formatter keeps them.
+fn stream_template(
+  writer: &mut impl Write,
+  template: &str,
+  value: impl Fn(&str) -> Option<String>,
+  code_block: &TokenStream,
+) -> io::Result<()> {
+  let write_lines = |writer: &mut dyn Write, template: &str| -> io::Result<()> {
+    for line in render_template(template, &value).lines().filter(|line| !line.trim().is_empty()) {
+      writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+  };
+  match template.split_once("{code_block}") {
+    Some((before, after)) => {
+      write_lines(writer, before)?;
+      writeln!(writer, "{}", code_block)?;
+      write_lines(writer, after)
+    },
+    None => write_lines(writer, template),
+  }
+}
+
+/// Replace each `{placeholder}` in `template` with its value in a single pass, so values are never re-scanned
+/// - Placeholders without a value are left as they are.
+fn render_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+  let mut rendered = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(open) = rest.find('{') {
+    rendered.push_str(&rest[..open]);
+    let after_open = &rest[open + 1..];
+    let placeholder = after_open.find('}')
+      .map(|close| &after_open[..close])
+      .filter(|placeholder| !placeholder.is_empty() && placeholder.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    match placeholder.and_then(|placeholder| value(placeholder).map(|value| (placeholder, value))) {
+      Some((placeholder, value)) => {
+        rendered.push_str(&value);
+        rest = &after_open[placeholder.len() + 1..];
+      },
+      None => {
+        rendered.push('{');
+        rest = after_open;
+      },
+    }
+  }
+  rendered.push_str(rest);
+  rendered
+}
+
+/// Flush the file at `target_path` to disk, and on unix its directory too, see [ProcoutConfig::durable]
+fn sync_output(target_path: &Path) {
+  File::open(target_path)
+    .and_then(|file| file.sync_all())
+    .expect("Syncs macro output file");
+  #[cfg(unix)]
+  {
+    if let Some(output_dir) = target_path.parent() {
+      File::open(output_dir)
+        .and_then(|dir| dir.sync_all())
+        .expect("Syncs macro output dir");
+    }
+  }
+}
+
+/// Rewrite the file with LF line endings and no BOM if it has either
+/// - With `one_newline`, also end it with exactly one `\n`, whether or not it was formatted.
+fn normalize_output(target_path: &Path, one_newline: bool) {
+  let contents = fs::read_to_string(target_path).expect("Reads macro output file");
+  let normalized = normalize_line_endings(&contents);
+  let normalized = match (one_newline, normalized) {
+    (true, normalized) if !normalized.ends_with('\n') || normalized.ends_with("\n\n") => {
+      Cow::Owned(format!("{}\n", normalized.trim_end_matches('\n')))
+    },
+    (_, normalized) => normalized,
+  };
+  if let Cow::Owned(normalized) = normalized {
+    fs::write(target_path, normalized).expect("Writes normalized macro output file");
+  }
+}
+
+/// Strip a leading BOM and convert CRLF line endings to LF, borrowing when there's nothing to change
+fn normalize_line_endings(contents: &str) -> Cow<'_, str> {
+  let without_bom = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+  if without_bom.contains("\r\n") {
+    Cow::Owned(without_bom.replace("\r\n", "\n"))
+  } else if without_bom.len() != contents.len() {
+    Cow::Owned(without_bom.to_string())
+  } else {
+    Cow::Borrowed(contents)
+  }
+}
+
+/// Let the owner write to an existing file so it can be overwritten
+#[cfg(unix)]
+fn make_writable(target_path: &Path) {
+  use std::os::unix::fs::PermissionsExt;
+  if let Ok(metadata) = fs::metadata(target_path) {
+    let mut permissions = metadata.permissions();
+    permissions.set_mode(permissions.mode() | 0o200);
+    fs::set_permissions(target_path, permissions).expect("Makes macro output file writable");
+  }
+}
+
+#[cfg(not(unix))]
+fn make_writable(_target_path: &Path) {}
+
+/// Apply Unix permission bits to the created file
+#[cfg(unix)]
+fn set_mode(target_path: &Path, mode: u32) {
+  use std::os::unix::fs::PermissionsExt;
+  fs::set_permissions(target_path, fs::Permissions::from_mode(mode)).expect("Sets macro output file mode");
+}
+
+#[cfg(not(unix))]
+fn set_mode(_target_path: &Path, _mode: u32) {}
+
+/// The env var choosing the output dir for calls that don't pass one, see [Output directory](crate#output-directory)
+static OUT_DIR_VAR: &str = "PROCOUT_OUT_DIR";
+
+/// The output dir registered with [set_default_output_dir]
+static DEFAULT_OUTPUT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Register the output dir for every call in this process that doesn't pass one, so the path needn't be passed 
+/// each time
+/// - An explicit `output_path` and the `PROCOUT_OUT_DIR` env var both take precedence over it, and it takes 
+///   precedence over the `tests` fallback. See [Output directory](crate#output-directory) for the whole chain.
+/// - Registering another dir replaces it from then on.
+pub fn set_default_output_dir(path: impl Into<PathBuf>) {
+  *DEFAULT_OUTPUT_DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(path.into());
+}
+
+/// The output dir for a call that doesn't pass one, from the `PROCOUT_OUT_DIR` env var `out_dir_var` or else 
+/// the `default_dir` registered with [set_default_output_dir], if either is set
+/// - An empty env var counts as unset.
+fn fallback_output_dir(out_dir_var: Option<&OsStr>, default_dir: Option<&Path>) -> Option<PathBuf> {
+  out_dir_var
+    .filter(|out_dir| !out_dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| default_dir.map(Path::to_path_buf))
+}
+
+/// Resolve a relative `output_path` against the crate being built rather than wherever the compiler runs
+/// - `manifest_dir` is `CARGO_MANIFEST_DIR`, which cargo sets during builds and tests.
+/// - Without it, the path is resolved against the current dir.
+/// - Absolute paths are used as they are.
+fn resolve_output_path(output_path: &Path, manifest_dir: Option<&OsStr>) -> PathBuf {
+  if output_path.is_absolute() {
+    return output_path.to_path_buf();
+  }
+  match manifest_dir {
+    Some(manifest_dir) => Path::new(manifest_dir).join(output_path),
+    None => env::current_dir().expect("Must identify current dir").join(output_path),
+  }
+}
+
+/// The dir of the file the compiler reported for a span, `local_file`
+/// - The compiler reports paths relative to its own working dir, `compiler_dir`, rather than the crate's.
+#[cfg(feature = "span_map")]
+fn call_site_dir(local_file: Option<PathBuf>, compiler_dir: Option<&Path>) -> Option<PathBuf> {
+  let local_file = local_file?;
+  let dir = local_file.parent()?;
+  match compiler_dir {
+    Some(compiler_dir) if dir.is_relative() => Some(compiler_dir.join(dir)),
+    _ => Some(dir.to_path_buf()),
+  }
+}
+
+/// Whether `output_dir` is inside the `src/` dir of the crate at `manifest_dir`
+fn under_src(output_dir: &Path, manifest_dir: Option<&OsStr>) -> bool {
+  match manifest_dir {
+    Some(manifest_dir) => normalize_dir(output_dir).starts_with(normalize_dir(&Path::new(manifest_dir).join("src"))),
+    None => false,
+  }
+}
+
+/// Find the `rustfmt` config file in the project root, `manifest_dir` or else the current dir
+fn find_rustfmt_config(manifest_dir: Option<&OsStr>) -> Option<PathBuf> {
+  let project_root = match manifest_dir {
+    Some(manifest_dir) => PathBuf::from(manifest_dir),
+    None => env::current_dir().ok()?,
+  };
+  ["rustfmt.toml", ".rustfmt.toml"].iter()
+    .map(|file_name| project_root.join(file_name))
+    .find(|config_path| config_path.is_file())
+}
+
+/// Resolve the `.` and `..` in a path without touching the file system, so equal directories compare equal
+fn normalize_dir(path: &Path) -> PathBuf {
+  let mut normalized = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {},
+      Component::ParentDir if normalized.file_name().is_some() => {
+        normalized.pop();
+      },
+      component => normalized.push(component),
+    }
+  }
+  normalized
+}
+
+/// The path handed to the formatter
+/// - With the `dunce` feature it's canonicalized without the `\\?\` extended-length prefix Windows 
+///   otherwise adds, which confuses `rustfmt` in deep directory trees.
+fn formatter_path(target_path: &Path) -> PathBuf {
+  #[cfg(feature = "dunce")]
+  {
+    if let Ok(canonical_path) = dunce::canonicalize(target_path) {
+      return canonical_path;
+    }
+  }
+  target_path.to_path_buf()
+}
+
+/// The copy of `target_path` that's formatted in its place, see [ProcoutConfig::format_output]
+/// - It sits next to the original so the formatter finds the same config, and is renamed over it only 
+///   once formatting succeeds, so a crashing formatter never leaves a half-formatted file behind.
+fn format_copy_path(target_path: &Path) -> PathBuf {
+  let file_name = target_path.file_name().and_then(OsStr::to_str).unwrap_or("output.rs");
+  target_path.with_file_name(format!(".procout-format.{}", file_name))
+}
+
+/// Find the nearest `Cargo.toml` in a directory at or above `path`
+fn find_manifest(path: &Path) -> Option<PathBuf> {
+  let absolute_path = env::current_dir().ok()?.join(path);
+  absolute_path.ancestors()
+    .map(|dir| dir.join("Cargo.toml"))
+    .find(|manifest_path| manifest_path.is_file())
+}
+
+/// Handle printing code to a file 
+/// - `code_block` This is the code that should be printed (the [TokenStream] output of the macro being debugged)
+/// - `module_ident` This is the optional name of the module generated by the macro.  
+/// - `output_path` This is the directory to write the file to.
+#[inline]
+pub fn procout(
+  code_block: &TokenStream,
+  module_ident: Option<Ident>,
+  output_path: Option<&str>,
+) {
+  ProcoutConfig::default().procout(code_block, module_ident, output_path)
+}
+
+/// Handle printing code to a file, taking any kind of path
+/// - `output_path` This is the directory to write the file to, as a `&str`, [Path] or [PathBuf].
+///
+/// See [ProcoutConfig::procout_to].
+pub fn procout_to(
+  code_block: &TokenStream,
+  module_ident: Option<Ident>,
+  output_path: Option<impl AsRef<Path>>,
+) {
+  ProcoutConfig::default().procout_to(code_block, module_ident, output_path)
+}
+
+/// Handle printing code to a file, but only when `cond` holds for it
+/// - `cond` This is the predicate, called with the code only when files are written at all.
+///
+/// See [ProcoutConfig::procout_if].
+pub fn procout_if(
+  cond: impl Fn(&TokenStream) -> bool,
+  code_block: &TokenStream,
+  module_ident: Option<Ident>,
+  output_path: Option<&str>,
+) {
+  ProcoutConfig::default().procout_if(cond, code_block, module_ident, output_path)
+}
+
+/// Handle printing code to a file named after `module_name`, which is what [procout!] expands to
+/// - Panics if `module_name` isn't an identifier.
+/// - The ident is only made when files are written, so without the features nothing is done at all.
+#[inline]
+pub fn procout_named(
+  code_block: &TokenStream,
+  module_name: &str,
+  output_path: Option<&str>,
+) {
+  if writes() {
+    procout(code_block, Some(Ident::new(module_name, Span::call_site())), output_path)
+  }
+}
+
+/// Handle printing a derive macro's output to a file, together with the item it derives for 
+/// - `input` This is the item the derive is applied to (the [TokenStream] input of the derive being debugged)
+/// - `output` This is the code that should be printed (the [TokenStream] output of the derive being debugged)
+/// - `output_path` This is the directory to write the file to.
+///
+/// See [ProcoutConfig::procout_derive].
+pub fn procout_derive(
+  input: &TokenStream,
+  output: &TokenStream,
+  output_path: Option<&str>,
+) {
+  ProcoutConfig::default().procout_derive(input, output, output_path)
+}
+
+/// Handle printing an attribute macro's output to a file, with comments recording its input 
+/// - `attr` These are the attribute's arguments (the first [TokenStream] input of the attribute being debugged)
+/// - `item` This is the item the attribute is applied to (the second [TokenStream] input)
+/// - `output` This is the code that should be printed (the [TokenStream] output of the attribute being debugged)
+/// - `output_path` This is the directory to write the file to.
+///
+/// See [ProcoutConfig::procout_attr].
+pub fn procout_attr(
+  attr: &TokenStream,
+  item: &TokenStream,
+  output: &TokenStream,
+  output_path: Option<&str>,
+) {
+  ProcoutConfig::default().procout_attr(attr, item, output, output_path)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  /// The attributes [DEFAULT_TEMPLATE] starts with
+  const ALLOWS: &str = if cfg!(feature = "strict_lints") { "" } else { "#![allow(unused_imports)]\n#![allow(dead_code)]\n" };
+  
+  #[test]
+  fn test_procout() {
+    let target_module = "test_procout_module";
+    let module_ident = Ident::new(target_module, Span::mixed_site());
+    let code_block: proc_macro2::TokenStream = quote!{  
+       pub mod #module_ident {
+         const CUSS: &str = "SPIT";
+       }
+    };
+    
+    procout(&code_block, Some(module_ident), None);
+    procout(&code_block, None, Some("tests/blah"));
+    let target_output = format!( 
+      "// @generated by procout\
+      \n// module: {}\
+      \n{}pub mod {} {{\
+      \n    const CUSS: &str = \"SPIT\";\n\
+      }}\n\
+      #[test]\n\
+      fn macro_test() {{\
+      \n    use {}::*;\n\
+      }}\n",
+      target_module,
+      ALLOWS,
+      target_module,
+      target_module,
+    );
+    let mut target_path: PathBuf = env::current_dir().expect("Must identify current dir");
+    target_path.push("tests");
+    target_path.push(format!("{}.rs", target_module));
+    let mut target_file = File::open(target_path).expect("Must open target file");
+    
+    let mut contents = String::new();
+    target_file.read_to_string(&mut contents).expect("Test must read file to string");
+    
+    // Messy output is left as the tokens were written
+    if cfg!(feature = "formatted") && !cfg!(feature = "procout_messy") {
+      assert_eq!(
+        contents,
+        target_output,
+        "Must write target output to file in tests directory corresponding to module Ident"
+      );
+    }
+  }
+  
+  /// A fresh directory for a test's output that stays out of this crate's `tests` targets
+  fn test_output_dir(test_name: &str) -> PathBuf {
+    let output_dir = env::temp_dir().join("procout_tests").join(test_name);
+    let _ = fs::remove_dir_all(&output_dir);
+    output_dir
+  }
+  
+  #[test]
+  fn test_diff_file() {
+    let output_dir = test_output_dir("test_diff_file");
+    let output_path = output_dir.to_str();
+    let module_ident = Ident::new("diffed_module", Span::mixed_site());
+    let config = ProcoutConfig::new().format(Some(true)).diff_file(true);
+    let diff_path = output_dir.join("diffed_module.diff");
+    
+    config.procout(&quote!{ pub mod diffed_module { const CUSS: &str = "SPIT"; } }, Some(module_ident.clone()), output_path);
+    assert!(!diff_path.exists(), "Must not write a diff on the first run");
+    
+    config.procout(&quote!{ pub mod diffed_module { const CUSS: &str = "SPAT"; } }, Some(module_ident.clone()), output_path);
+    let diff = fs::read_to_string(&diff_path).expect("Must write a diff when the content changes");
+    assert!(diff.contains("-    const CUSS: &str = \"SPIT\";"), "Must remove the old line in the diff: {}", diff);
+    assert!(diff.contains("+    const CUSS: &str = \"SPAT\";"), "Must add the new line in the diff: {}", diff);
+    
+    config.procout(&quote!{ pub mod diffed_module { const CUSS: &str = "SPAT"; } }, Some(module_ident), output_path);
+    assert!(!diff_path.exists(), "Must delete a stale diff when the content is unchanged");
+  }
+  
+  #[cfg(unix)]
+  #[test]
+  fn test_mode() {
+    use std::os::unix::fs::PermissionsExt;
+    let output_dir = test_output_dir("test_mode");
+    let module_ident = Ident::new("read_only_module", Span::mixed_site());
+    let code_block = quote!{ pub mod read_only_module {} };
+    let config = ProcoutConfig::new().mode(Some(0o444));
+    
+    config.procout(&code_block, Some(module_ident.clone()), output_dir.to_str());
+    // Overwriting the read-only file from the first run must still work
+    config.procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let metadata = fs::metadata(output_dir.join("read_only_module.rs")).expect("Must write target file");
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o444, "Must apply the mode to the target file");
+  }
+  
+  #[test]
+  fn test_test_body() {
+    let output_dir = test_output_dir("test_test_body");
+    let module_ident = Ident::new("smoke_module", Span::mixed_site());
+    let code_block = quote!{ pub mod smoke_module { pub fn answer() -> u8 { 42 } } };
+    ProcoutConfig::new()
+      .format(Some(true))
+      .test_body(Some(quote!{ assert_eq!(answer(), 42); }))
+      .procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("smoke_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains("fn macro_test() {\n    use smoke_module::*;\n    assert_eq!(answer(), 42);\n}"),
+      "Must append the test body after the import: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  #[should_panic(expected = "Test body must parse as a block")]
+  fn test_test_body_must_parse() {
+    let _ = ProcoutConfig::new().test_body(Some(quote!{ let = ; }));
+  }
+  
+  #[test]
+  fn test_line_endings() {
+    assert_eq!(normalize_line_endings("\u{feff}mod a {\r\n}\r\n"), "mod a {\n}\n", "Must strip the BOM and CRLFs");
+    assert!(
+      matches!(normalize_line_endings("mod a {\n}\n"), Cow::Borrowed(_)),
+      "Must leave normalized contents alone"
+    );
+    
+    let output_dir = test_output_dir("test_line_endings");
+    let module_ident = Ident::new("lf_module", Span::mixed_site());
+    procout(&quote!{ pub mod lf_module { const CUSS: &str = "SPIT"; } }, Some(module_ident), output_dir.to_str());
+    let contents = fs::read(output_dir.join("lf_module.rs")).expect("Must write target file");
+    assert!(!contents.contains(&b'\r'), "Must write only LF line endings");
+    assert!(!contents.starts_with("\u{feff}".as_bytes()), "Must not write a BOM");
+  }
+  
+  #[cfg(feature = "prettyplease")]
+  #[test]
+  fn test_prettyplease() {
+    let output_dir = test_output_dir("test_prettyplease");
+    let module_ident = Ident::new("pretty_module", Span::mixed_site());
+    let code_block = quote!{
+      /// Documented
+      pub mod pretty_module { const CUSS: &str = "SPIT"; }
+    };
+    ProcoutConfig::new()
+      .format(Some(true))
+      .format_command(FormatCommand::Prettyplease)
+      .procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("pretty_module.rs")).expect("Must write target file");
+    assert!(contents.starts_with(GENERATED_HEADER), "Must keep the header comment: {}", contents);
+    assert!(
+      contents.contains("/// Documented\npub mod pretty_module {\n    const CUSS: &str = \"SPIT\";\n}"),
+      "Must lay out the code and keep doc comments: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_resolve_output_path() {
+    let manifest_dir = OsStr::new(env!("CARGO_MANIFEST_DIR"));
+    assert_eq!(
+      resolve_output_path(Path::new("tests/dumps"), Some(manifest_dir)),
+      Path::new(manifest_dir).join("tests/dumps"),
+      "Must resolve a relative path against CARGO_MANIFEST_DIR when it's set"
+    );
+    assert_eq!(
+      resolve_output_path(Path::new("tests/dumps"), None),
+      env::current_dir().expect("Must identify current dir").join("tests/dumps"),
+      "Must resolve a relative path against the current dir without CARGO_MANIFEST_DIR"
+    );
+    let absolute_path = env::temp_dir().join("dumps");
+    assert_eq!(
+      resolve_output_path(&absolute_path, Some(manifest_dir)),
+      absolute_path,
+      "Must use an absolute path as it is"
+    );
+  }
+  
+  #[test]
+  fn test_procout_derive() {
+    let output_dir = test_output_dir("test_procout_derive");
+    let input = quote!{
+      #[derived(helper)]
+      pub struct DerivedType { field: u8 }
+    };
+    let output = quote!{
+      impl DerivedType { pub fn field(&self) -> u8 { self.field } }
+    };
+    ProcoutConfig::new().format(Some(true)).procout_derive(&input, &output, output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("derived_type.rs")).expect("Must write file named after the type");
+    assert!(contents.contains("// module: DerivedType\n"), "Must record the type in the header: {}", contents);
+    assert!(
+      contents.contains("pub struct DerivedType {\n    field: u8,\n}\nimpl DerivedType {"),
+      "Must write the item without helper attributes, followed by the output: {}",
+      contents
+    );
+    assert!(contents.contains("    use DerivedType as _;\n"), "Must name the type in the test: {}", contents);
+  }
+  
+  #[test]
+  fn test_procout_attr() {
+    let output_dir = test_output_dir("test_procout_attr");
+    let attr = quote!{ level = "debug" };
+    let item = quote!{ fn traced() -> u8 { 1 } };
+    let output = quote!{ fn traced() -> u8 { std::println!("traced"); 1 } };
+    ProcoutConfig::new().format(Some(true)).procout_attr(&attr, &item, &output, output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("traced.rs")).expect("Must write file named after the item");
+    assert!(
+      contents.contains("// module: traced\n// attribute args: level = \"debug\"\n// original item: fn traced () -> u8 { 1 }\n"),
+      "Must record the attribute's input after the header: {}",
+      contents
+    );
+    assert!(contents.contains("    std::println!(\"traced\");\n"), "Must write the output: {}", contents);
+    assert!(contents.contains("    use traced as _;\n"), "Must name the transformed item in the test: {}", contents);
+  }
+  
+  #[test]
+  fn test_no_std() {
+    let output_dir = test_output_dir("test_no_std");
+    let module_ident = Ident::new("no_std_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .no_std(true)
+      .no_std_alloc(true)
+      .procout(&quote!{ pub mod no_std_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("no_std_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains(&format!("#![no_std]\n{}extern crate alloc;\n#[cfg(test)]\nextern crate std;\n", ALLOWS)),
+      "Must make the file no_std with alloc, linking std for the test: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_upsert_module() {
+    let output_dir = test_output_dir("test_upsert_module");
+    let config = ProcoutConfig::new().format(Some(true)).upsert_module(Some("curated.rs"));
+    let first_ident = Ident::new("first_module", Span::mixed_site());
+    let second_ident = Ident::new("second_module", Span::mixed_site());
+    config.procout(&quote!{ pub mod first_module { const CUSS: &str = "SPIT"; } }, Some(first_ident.clone()), output_dir.to_str());
+    config.procout(&quote!{ pub mod second_module {} }, Some(second_ident), output_dir.to_str());
+    config.procout(&quote!{ pub mod first_module { const CUSS: &str = "SPAT"; } }, Some(first_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("curated.rs")).expect("Must write the curated file");
+    assert!(contents.contains("\"SPAT\"") && !contents.contains("\"SPIT\""), "Must replace the first module: {}", contents);
+    assert_eq!(contents.matches("fn macro_test_first_module()").count(), 1, "Must replace the first test: {}", contents);
+    assert!(contents.contains("fn macro_test_second_module()"), "Must keep the second module's test: {}", contents);
+    
+    fs::write(output_dir.join("curated.rs"), "mod   untouched {}\n").expect("Must write a curated file");
+    config.procout(&quote!{ pub mod first_module { const CUSS: &str = "SPIT"; } }, Some(Ident::new("first_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("curated.rs")).expect("Must update the curated file");
+    assert!(
+      contents.starts_with("mod   untouched {}\npub mod first_module {\n    const CUSS: &str = \"SPIT\";\n}\n#[test]\n"),
+      "Must format only the new items: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_render_notification() {
+    assert_eq!(
+      render_notification(DEFAULT_NOTIFICATION_TEMPLATE, "tests/a.rs", "a", 12, 1),
+      "Wrote macro to `tests/a.rs` ",
+      "Must render the default template as before"
+    );
+    assert_eq!(
+      render_notification("[procout] {module} -> {path} ({bytes} bytes, write {count})", "tests/a.rs", "a", 12, 3),
+      "[procout] a -> tests/a.rs (12 bytes, write 3)",
+      "Must fill in every placeholder"
+    );
+  }
+  
+  #[test]
+  fn test_render_snapshot() {
+    let code_block = quote!{ pub mod snapshot { const CUSS: &str = "SPIT"; } };
+    assert_eq!(
+      ProcoutConfig::new().format(Some(true)).render_snapshot(&code_block),
+      "pub mod snapshot {\n    const CUSS: &str = \"SPIT\";\n}\n",
+      "Must render just the formatted expansion"
+    );
+  }
+  
+  #[cfg(feature = "insta")]
+  #[test]
+  fn test_procout_insta_snapshot() {
+    procout_insta_snapshot!(&quote!{ pub mod snapshot { const CUSS: &str = "SPIT"; } }, "procout_insta_snapshot", ProcoutConfig::new().format(Some(true)));
+  }
+  
+  #[test]
+  fn test_filename_transform() {
+    let output_dir = test_output_dir("test_filename_transform");
+    let module_ident = Ident::new("IPv4", Span::mixed_site());
+    ProcoutConfig::new()
+      .filename_transform(Box::new(|ident: &str| ident.to_lowercase()))
+      .procout(&quote!{ pub mod IPv4 {} }, Some(module_ident), output_dir.to_str());
+    
+    assert!(output_dir.join("ipv4.rs").is_file(), "Must name the file with the transform");
+    assert!(!output_dir.join("i_pv_4.rs").exists(), "Must bypass snake case");
+  }
+  
+  #[test]
+  fn test_test_name_collision() {
+    let output_dir = test_output_dir("test_test_name_collision");
+    let module_ident = Ident::new("colliding_module", Span::mixed_site());
+    let code_block = quote!{
+      pub mod colliding_module {}
+      fn macro_test() {}
+    };
+    ProcoutConfig::new().format(Some(true)).procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("colliding_module.rs")).expect("Must write target file");
+    assert!(contents.contains("fn macro_test() {}\n"), "Must keep the output's own item: {}", contents);
+    assert!(contents.contains("#[test]\nfn macro_test_generated() {\n"), "Must rename the generated test: {}", contents);
+  }
+  
+  #[test]
+  fn test_procout_macro() {
+    let output_dir = test_output_dir("test_procout_macro");
+    let output_path = output_dir.to_str().expect("Must name output dir");
+    let macro_module = quote!{ pub mod macro_module {} };
+    procout!(macro_module => output_path);
+    assert!(output_dir.join("macro_module.rs").is_file(), "Must name the file after the variable");
+    procout!(labelled_module: quote!{ pub mod labelled_module {} } => output_path);
+    let contents = fs::read_to_string(output_dir.join("labelled_module.rs")).expect("Must name the file after the label");
+    if cfg!(feature = "formatted") && !cfg!(feature = "procout_messy") {
+      assert!(contents.contains("    use labelled_module::*;\n"), "Must name the module after the label: {}", contents);
+    }
+  }
+  
+  #[test]
+  fn test_env_enables() {
+    assert!(env_enables(None), "Must write when the var is unset");
+    for value in ["1", "true", "YES", "on"] {
+      assert!(env_enables(Some(OsStr::new(value))), "Must write when the var is `{}`", value);
+    }
+    for value in ["", "0", "False", "no", "OFF"] {
+      assert!(!env_enables(Some(OsStr::new(value))), "Must not write when the var is `{}`", value);
+    }
+  }
+  
+  #[test]
+  fn test_fallback_output_dir() {
+    let default_dir = Path::new("registered/dumps");
+    assert_eq!(fallback_output_dir(None, None), None, "Must leave the tests dir fallback when nothing is set");
+    assert_eq!(fallback_output_dir(None, Some(default_dir)), Some(default_dir.to_path_buf()), "Must use the registered dir");
+    assert_eq!(
+      fallback_output_dir(Some(OsStr::new("env/dumps")), Some(default_dir)),
+      Some(PathBuf::from("env/dumps")),
+      "Must prefer the env var to the registered dir"
+    );
+    assert_eq!(fallback_output_dir(Some(OsStr::new("")), Some(default_dir)), Some(default_dir.to_path_buf()), "Must ignore an empty env var");
+  }
+  
+  #[test]
+  fn test_record_input() {
+    let output_dir = test_output_dir("test_record_input");
+    let module_ident = Ident::new("recorded_module", Span::mixed_site());
+    let input = quote!{ recorded_module, "SPIT" };
+    ProcoutConfig::new()
+      .record_input(Some(&input))
+      .procout(&quote!{ pub mod recorded_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("recorded_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains("// module: recorded_module\n/* procout input: recorded_module , \"SPIT\" */\n"),
+      "Must record the input after the header: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_markdown() {
+    let output_dir = test_output_dir("test_markdown");
+    let module_ident = Ident::new("documented_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .markdown(true)
+      .procout(&quote!{ pub mod documented_module { pub const CUSS: &str = "SPIT"; } }, Some(module_ident), output_dir.to_str());
+    
+    assert!(!output_dir.join("documented_module.rs").exists(), "Must not write the test file");
+    let contents = fs::read_to_string(output_dir.join("documented_module.md")).expect("Must write the markdown file");
+    assert!(
+      contents.ends_with("```rust\npub mod documented_module {\n    pub const CUSS: &str = \"SPIT\";\n}\n# use documented_module::*;\n```\n"),
+      "Must write the formatted code as a doctest: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_count_in_filename() {
+    let output_dir = test_output_dir("test_count_in_filename");
+    let config = ProcoutConfig::new().count_in_filename(true);
+    for _ in 0..2 {
+      config.procout(&quote!{ pub mod counted_file {} }, Some(Ident::new("counted_file", Span::mixed_site())), output_dir.to_str());
+    }
+    assert_eq!(write_count("counted_file"), 2, "Must count every write");
+    assert!(output_dir.join("counted_file_1.rs").is_file(), "Must keep the first write");
+    assert!(output_dir.join("counted_file_2.rs").is_file(), "Must number the second write");
+    
+    fs::write(output_dir.join("refused_file.rs"), "").expect("Must write the existing file");
+    let refused = std::panic::catch_unwind(|| {
+      ProcoutConfig::new()
+        .overwrite_policy(OverwritePolicy::Never)
+        .procout(&quote!{ pub mod refused_file {} }, Some(Ident::new("refused_file", Span::mixed_site())), output_dir.to_str());
+    });
+    assert!(refused.is_err(), "Must refuse to overwrite");
+    assert_eq!(write_count("refused_file"), 0, "Must not count a write that was refused");
+  }
+  
+  #[test]
+  fn test_anchors() {
+    let output_dir = test_output_dir("test_anchors");
+    let module_ident = Ident::new("anchored_module", Span::mixed_site());
+    let code_block = quote!{
+      pub mod anchored_module {}
+      /// Documented
+      pub struct Anchored;
+      impl Anchored {}
+    };
+    ProcoutConfig::new().format(Some(true)).anchors(true).procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("anchored_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains(
+        "// procout-item: anchored_module\npub mod anchored_module {}\n// procout-item: Anchored\n#[doc = r\" Documented\"]\npub struct Anchored;\n// procout-item: impl Anchored\nimpl Anchored {}\n#[test]\n"
+      ),
+      "Must anchor each item: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_find_rustfmt_config() {
+    let project_root = test_output_dir("test_find_rustfmt_config");
+    fs::create_dir_all(&project_root).expect("Must create project root");
+    assert_eq!(find_rustfmt_config(Some(project_root.as_os_str())), None, "Must do without a config");
+    fs::write(project_root.join(".rustfmt.toml"), "tab_spaces = 2\n").expect("Must write config");
+    assert_eq!(
+      find_rustfmt_config(Some(project_root.as_os_str())),
+      Some(project_root.join(".rustfmt.toml")),
+      "Must find the config in the project root"
+    );
+  }
+  
+  #[test]
+  fn test_procout_into_file() {
+    let output_dir = test_output_dir("test_procout_into_file");
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let path = output_dir.join("opened.rs");
+    let mut file = File::create(&path).expect("Must open file");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .procout_into_file(&quote!{ pub mod opened_module {} }, Some(Ident::new("opened_module", Span::mixed_site())), &mut file)
+      .expect("Must write into the file");
+    
+    let contents = fs::read_to_string(&path).expect("Must read file");
+    assert!(contents.starts_with("// @generated by procout\n// module: opened_module\n"), "Must write the header: {}", contents);
+    assert!(
+      contents.ends_with("pub mod opened_module {}\n#[test]\nfn macro_test() {\n    use opened_module::*;\n}\n"),
+      "Must write the formatted code and test: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_split_impls() {
+    let output_dir = test_output_dir("test_split_impls");
+    let input = quote!{ pub struct SplitType; };
+    let output = quote!{
+      impl SplitType { pub fn new() -> Self { SplitType } }
+      impl Default for SplitType { fn default() -> Self { Self::new() } }
+    };
+    ProcoutConfig::new().format(Some(true)).split_impls(true).procout_derive(&input, &output, output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("split_type.rs")).expect("Must write the definitions file");
+    assert!(
+      contents.contains("pub struct SplitType;\n#[path = \"split_type/split_type_impls.rs\"]\nmod impls;\n"),
+      "Must declare the impls module: {}",
+      contents
+    );
+    assert!(!contents.contains("impl Default"), "Must move the impls: {}", contents);
+    let impls = fs::read_to_string(output_dir.join("split_type").join("split_type_impls.rs")).expect("Must write the impls file");
+    assert!(impls.contains("use super::*;\nimpl SplitType {\n"), "Must import the definitions: {}", impls);
+    assert!(impls.contains("impl Default for SplitType {\n"), "Must write every impl: {}", impls);
+  }
+  
+  #[cfg(feature = "trybuild")]
+  #[test]
+  fn test_procout_trybuild() {
+    let output_dir = test_output_dir("test_procout_trybuild");
+    let cases = trybuild::TestCases::new();
+    let target_path = ProcoutConfig::new().format(Some(true)).procout_trybuild(
+      &quote!{ pub mod tried_module { pub const CUSS: &str = "SPIT"; } },
+      Some(Ident::new("tried_module", Span::mixed_site())),
+      output_dir.to_str(),
+      &cases,
+    );
+    let contents = fs::read_to_string(&target_path).expect("Must write the case");
+    assert!(contents.contains("fn main() {}\n"), "Must make the case a binary: {}", contents);
+  }
+  
+  #[test]
+  fn test_test_import_visibility() {
+    let output_dir = test_output_dir("test_test_import_visibility");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .test_import_visibility(TestImportVisibility::PubCrate)
+      .procout(&quote!{ pub mod visible_module {} }, Some(Ident::new("visible_module", Span::mixed_site())), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("visible_module.rs")).expect("Must write target file");
+    assert!(contents.contains("    pub(crate) use visible_module::*;\n"), "Must give the import the visibility: {}", contents);
+    assert_eq!(
+      TestImportVisibility::Pub.apply(quote!{ let _ = fragment; }).to_string(),
+      quote!{ let _ = fragment; }.to_string(),
+      "Must leave an import that isn't a use alone"
+    );
+  }
+  
+  #[test]
+  fn test_format_output_atomic() {
+    let output_dir = test_output_dir("test_format_output_atomic");
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let broken_path = output_dir.join("broken.rs");
+    fs::write(&broken_path, "fn broken( {").expect("Must write broken file");
+    ProcoutConfig::new().format_output(&broken_path);
+    assert_eq!(
+      fs::read_to_string(&broken_path).expect("Must keep broken file"), "fn broken( {",
+      "Must leave the original alone when formatting fails"
+    );
+    assert!(!format_copy_path(&broken_path).exists(), "Must remove the copy when formatting fails");
+    
+    let messy_path = output_dir.join("messy.rs");
+    fs::write(&messy_path, "fn   messy ( ) { }").expect("Must write messy file");
+    ProcoutConfig::new().format_output(&messy_path);
+    assert_eq!(
+      fs::read_to_string(&messy_path).expect("Must keep messy file"), "fn messy() {}\n",
+      "Must replace the original with its formatted copy"
+    );
+    assert!(!format_copy_path(&messy_path).exists(), "Must not leave the copy behind");
+  }
+  
+  #[test]
+  fn test_max_depth() {
+    let output_dir = test_output_dir("test_max_depth");
+    let mut deep = quote!{ 1 };
+    for _ in 0..500 {
+      deep = quote!{ (#deep) };
+    }
+    let module_ident = Ident::new("deep_module", Span::mixed_site());
+    ProcoutConfig::new().procout(&quote!{ pub mod deep_module { pub const DEEP: u8 = #deep; } }, Some(module_ident), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("deep_module.rs")).expect("Must write target file");
+    assert!(contents.contains("pub mod deep_module { pub const DEEP : u8 = (((("), "Must write the output raw: {}", &contents[..200]);
+    
+    let module_ident = Ident::new("shallow_module", Span::mixed_site());
+    ProcoutConfig::new().max_depth(1).procout(&quote!{ pub mod shallow_module { pub const SHALLOW: u8 = (1); } }, Some(module_ident), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("shallow_module.rs")).expect("Must write target file");
+    assert!(contents.contains("pub mod shallow_module { pub const SHALLOW : u8 = (1) ; }"), "Must honour a lower max depth: {}", contents);
+  }
+  
+  #[test]
+  fn test_skip_format_over_bytes() {
+    let output_dir = test_output_dir("test_skip_format_over_bytes");
+    let code_block = quote!{ pub mod huge_module { pub fn huge() {} } };
+    ProcoutConfig::new()
+      .format(Some(true))
+      .skip_format_over_bytes(Some(8))
+      .procout(&code_block, Some(Ident::new("huge_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("huge_module.rs")).expect("Must write target file");
+    assert!(contents.contains(&code_block.to_string()), "Must write the raw tokens over the threshold: {}", contents);
+    
+    ProcoutConfig::new()
+      .format(Some(true))
+      .skip_format_over_bytes(Some(1 << 20))
+      .procout(&code_block, Some(Ident::new("huge_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("huge_module.rs")).expect("Must write target file");
+    assert!(contents.contains("pub mod huge_module {\n"), "Must format under the threshold: {}", contents);
+  }
+  
+  #[cfg(feature = "json")]
+  #[test]
+  fn test_output_format_json() {
+    let output_dir = test_output_dir("test_output_format_json");
+    ProcoutConfig::new()
+      .output_format(OutputFormat::Json)
+      .procout(&quote!{ pub mod json_module { pub struct Analyzed; } }, Some(Ident::new("json_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("json_module.json")).expect("Must write a JSON file");
+    let value: serde_json::Value = serde_json::from_str(&contents).expect("Must write valid JSON");
+    assert_eq!(value["items"][0]["kind"], "mod", "Must describe the code: {}", contents);
+  }
+  
+  #[test]
+  fn test_output_format_const() {
+    let output_dir = test_output_dir("test_output_format_const");
+    let module_ident = Ident::new("const_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .output_format(OutputFormat::Const)
+      .procout(&quote!{ pub mod const_module { pub const RAW: &str = r#"quoted"#; } }, Some(module_ident), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("const_module.rs")).expect("Must write target file");
+    let file = syn::parse_file(&contents).unwrap_or_else(|err| panic!("Must write valid Rust: {}\n{}", err, contents));
+    let expansion = match &file.items[..] {
+      [Item::Const(item_const)] if item_const.ident == "EXPANSION" => match &*item_const.expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit_str), .. }) => lit_str.value(),
+        expr => panic!("Must hold a string, not `{}`", expr.to_token_stream()),
+      },
+      _ => panic!("Must hold just the const: {}", contents),
+    };
+    assert_eq!(expansion, "pub mod const_module {\n    pub const RAW: &str = r#\"quoted\"#;\n}\n", "Must hold the formatted code");
+    assert!(expansion.contains("r#\"quoted\"#"), "Must hold the code as it is: {}", expansion);
+  }
+  
+  #[test]
+  fn test_procout_if() {
+    let output_dir = test_output_dir("test_procout_if");
+    let mentions_wanted = |code_block: &TokenStream| code_block.to_string().contains("wanted");
+    procout_if(mentions_wanted, &quote!{ pub mod skipped_module {} }, Some(Ident::new("skipped_module", Span::mixed_site())), output_dir.to_str());
+    procout_if(mentions_wanted, &quote!{ pub mod wanted_module {} }, Some(Ident::new("wanted_module", Span::mixed_site())), output_dir.to_str());
+    assert!(!output_dir.join("skipped_module.rs").exists(), "Must not write when the predicate fails");
+    assert!(output_dir.join("wanted_module.rs").exists(), "Must write when the predicate holds");
+  }
+  
+  #[test]
+  fn test_under_src() {
+    let manifest_dir = OsStr::new("/crate");
+    assert!(under_src(Path::new("/crate/src/dumps"), Some(manifest_dir)), "Must detect a dir in src/");
+    assert!(under_src(Path::new("/crate/tests/../src"), Some(manifest_dir)), "Must resolve the path first");
+    assert!(!under_src(Path::new("/crate/tests/src"), Some(manifest_dir)), "Must only detect the crate's src/");
+    assert!(!under_src(Path::new("/crate/srcs"), Some(manifest_dir)), "Must compare whole components");
+    assert!(!under_src(Path::new("/crate/src"), None), "Must not guess without a crate");
+  }
+  
+  #[test]
+  #[should_panic(expected = "inside the crate's src/ dir")]
+  fn test_deny_src_output() {
+    let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    ProcoutConfig::new()
+      .deny_src_output(true)
+      .procout(&quote!{ pub mod denied_module {} }, Some(Ident::new("denied_module", Span::mixed_site())), src_dir.to_str());
+  }
+  
+  #[test]
+  fn test_git_commit() {
+    let output_dir = test_output_dir("test_git_commit");
+    ProcoutConfig::new()
+      .git_commit(true)
+      .procout(&quote!{ pub mod committed_module {} }, Some(Ident::new("committed_module", Span::mixed_site())), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("committed_module.rs")).expect("Must write target file");
+    match git_commit() {
+      Some(commit) => assert!(
+        contents.contains(&format!("// module: committed_module\n// commit: {}\n", commit)),
+        "Must record the commit after the module: {}",
+        contents
+      ),
+      None => assert!(!contents.contains(COMMIT_HEADER_PREFIX), "Must leave out a missing commit: {}", contents),
+    }
+  }
+  
+  #[test]
+  fn test_procout_modules() {
+    let output_dir = test_output_dir("test_procout_modules");
+    let module_idents = [
+      Ident::new("first_module", Span::mixed_site()),
+      Ident::new("second_module", Span::mixed_site()),
+    ];
+    ProcoutConfig::new().format(Some(true)).procout_modules(
+      &quote!{ pub mod first_module {} pub mod second_module {} },
+      &module_idents,
+      output_dir.to_str(),
+    );
+    
+    let contents = fs::read_to_string(output_dir.join("first_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains("fn macro_test_second_module() {\n    use second_module::*;\n}\n"),
+      "Must test the other modules: {}",
+      contents
+    );
+    assert!(
+      contents.ends_with("fn macro_test_first_module() {\n    use first_module::*;\n}\n"),
+      "Must test the first module: {}",
+      contents
+    );
+    assert!(syn::parse_file(&contents).is_ok(), "Must write a valid file: {}", contents);
+  }
+  
+  #[test]
+  fn test_overwrite_policy() {
+    let output_dir = test_output_dir("test_overwrite_policy");
+    let code_block = quote!{ pub mod kept_module {} };
+    let write = |overwrite_policy| ProcoutConfig::new()
+      .overwrite_policy(overwrite_policy)
+      .procout(&code_block, Some(Ident::new("kept_module", Span::mixed_site())), output_dir.to_str());
+    write(OverwritePolicy::Never);
+    let target_path = output_dir.join("kept_module.rs");
+    assert!(target_path.exists(), "Must write a new file");
+    
+    let old_modified = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    File::options().write(true).open(&target_path).and_then(|file| file.set_modified(old_modified)).expect("Must age file");
+    write(OverwritePolicy::Newer);
+    assert_eq!(
+      fs::metadata(&target_path).and_then(|metadata| metadata.modified()).ok(), Some(old_modified),
+      "Must leave an unchanged file looking untouched"
+    );
+    
+    let overwrite = std::panic::catch_unwind(|| write(OverwritePolicy::Never));
+    assert!(overwrite.is_err(), "Must refuse to overwrite");
+  }
+  
+  #[test]
+  fn test_format_file() {
+    let output_dir = test_output_dir("test_format_file");
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let path = output_dir.join("generated.rs");
+    fs::write(&path, "pub  fn  generated ( ) { }").expect("Must write file");
+    format_file(&path).expect("Must format a valid file");
+    assert_eq!(fs::read_to_string(&path).expect("Must keep file"), "pub fn generated() {}\n", "Must format in place");
+    
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      fs::write(&path, "pub  fn  generated ( ) { }").expect("Must write file");
+      fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).expect("Must set the mode");
+      format_file(&path).expect("Must format a valid file");
+      let mode = fs::metadata(&path).expect("Must keep file").permissions().mode();
+      assert_eq!(mode & 0o777, 0o640, "Must keep the file's mode");
     }
     
-    if cfg!(feature = "formatted") {
-      // Try to rustfmt the output, ignoring failure 
-      match Command::new("rustfmt").arg(target_path_str).output() {
-        Ok(output) => std::println!("rustfmt status: {}", output.status),
-        Err(err) => std::println!("Could not rustfmt \"{}\":\n {:#?}", target_path_str, err),
+    fs::write(&path, "pub fn broken( {").expect("Must write file");
+    assert!(matches!(format_file(&path), Err(FormatError::Failed { .. })), "Must report the formatter's failure");
+    assert!(matches!(format_file(&output_dir.join("missing.rs")), Err(FormatError::Io(_))), "Must report a missing file");
+  }
+  
+  #[test]
+  fn test_test_mode() {
+    let output_dir = test_output_dir("test_test_mode");
+    let write = |test_mode, module_name: &str| {
+      ProcoutConfig::new()
+        .format(Some(true))
+        .test_mode(test_mode)
+        .procout(&quote!{ pub mod tested_module {} }, Some(Ident::new(module_name, Span::mixed_site())), output_dir.to_str());
+      fs::read_to_string(output_dir.join(format!("{}.rs", module_name))).expect("Must write target file")
+    };
+    let compiled = write(TestMode::CompileOnly, "compiled_module");
+    assert!(compiled.contains("#[allow(dead_code)]\nfn macro_test() {\n"), "Must only compile the test: {}", compiled);
+    assert!(!compiled.contains("#[test]"), "Must not run the test: {}", compiled);
+    
+    let untested = write(TestMode::None, "untested_module");
+    assert!(untested.ends_with("pub mod tested_module {}\n"), "Must leave the test out: {}", untested);
+  }
+  
+  #[test]
+  fn test_call_site_spans() {
+    let module_ident: Ident = syn::parse_str("  spanned_module").expect("Must parse the ident");
+    let call_site = format!("{:?}", Span::call_site());
+    fn spans(tokens: TokenStream, found: &mut Vec<String>) {
+      for token in tokens {
+        found.push(format!("{:?}", token.span()));
+        if let proc_macro2::TokenTree::Group(group) = token {
+          spans(group.stream(), found);
+        }
       }
     }
+    let rendered = |config: &ProcoutConfig| {
+      let (_, _, import) = config.module_import(&module_ident);
+      let mut found = Vec::new();
+      spans(config.render_test(&module_test_ident(&module_ident), &import), &mut found);
+      found
+    };
+    let test_body = Some(syn::parse_str::<TokenStream>("  assert!(true);").expect("Must parse the body"));
+    
+    // Spans only tell apart where they're from when they have locations
+    #[cfg(feature = "span_map")]
+    {
+      let found = rendered(&ProcoutConfig::new().test_body(test_body.clone()));
+      assert!(found.iter().any(|span| span != &call_site), "Must keep the module's spans by default");
+    }
+    let found = rendered(&ProcoutConfig::new().test_body(test_body).call_site_spans(true));
+    assert!(found.iter().all(|span| span == &call_site), "Must respan the test and its import to the call site: {:?}", found);
   }
-}
-
-
-#[cfg(test)]
-mod tests {
-  use super::*;
+  
   #[test]
-  fn test_procout() {
-    let target_module = "test_procout_module";
-    let module_ident = Ident::new(&target_module, Span::mixed_site());
-    let code_block: proc_macro2::TokenStream = quote!{  
-       pub mod #module_ident {
-         const CUSS: &str = "SPIT";
-       }
+  fn test_html_index() {
+    let output_dir = test_output_dir("test_html_index");
+    let ident = |name: &str| Ident::new(name, Span::mixed_site());
+    let config = ProcoutConfig::new().html_index(true);
+    config.procout(&quote!{ pub mod first_indexed {} }, Some(ident("first_indexed")), output_dir.to_str());
+    config.procout(&quote!{ pub mod second_indexed {} }, Some(ident("second_indexed")), output_dir.to_str());
+    
+    let index = fs::read_to_string(output_dir.join(INDEX_FILE)).expect("Must write the index");
+    assert!(index.contains("<p>2 files.</p>"), "Must index every dump: {}", index);
+    assert!(index.contains("<a href=\"first_indexed.rs\">") && index.contains("<a href=\"second_indexed.rs\">"), "Must link each dump");
+    config.procout(&quote!{ pub mod second_indexed {} }, Some(ident("second_indexed")), output_dir.to_str());
+    assert_eq!(fs::read_to_string(output_dir.join(INDEX_FILE)).expect("Must keep the index"), index, "Must rewrite the same index for the same dumps");
+  }
+  
+  #[test]
+  fn test_include_stubs() {
+    let output_dir = test_output_dir("test_include_stubs");
+    for module_name in ["first_included", "second_included"] {
+      ProcoutConfig::new()
+        .include_stubs(true)
+        .procout(&quote!{ pub mod included {} }, Some(Ident::new(module_name, Span::mixed_site())), output_dir.to_str());
+    }
+    let contents = fs::read_to_string(output_dir.join(INCLUDE_DIR).join(INCLUDE_FILE)).expect("Must write the include file");
+    assert!(
+      contents.ends_with("#[path = \"../first_included.rs\"]\nmod first_included;\n#[path = \"../second_included.rs\"]\nmod second_included;\n"),
+      "Must stub every dump: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_durable() {
+    let output_dir = test_output_dir("test_durable");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .durable(true)
+      .procout(&quote!{ pub mod durable_module {} }, Some(Ident::new("durable_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("durable_module.rs")).expect("Must write target file");
+    assert!(contents.contains("pub mod durable_module {}\n"), "Must write the file as usual: {}", contents);
+  }
+  
+  #[test]
+  fn test_reformat_dir() {
+    let output_dir = test_output_dir("test_reformat_dir");
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let dump_path = output_dir.join("messy_dump.rs");
+    fs::write(&dump_path, format!("{}pub  mod  messy_dump {{ }}", render_header("messy_dump"))).expect("Must write dump");
+    fs::write(output_dir.join("broken_dump.rs"), format!("{}pub mod broken_dump {{", render_header("broken_dump")))
+      .expect("Must write dump");
+    fs::write(output_dir.join("hand_written.rs"), "pub  mod  hand_written { }").expect("Must write unrelated file");
+    
+    assert_eq!(reformat_dir(&output_dir).expect("Must read the dir"), vec![dump_path.clone()], "Must reformat the dumps that format");
+    assert!(fs::read_to_string(&dump_path).expect("Must keep dump").ends_with("pub mod messy_dump {}\n"), "Must reformat the dump");
+    assert_eq!(
+      fs::read_to_string(output_dir.join("hand_written.rs")).expect("Must keep unrelated file"), "pub  mod  hand_written { }",
+      "Must leave files that aren't dumps alone"
+    );
+  }
+  
+  #[test]
+  fn test_test_attr() {
+    let output_dir = test_output_dir("test_test_attr");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .test_attr(Some(quote!{ #[tokio::test] }))
+      .procout(&quote!{ pub mod async_module {} }, Some(Ident::new("async_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("async_module.rs")).expect("Must write target file");
+    assert!(contents.contains("#[tokio::test]\nfn macro_test() {\n"), "Must use the test attribute: {}", contents);
+    
+    let invalid = std::panic::catch_unwind(|| ProcoutConfig::new().test_attr(Some(quote!{ tokio::test })));
+    assert!(invalid.is_err(), "Must reject a test attribute that isn't an attribute");
+  }
+  
+  #[test]
+  fn test_test_harness() {
+    struct WasmTest;
+    impl TestHarness for WasmTest {
+      fn attributes(&self) -> TokenStream {
+        quote!{ #[wasm_bindgen_test] }
+      }
+    }
+    let output_dir = test_output_dir("test_test_harness");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .test_attr(Some(quote!{ #[tokio::test] }))
+      .test_harness(Box::new(WasmTest))
+      .procout(&quote!{ pub mod wasm_module {} }, Some(Ident::new("wasm_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("wasm_module.rs")).expect("Must write target file");
+    assert!(contents.contains("#[wasm_bindgen_test]\nfn macro_test() {\n"), "Must declare the test with the harness: {}", contents);
+  }
+  
+  #[cfg(feature = "tokio")]
+  #[test]
+  fn test_tokio_test() {
+    let output_dir = test_output_dir("test_tokio_test");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .test_harness(Box::new(TokioTest))
+      .procout(&quote!{ pub mod tokio_module {} }, Some(Ident::new("tokio_module", Span::mixed_site())), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("tokio_module.rs")).expect("Must write target file");
+    assert!(contents.contains("#[tokio::test]\nasync fn macro_test() {\n"), "Must declare an async test: {}", contents);
+  }
+  
+  #[cfg(feature = "content_hash")]
+  #[test]
+  fn test_content_hash() {
+    let output_dir = test_output_dir("test_content_hash");
+    let config = ProcoutConfig::new().content_hash(true);
+    let write = |code_block: TokenStream| config
+      .procout_to_path(&code_block, Some(Ident::new("hashed_module", Span::mixed_site())), Some(&output_dir))
+      .expect("Must write the file");
+    let path = write(quote!{ pub mod hashed_module {} });
+    assert!(
+      path.file_name().and_then(OsStr::to_str).is_some_and(|name| name.starts_with("dump_") && name.ends_with(".rs")),
+      "Must name the file by its hash: {}",
+      path.display()
+    );
+    assert!(path.exists(), "Must return the path written");
+    assert_eq!(write(quote!{ pub mod hashed_module {} }), path, "Must collapse identical expansions into one file");
+    assert_ne!(write(quote!{ pub mod hashed_module { const X: u8 = 1; } }), path, "Must write changed expansions to a new file");
+    assert!(
+      !fs::read_dir(&output_dir).expect("Must list the output dir")
+        .any(|entry| entry.expect("Must read the entry").file_name().to_string_lossy().starts_with(hash::STAGED_PREFIX)),
+      "Must not leave the staged file behind"
+    );
+    
+    // The same code written differently is hashed as it's written
+    let gated_path = ProcoutConfig::new()
+      .content_hash(true)
+      .feature_gate(Some("hashed"))
+      .procout_to_path(&quote!{ pub mod hashed_module {} }, Some(Ident::new("hashed_module", Span::mixed_site())), Some(&output_dir))
+      .expect("Must write the file");
+    assert_ne!(gated_path, path, "Must name a differently gated file differently");
+    let tested_path = ProcoutConfig::new()
+      .content_hash(true)
+      .test_body(Some(quote!{ assert!(true); }))
+      .procout_to_path(&quote!{ pub mod hashed_module {} }, Some(Ident::new("hashed_module", Span::mixed_site())), Some(&output_dir))
+      .expect("Must write the file");
+    assert_ne!(tested_path, path, "Must name a file with another test differently");
+    assert_ne!(tested_path, gated_path, "Must name each file by its own contents");
+    let contents = fs::read(&tested_path).expect("Must write the file");
+    assert_eq!(
+      tested_path.file_stem().and_then(OsStr::to_str),
+      Some(hash::content_hash_stem(&contents).as_str()),
+      "Must hash the file as it's written"
+    );
+  }
+  
+  #[cfg(feature = "tempfile")]
+  #[test]
+  fn test_procout_tempfile() {
+    let temp_file = ProcoutConfig::new()
+      .format(Some(true))
+      .procout_tempfile(&quote!{ pub mod temporary_module {} }, Some(Ident::new("temporary_module", Span::mixed_site())))
+      .expect("Must write the temp file");
+    let path = temp_file.path().to_path_buf();
+    let file_name = path.file_name().and_then(OsStr::to_str).expect("Must name the temp file");
+    assert!(file_name.starts_with("temporary_module_") && file_name.ends_with(".rs"), "Must name it after the module: {}", file_name);
+    let contents = fs::read_to_string(&path).expect("Must write the temp file");
+    assert!(contents.contains("pub mod temporary_module {}\n#[test]\n"), "Must write the formatted file: {}", contents);
+    drop(temp_file);
+    assert!(!path.exists(), "Must leave deleting it to the caller's temp file");
+  }
+  
+  #[test]
+  fn test_procout_to() {
+    let output_dir = test_output_dir("test_procout_to");
+    procout_to(&quote!{ pub mod pathed_module {} }, Some(Ident::new("pathed_module", Span::mixed_site())), Some(&output_dir));
+    assert!(output_dir.join("pathed_module.rs").exists(), "Must write to a PathBuf");
+    procout_to(&quote!{ pub mod pathed_str {} }, Some(Ident::new("pathed_str", Span::mixed_site())), output_dir.to_str());
+    assert!(output_dir.join("pathed_str.rs").exists(), "Must write to a &str");
+  }
+  
+  #[test]
+  fn test_report() {
+    let output_dir = test_output_dir("test_report");
+    ProcoutConfig::new()
+      .report(true)
+      .procout(&quote!{ pub mod reported_file {} }, Some(Ident::new("reported_file", Span::mixed_site())), output_dir.to_str());
+    let report_path = output_dir.join(REPORT_FILE);
+    write_report(&report_path).expect("Must write the report");
+    let report = fs::read_to_string(&report_path).expect("Must read the report");
+    assert!(
+      report.contains(&format!("| `reported_file` | `{}` | ", output_dir.join("reported_file.rs").display())),
+      "Must report the file: {}",
+      report
+    );
+  }
+  
+  #[test]
+  fn test_unchanged_mtime() {
+    let output_dir = test_output_dir("test_unchanged_mtime");
+    let write = |code_block: TokenStream| ProcoutConfig::new()
+      .overwrite_policy(OverwritePolicy::Newer)
+      .procout(&code_block, Some(Ident::new("stable_module", Span::mixed_site())), output_dir.to_str());
+    let target_path = output_dir.join("stable_module.rs");
+    let modified = || fs::metadata(&target_path).and_then(|metadata| metadata.modified()).expect("Must read the time");
+    write(quote!{ pub mod stable_module { pub const STABLE: u8 = 1; } });
+    let first_modified = modified();
+    
+    // Make sure a rewrite would show up even on file systems with coarse timestamps
+    thread::sleep(Duration::from_millis(1100));
+    write(quote!{ pub mod stable_module { pub const STABLE: u8 = 1; } });
+    assert_eq!(modified(), first_modified, "Must keep the time when the output is unchanged");
+    
+    write(quote!{ pub mod stable_module { pub const STABLE: u8 = 2; } });
+    assert_ne!(modified(), first_modified, "Must update the time when the output changes");
+  }
+  
+  #[test]
+  fn test_as_crate_root() {
+    let output_dir = test_output_dir("test_as_crate_root");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .as_crate_root(true)
+      .procout(&quote!{ pub mod rooted_module { pub struct Rooted; } }, Some(Ident::new("rooted_module", Span::mixed_site())), output_dir.to_str());
+    let target_path = output_dir.join("rooted_module.rs");
+    let contents = fs::read_to_string(&target_path).expect("Must write target file");
+    assert!(contents.contains("    use crate::rooted_module::*;\n"), "Must import from the crate root: {}", contents);
+    
+    let compiled = Command::new("rustc")
+      .args(["--crate-type", "lib", "--edition", "2021", "--test", "--emit=metadata", "--out-dir"])
+      .arg(&output_dir)
+      .arg(&target_path)
+      .status();
+    if let Ok(status) = compiled {
+      assert!(status.success(), "Must compile as a crate of its own: {}", contents);
+    }
+  }
+  
+  #[test]
+  fn test_trailing_newline() {
+    let output_dir = test_output_dir("test_trailing_newline");
+    for (module_name, threshold) in [("formatted_ending", None), ("raw_ending", Some(0))] {
+      ProcoutConfig::new()
+        .skip_format_over_bytes(threshold)
+        .procout(&quote!{ pub mod ending {} }, Some(Ident::new(module_name, Span::mixed_site())), output_dir.to_str());
+      let contents = fs::read_to_string(output_dir.join(format!("{}.rs", module_name))).expect("Must write target file");
+      assert!(contents.ends_with("}\n"), "Must end `{}` in exactly one newline: {:?}", module_name, contents);
+    }
+    
+    let target_path = output_dir.join("hand_ended.rs");
+    for (contents, one_newline, expected) in [
+      ("fn ended() {}", true, "fn ended() {}\n"),
+      ("fn ended() {}\n\n\n", true, "fn ended() {}\n"),
+      ("fn ended() {}", false, "fn ended() {}"),
+    ] {
+      fs::write(&target_path, contents).expect("Must write file");
+      normalize_output(&target_path, one_newline);
+      assert_eq!(fs::read_to_string(&target_path).expect("Must read file"), expected, "Must normalize {:?}", contents);
+    }
+  }
+  
+  #[test]
+  fn test_rustfmt_args() {
+    let output_dir = test_output_dir("test_rustfmt_args");
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let path = output_dir.join("configured.rs");
+    fs::write(&path, "fn configured() { let tuple = (1,2); }").expect("Must write file");
+    ProcoutConfig::new()
+      .rustfmt_args(vec!["--config".to_string(), "tab_spaces=2".to_string()])
+      .format_file(&path)
+      .expect("Must format with the extra arguments");
+    assert_eq!(
+      fs::read_to_string(&path).expect("Must keep file"), "fn configured() {\n  let tuple = (1, 2);\n}\n",
+      "Must pass the extra arguments to rustfmt"
+    );
+    
+    let malformed = ProcoutConfig::new().rustfmt_args(vec!["--no-such-flag".to_string()]).format_file(&path);
+    assert!(matches!(malformed, Err(FormatError::Failed { .. })), "Must report malformed arguments as rustfmt errors");
+  }
+  
+  #[test]
+  fn test_runtime_overrides() {
+    let code_block = quote!{ pub mod overridden { const CUSS: &str = "SPIT"; } };
+    assert_eq!(
+      ProcoutConfig::new().format(Some(false)).render_snapshot(&code_block), code_block.to_string(),
+      "Must skip formatting when told to at runtime"
+    );
+    assert_eq!(
+      ProcoutConfig::new().format(Some(true)).render_snapshot(&code_block),
+      "pub mod overridden {\n    const CUSS: &str = \"SPIT\";\n}\n",
+      "Must format when told to at runtime"
+    );
+    assert!(!ProcoutConfig::new().notify(Some(false)).notifies(), "Must stay quiet when told to at runtime");
+    assert_eq!(ProcoutConfig::new().notifies(), cfg!(feature = "notification"), "Must defer to the feature by default");
+  }
+  
+  #[test]
+  fn test_notify_quiet() {
+    let output_dir = test_output_dir("test_notify_quiet");
+    let write = |notify: bool| {
+      let module_ident = Ident::new("quiet_module", Span::mixed_site());
+      ProcoutConfig::new()
+        .format(Some(true))
+        .notify(Some(notify))
+        .notification_stream(Stream::Captured)
+        .procout(&quote!{ pub mod quiet_module {} }, Some(module_ident), output_dir.to_str());
+      take_captured()
     };
+    assert_eq!(write(false), "", "Must print nothing when told to stay quiet");
+    let printed = write(true);
+    assert!(printed.contains("Wrote macro to") && printed.contains("rustfmt status: ok"), "Must print the status otherwise: {}", printed);
+  }
+  
+  #[cfg(feature = "span_map")]
+  #[test]
+  fn test_call_site_dir() {
+    assert_eq!(
+      call_site_dir(Some(PathBuf::from("member/src/lib.rs")), Some(Path::new("/workspace"))),
+      Some(PathBuf::from("/workspace/member/src")),
+      "Must resolve a relative file from the compiler's dir"
+    );
+    assert_eq!(
+      call_site_dir(Some(PathBuf::from("/crate/src/lib.rs")), Some(Path::new("/workspace"))),
+      Some(PathBuf::from("/crate/src")),
+      "Must keep an absolute file's dir"
+    );
+    assert_eq!(call_site_dir(Span::call_site().local_file(), None), None, "Must fall back without a file");
+  }
+  
+  #[cfg(feature = "expect-test")]
+  #[test]
+  fn test_assert_expect() {
+    ProcoutConfig::new().format(Some(true)).assert_expect(&quote!{ pub mod expected { const CUSS: &str = "SPIT"; } }, &expect_test::expect![[r#"
+        pub mod expected {
+            const CUSS: &str = "SPIT";
+        }
+    "#]]);
+  }
+  
+  #[test]
+  fn test_fragment() {
+    let output_dir = test_output_dir("test_fragment");
+    let module_ident = Ident::new("fragment_module", Span::mixed_site());
+    ProcoutConfig::new().format(Some(true)).procout(&quote!{ let x = 1; x + 1 }, Some(module_ident), output_dir.to_str());
     
-    procout(&code_block, Some(module_ident), None);
-    procout(&code_block, None, Some("tests/blah"));
-    let target_output = format!( 
-      "#![allow(unused_imports)]\
-      \n#![allow(dead_code)]\
-      \npub mod {} {{\
-      \n    const CUSS: &str = \"SPIT\";\n\
-      }}\n\
-      #[test]\n\
-      fn macro_test() {{\
-      \n    use {}::*;\n\
-      }}\n",
-      target_module,
-      target_module,
+    let contents = fs::read_to_string(output_dir.join("fragment_module.rs")).expect("Must write target file");
+    assert!(contents.contains("// module: fragment_module\n// fragment: statements\n"), "Must record the fragment kind: {}", contents);
+    assert!(
+      contents.contains("fn _procout_fragment() {\n    let _ = {\n        let x = 1;\n        x + 1\n    };\n}\n"),
+      "Must wrap and format the fragment: {}",
+      contents
     );
-    let mut target_path: PathBuf = env::current_dir().expect("Must identify current dir");
-    target_path.push("tests");
-    target_path.push(format!("{}.rs", target_module));
-    let mut target_file = File::open(target_path).expect("Must open target file");
+    assert!(contents.contains("    let _ = _procout_fragment;\n"), "Must reference the wrapper in the test: {}", contents);
+  }
+  
+  #[test]
+  fn test_fragment_expression() {
+    let output_dir = test_output_dir("test_fragment_expression");
+    let module_ident = Ident::new("expression_module", Span::mixed_site());
+    ProcoutConfig::new().format(Some(true)).procout(&quote!{ vec![1, 2, 3].len() + 1 }, Some(module_ident), output_dir.to_str());
     
-    let mut contents = String::new();
-    target_file.read_to_string(&mut contents).expect("Test must read file to string");
+    let contents = fs::read_to_string(output_dir.join("expression_module.rs")).expect("Must write target file");
+    assert!(contents.contains("// fragment: expression\n"), "Must detect an expression: {}", contents);
+    assert!(
+      syn::parse_file(&contents).is_ok(),
+      "Must wrap an expression into a file: {}",
+      contents
+    );
+    assert!(contents.contains("    let _ = { vec![1, 2, 3].len() + 1 };\n"), "Must keep the expression: {}", contents);
+  }
+  
+  #[test]
+  fn test_procout_dirs() {
+    let output_dir = test_output_dir("test_procout_dirs");
+    let module_ident = Ident::new("copied_module", Span::mixed_site());
+    let written = ProcoutConfig::new().procout_dirs(
+      &quote!{ pub mod copied_module {} },
+      Some(module_ident),
+      &[output_dir.join("tests"), output_dir.join("artifacts"), output_dir.join("tests/../tests")],
+    );
+    assert_eq!(
+      written,
+      vec![output_dir.join("tests/copied_module.rs"), output_dir.join("artifacts/copied_module.rs")],
+      "Must write to each directory once"
+    );
+    assert_eq!(
+      fs::read_to_string(&written[0]).expect("Must write the first copy"),
+      fs::read_to_string(&written[1]).expect("Must write the second copy"),
+      "Must write the same file to each"
+    );
+  }
+  
+  #[test]
+  fn test_rustfmt_retries() {
+    let delay = RetryDelay::Exponential(Duration::from_millis(10));
+    assert_eq!(
+      (0..3).map(|retry| delay.before_retry(retry)).collect::<Vec<_>>(),
+      vec![Duration::from_millis(10), Duration::from_millis(20), Duration::from_millis(40)],
+      "Must double the delay before each retry"
+    );
+    assert_eq!(RetryDelay::Fixed(Duration::from_millis(10)).before_retry(2), Duration::from_millis(10), "Must keep the delay");
+    
+    let (output, retried) = output_with_retries(&mut Command::new("procout-missing-formatter"), 3, delay);
+    assert_eq!(output.map_err(|err| err.kind()).err(), Some(io::ErrorKind::NotFound), "Must report the failure");
+    assert_eq!(retried, 0, "Must not retry a formatter that isn't installed");
+  }
+  
+  #[test]
+  fn test_render_link() {
+    assert_eq!(render_link(LinkStyle::None, Path::new("tests/a.rs")), "tests/a.rs", "Must print the plain path by default");
+    assert!(
+      render_link(LinkStyle::FileUrl, Path::new("tests/a b.rs")).starts_with("file:///"),
+      "Must link to the absolute path"
+    );
+    assert!(
+      render_link(LinkStyle::FileUrl, Path::new("tests/a b.rs")).ends_with("/tests/a%20b.rs"),
+      "Must escape the path"
+    );
+    #[cfg(unix)]
+    assert_eq!(
+      render_link(LinkStyle::VsCode, Path::new("/tmp/a.rs")),
+      "vscode://file/tmp/a.rs",
+      "Must link for the editor"
+    );
+  }
+  
+  #[cfg(windows)]
+  #[test]
+  fn test_formats_path_with_spaces() {
+    let output_dir = test_output_dir("test formats path with spaces");
+    let module_ident = Ident::new("spaced_module", Span::mixed_site());
+    procout(&quote!{ pub mod spaced_module { const CUSS: &str = "SPIT"; } }, Some(module_ident), output_dir.to_str());
+    let contents = fs::read_to_string(output_dir.join("spaced_module.rs")).expect("Must write target file");
+    assert!(contents.contains("\n    const CUSS: &str = \"SPIT\";\n"), "Must rustfmt the file: {}", contents);
+  }
+  
+  #[test]
+  fn test_formatter() {
+    struct ShoutingFormatter;
+    impl Formatter for ShoutingFormatter {
+      fn format(&self, source: &str) -> Result<String, FormatError> {
+        Ok(format!("// SHOUTING\n{}\n", source))
+      }
+    }
+    let output_dir = test_output_dir("test_formatter");
+    let module_ident = Ident::new("shouted_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .formatter(Box::new(ShoutingFormatter))
+      .procout(&quote!{ pub mod shouted_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("shouted_module.rs")).expect("Must write target file");
+    assert!(
+      contents.starts_with(&format!("{}\n// module: shouted_module\n// SHOUTING\n", GENERATED_HEADER)),
+      "Must use the custom formatter after the header: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_infer_imports() {
+    let output_dir = test_output_dir("test_infer_imports");
+    let module_ident = Ident::new("expanded_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .context(Some(quote!{ pub struct Context; }))
+      .infer_imports(true)
+      .procout(
+        &quote!{ pub mod expanded_module { pub type Lookup = HashMap<u8, super::Context>; } },
+        Some(module_ident),
+        output_dir.to_str()
+      );
     
+    let contents = fs::read_to_string(output_dir.join("expanded_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains("pub struct Context;\npub mod expanded_module {\n    use std::collections::HashMap;\n"),
+      "Must write the context first and import what the module uses: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_rustfmt_skip() {
+    let output_dir = test_output_dir("test_rustfmt_skip");
+    let module_ident = Ident::new("table_module", Span::mixed_site());
+    let code_block = quote!{ pub mod table_module { pub const ROWS: [u8; 2] = [1, 2]; } };
+    ProcoutConfig::new().format(Some(true)).rustfmt_skip(true).procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("table_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains(&format!("{}\n", quote!{ #[rustfmt::skip] #code_block })),
+      "Must leave the code as the macro printed it: {}",
+      contents
+    );
+    assert!(contents.contains("fn macro_test() {\n    use table_module::*;\n"), "Must still format the scaffold: {}", contents);
+  }
+  
+  #[test]
+  fn test_feature_gate() {
+    let output_dir = test_output_dir("test_feature_gate");
+    let module_ident = Ident::new("gated_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .feature_gate(Some("dumps"))
+      .procout(&quote!{ pub mod gated_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("gated_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains("// module: gated_module\n#![cfg(feature = \"dumps\")]\n"),
+      "Must gate the whole file, test included, first thing: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_output_file() {
+    let output_dir = test_output_dir("test_output_file");
+    let module_ident = Ident::new("PlacedModule", Span::mixed_site());
+    let target_path = output_dir.join("exactly").join("here.rs");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .output_file(Some(&target_path))
+      .procout(&quote!{ pub mod PlacedModule {} }, Some(module_ident.clone()), None);
+    
+    let contents = fs::read_to_string(&target_path).expect("Must write the exact file");
+    assert!(contents.contains("use PlacedModule::*;"), "Must still import the module: {}", contents);
+    let both = std::panic::catch_unwind(move || {
+      ProcoutConfig::new()
+        .format(Some(true))
+        .output_file(Some(target_path))
+        .procout(&quote!{ pub mod PlacedModule {} }, Some(module_ident), output_dir.to_str())
+    });
+    assert!(both.is_err(), "Must refuse both an output file and an output dir");
+  }
+  
+  #[test]
+  fn test_item_visibility() {
+    let output_dir = test_output_dir("test_item_visibility");
+    let module_ident = Ident::new("narrowed_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .format(Some(true))
+      .item_visibility(ItemVisibility::PubCrate)
+      .procout(&quote!{ pub mod narrowed_module { pub struct Nested; } }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("narrowed_module.rs")).expect("Must write target file");
+    assert!(contents.contains("pub(crate) mod narrowed_module {\n    pub struct Nested;"), "Must narrow the root items: {}", contents);
+  }
+  
+  #[test]
+  fn test_dump_when() {
+    let output_dir = test_output_dir("test_dump_when");
+    let ident = |name: &str| Ident::new(name, Span::mixed_site());
+    let config = ProcoutConfig::new().dump_when(DumpWhen::ParseFails);
+    assert_eq!(config.procout_to_path(&quote!{ pub mod sound_module {} }, Some(ident("sound_module")), Some(&output_dir)), None, "Must skip output that parses");
+    assert!(config.procout_to_path(&quote!{ pub mod broken_module { let } }, Some(ident("broken_module")), Some(&output_dir)).is_some(), "Must dump output that doesn't parse");
+    
+    let config = ProcoutConfig::new().dump_when(DumpWhen::CheckFails);
+    assert_eq!(config.procout_to_path(&quote!{ pub mod checked_module {} }, Some(ident("checked_module")), Some(&output_dir)), None, "Must skip output that compiles");
+    let unchecked = quote!{ pub mod unchecked_module { pub fn missing() -> u8 { missing } } };
+    assert!(config.procout_to_path(&unchecked, Some(ident("unchecked_module")), Some(&output_dir)).is_some(), "Must dump output that doesn't compile");
+    assert!(!output_dir.join("sound_module.rs").exists() && !output_dir.join("checked_module.rs").exists(), "Must not write what's skipped");
+  }
+  
+  #[test]
+  fn test_annotated() {
+    let output_dir = test_output_dir("test_annotated");
+    let module_ident = Ident::new("annotated_module", Span::mixed_site());
+    ProcoutConfig::new().annotated(true).procout(&quote!{ pub mod annotated_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("annotated_module.rs")).expect("Must write target file");
+    let annotated = fs::read_to_string(output_dir.join("annotated_module").join("annotated_module.annotated.rs"))
+      .expect("Must write the annotated copy");
+    assert_eq!(annotated.lines().count(), contents.lines().count(), "Must annotate every line");
+    assert!(annotated.starts_with(&format!("/* 001 */ {}\n", GENERATED_HEADER)), "Must number the lines: {}", annotated);
+  }
+  
+  #[test]
+  fn test_create_dirs() {
+    let output_dir = test_output_dir("test_create_dirs");
+    let module_ident = Ident::new("created_module", Span::mixed_site());
+    let code_block = quote!{ pub mod created_module {} };
+    let create = |create_dirs: CreatePolicy, output_dir: PathBuf| {
+      let module_ident = module_ident.clone();
+      let code_block = code_block.clone();
+      std::panic::catch_unwind(move || {
+        ProcoutConfig::new()
+          .create_dirs(create_dirs)
+          .procout(&code_block, Some(module_ident), output_dir.to_str());
+      }).is_ok()
+    };
+    
+    assert!(!create(CreatePolicy::MustExist, output_dir.clone()), "Must not create a missing dir");
+    assert!(!create(CreatePolicy::ParentMustExist, output_dir.join("typo")), "Must not create a missing parent");
+    assert!(create(CreatePolicy::ParentMustExist, output_dir.clone()), "Must create a dir whose parent exists");
+    assert!(create(CreatePolicy::MustExist, output_dir.clone()), "Must write into an existing dir");
+    assert!(create(CreatePolicy::Recursive, output_dir.join("deep").join("er")), "Must create every missing dir");
+    
+    assert!(create_output_dir(CreatePolicy::Recursive, &output_dir.join("fresh")), "Must report a new dir");
+    assert!(!create_output_dir(CreatePolicy::Recursive, &output_dir.join("fresh")), "Must report an existing dir");
+  }
+  
+  #[test]
+  fn test_stream_template() {
+    let mut streamed = Vec::new();
+    stream_template(
+      &mut streamed,
+      DEFAULT_TEMPLATE,
+      |placeholder| match placeholder {
+        "test_ident" => Some("macro_test".to_string()),
+        "import" => Some("use streamed::*;".to_string()),
+        "attributes" | "prelude" | "test_body" => Some(String::new()),
+        _ => None,
+      },
+      &quote!{ pub mod streamed {} },
+    ).expect("Must stream to a buffer");
+    assert_eq!(
+      String::from_utf8(streamed).expect("Must stream UTF-8"),
+      format!("{}pub mod streamed {{ }}\n#[test]\nfn macro_test() {{\n    use streamed::*;\n}}\n", ALLOWS),
+      "Must stream the code into the scaffold, dropping blank lines"
+    );
+  }
+  
+  #[test]
+  fn test_default_template() {
+    let rendered = render_template(DEFAULT_TEMPLATE, |placeholder| match placeholder {
+      "code_block" => Some("pub mod templated {}".to_string()),
+      "test_ident" => Some("macro_test".to_string()),
+      "import" => Some("use templated::*;".to_string()),
+      "attributes" | "prelude" | "test_body" => Some(String::new()),
+      _ => None,
+    });
+    assert_eq!(
+      rendered,
+      format!("\n{}\npub mod templated {{}}\n#[test]\nfn macro_test() {{\n    use templated::*;\n    \n}}\n", ALLOWS),
+      "Must render the documented scaffold"
+    );
+    assert_eq!(
+      render_template("{a} {b} {unknown} {", |placeholder| match placeholder {
+        "a" => Some("{b}".to_string()),
+        "b" => Some("B".to_string()),
+        _ => None,
+      }),
+      "{b} B {unknown} {",
+      "Must not re-scan values, and must leave unknown placeholders alone"
+    );
+  }
+  
+  #[test]
+  fn test_module_path() {
+    let output_dir = test_output_dir("test_module_path");
+    for (module_path, file_name, import) in [
+      ("single", "single.rs", "use single::*;"),
+      ("outer::Inner", "inner.rs", "use outer::Inner::*;"),
+    ] {
+      let module_path: syn::Path = syn::parse_str(module_path).expect("Must parse module path");
+      ProcoutConfig::new()
+        .format(Some(true))
+        .module_path(Some(module_path))
+        .procout(&quote!{ pub mod outer { pub mod Inner {} } pub mod single {} }, None, output_dir.to_str());
+      let contents = fs::read_to_string(output_dir.join(file_name)).expect("Must name the file after the last segment");
+      assert!(contents.contains(import), "Must import the whole path: {}", contents);
+    }
+    
+    assert!(
+      std::panic::catch_unwind(|| ProcoutConfig::new().module_path(syn::parse_str("outer::<T>::inner").ok())).is_err(),
+      "Must reject generic arguments"
+    );
+  }
+  
+  #[test]
+  fn test_cargo_toml() {
+    let output_dir = test_output_dir("test_cargo_toml");
+    let module_ident = Ident::new("standalone_module", Span::mixed_site());
+    ProcoutConfig::new()
+      .cargo_toml(Some(vec![]))
+      .procout(&quote!{ pub mod standalone_module {} }, Some(module_ident), output_dir.to_str());
+    
+    let manifest = fs::read_to_string(output_dir.join("standalone_module").join("Cargo.toml")).expect("Must write the manifest");
+    assert!(
+      manifest.contains("[[test]]\nname = \"standalone_module\"\npath = \"../standalone_module.rs\"\n"),
+      "Must declare the file as a test target: {}",
+      manifest
+    );
+    assert!(!output_dir.join("Cargo.toml").exists(), "Must keep the manifest out of the output dir itself");
+  }
+  
+  #[test]
+  fn test_max_bytes() {
+    let output_dir = test_output_dir("test_max_bytes");
+    let module_ident = Ident::new("truncated_module", Span::mixed_site());
+    let code_block = quote!{
+      pub mod truncated_module {
+        const A: u8 = 0;
+        const B: u8 = 1;
+        const C: u8 = 2;
+      }
+    };
+    let max_bytes = quote!{ pub mod truncated_module { const A: u8 = 0; } }.to_string().len();
+    ProcoutConfig::new()
+      .format(Some(true))
+      .max_bytes(Some(max_bytes))
+      .procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("truncated_module.rs")).expect("Must write target file");
+    assert!(contents.contains("    const A: u8 = 0;\n}\n"), "Must keep what fits, formatted: {}", contents);
+    assert!(!contents.contains("const B"), "Must omit what doesn't fit: {}", contents);
+    assert!(
+      contents.ends_with("\n// ... truncated by procout (2 items omitted)\n"),
+      "Must end with the marker: {}",
+      contents
+    );
+  }
+  
+  #[cfg(feature = "span_map")]
+  #[test]
+  fn test_span_map() {
+    let output_dir = test_output_dir("test_span_map");
+    let module_ident = Ident::new("mapped_module", Span::mixed_site());
+    let code_block: TokenStream = "pub mod mapped_module {\n  const CUSS: &str = \"SPIT\";\n}".parse().expect("Must lex");
+    ProcoutConfig::new()
+      .span_map(true)
+      .procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let span_map = fs::read_to_string(output_dir.join("mapped_module.map")).expect("Must write the span map");
+    // After the header and any allows
+    let module_line = 3 + ALLOWS.lines().count();
+    #[cfg(not(feature = "json"))]
+    assert!(
+      span_map.starts_with(&format!("{}-{}\tmod mapped_module\t", module_line, module_line + 2)),
+      "Must map the module to its lines: {}",
+      span_map
+    );
+    #[cfg(feature = "json")]
+    assert!(span_map.contains(&format!("\"start_line\": {}", module_line)), "Must map the module to its lines: {}", span_map);
+  }
+  
+  #[cfg(feature = "span_map")]
+  #[test]
+  fn test_line_hints() {
+    let output_dir = test_output_dir("test_line_hints");
+    let module_ident = Ident::new("hinted_module", Span::mixed_site());
+    let code_block: TokenStream = "\n\npub mod hinted_module {}".parse().expect("Must lex");
+    ProcoutConfig::new()
+      .format(Some(true))
+      .line_hints(true)
+      .procout(&code_block, Some(module_ident), output_dir.to_str());
+    
+    let contents = fs::read_to_string(output_dir.join("hinted_module.rs")).expect("Must write target file");
+    assert!(
+      contents.contains(":3:1\npub mod hinted_module {}\n") && contents.contains("// procout-input: "),
+      "Must hint where the module came from: {}",
+      contents
+    );
+  }
+  
+  #[test]
+  fn test_find_manifest() {
+    let mut target_path: PathBuf = env::current_dir().expect("Must identify current dir");
+    target_path.push("tests");
+    target_path.push("test_find_manifest.rs");
     assert_eq!(
-      contents,
-      target_output,
-      "Must write target output to file in tests directory corresponding to module Ident"
+      find_manifest(&target_path),
+      Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")),
+      "Must find this crate's manifest above its tests directory"
     );
   }
 }
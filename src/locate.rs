@@ -0,0 +1,250 @@
+//! Finding where each item of a file is in its text
+//!
+//! Items parsed from text inside a proc macro get their spans from the compiler, which puts them all at the
+//! macro's call site, so their lines and columns say nothing about the text. Instead the text is scanned for the
+//! `;`s and `{ ... }`s that end items at the top level, and each parsed item says how many of those it has.
+use proc_macro2::{
+  Delimiter, TokenTree,
+};
+use quote::{
+  ToTokens,
+};
+use std::{
+  ops::{
+    Range,
+  },
+};
+use syn::{
+  Item,
+};
+
+/// Part of the text that matters for finding items
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Piece {
+  /// Whitespace, a comment that isn't an outer doc comment, or an inner attribute
+  Trivia,
+  /// An opening delimiter
+  Open(u8),
+  /// A closing delimiter
+  Close(u8),
+  /// Anything else, a single `#` and `!` included
+  Other(u8),
+}
+
+/// Cut `source` into [Piece]s with their byte ranges
+/// - Only what can hide a delimiter or a `;` is scanned whole: comments, strings and character literals.
+fn pieces(source: &str) -> Vec<(Piece, Range<usize>)> {
+  let bytes = source.as_bytes();
+  let at = |index: usize| bytes.get(index).copied().unwrap_or_default();
+  let is_ident = |byte: u8| byte == b'_' || byte.is_ascii_alphanumeric() || !byte.is_ascii();
+  let mut pieces = Vec::new();
+  let mut start = 0;
+  while start < bytes.len() {
+    let byte = bytes[start];
+    let (piece, end) = match byte {
+      b'/' if at(start + 1) == b'/' => {
+        let end = source[start..].find('\n').map_or(bytes.len(), |offset| start + offset);
+        let outer_doc = at(start + 2) == b'/' && at(start + 3) != b'/';
+        (if outer_doc { Piece::Other(byte) } else { Piece::Trivia }, end)
+      },
+      b'/' if at(start + 1) == b'*' => {
+        let mut depth = 0;
+        let mut end = start;
+        while end < bytes.len() {
+          match (bytes[end], at(end + 1)) {
+            (b'/', b'*') => { depth += 1; end += 2; },
+            (b'*', b'/') => {
+              depth -= 1;
+              end += 2;
+              if depth == 0 {
+                break;
+              }
+            },
+            _ => end += 1,
+          }
+        }
+        let outer_doc = at(start + 2) == b'*' && !matches!(at(start + 3), b'*' | b'/');
+        (if outer_doc { Piece::Other(byte) } else { Piece::Trivia }, end.min(bytes.len()))
+      },
+      b'"' => (Piece::Other(byte), string_end(bytes, start + 1, byte)),
+      b'\'' => {
+        let mut chars = source[start + 1..].chars();
+        let end = match (chars.next(), chars.next()) {
+          (Some('\\'), _) => string_end(bytes, start + 1, byte),
+          (Some(first), Some('\'')) => start + 1 + first.len_utf8() + 1,
+          // A lifetime or a label, whose name follows
+          _ => start + 1,
+        };
+        (Piece::Other(byte), end)
+      },
+      b'(' | b'[' | b'{' => (Piece::Open(byte), start + 1),
+      b')' | b']' | b'}' => (Piece::Close(byte), start + 1),
+      byte if is_ident(byte) => {
+        let mut end = start;
+        while end < bytes.len() && is_ident(bytes[end]) {
+          end += 1;
+        }
+        let hashes = bytes[end..].iter().take_while(|byte| **byte == b'#').count();
+        match &source[start..end] {
+          "r" | "br" | "cr" if at(end + hashes) == b'"' => (Piece::Other(byte), raw_string_end(bytes, end + hashes + 1, hashes)),
+          _ => (Piece::Other(byte), end),
+        }
+      },
+      byte if byte.is_ascii_whitespace() => (Piece::Trivia, start + 1),
+      byte => (Piece::Other(byte), start + 1),
+    };
+    pieces.push((piece, start..end));
+    start = end;
+  }
+  pieces
+}
+
+/// Where a string or character literal closed by `quote`, whose contents start at `start`, ends
+fn string_end(bytes: &[u8], start: usize, quote: u8) -> usize {
+  let mut end = start;
+  while end < bytes.len() {
+    match bytes[end] {
+      b'\\' => end += 2,
+      byte if byte == quote => return end + 1,
+      _ => end += 1,
+    }
+  }
+  bytes.len()
+}
+
+/// Where a raw string closed by `"` and `hashes` `#`s, whose contents start at `start`, ends
+fn raw_string_end(bytes: &[u8], start: usize, hashes: usize) -> usize {
+  (start..bytes.len())
+    .find(|end| bytes[*end] == b'"' && bytes[end + 1..].iter().take(hashes).filter(|byte| **byte == b'#').count() == hashes)
+    .map_or(bytes.len(), |end| end + 1 + hashes)
+}
+
+/// How many `;`s and `{ ... }`s `item` has at its top level, the last of which ends it
+fn item_ends(item: &Item) -> usize {
+  item.to_token_stream().into_iter()
+    .filter(|token| match token {
+      TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+      TokenTree::Punct(punct) => punct.as_char() == ';',
+      _ => false,
+    })
+    .count()
+}
+
+/// The byte range of each of `items` in `source`, the text they were parsed from, from their first attribute
+/// or doc comment to their last token
+/// - Returns [None] if the text doesn't hold the items, e.g. if they were parsed from other text.
+pub(crate) fn item_ranges(source: &str, items: &[Item]) -> Option<Vec<Range<usize>>> {
+  let mut pieces = pieces(source).into_iter().filter(|(piece, _)| *piece != Piece::Trivia).peekable();
+  // Inner attributes can only come before the items
+  while let Some((Piece::Other(b'#'), _)) = pieces.peek() {
+    let mut lookahead = pieces.clone();
+    lookahead.next();
+    if !matches!(lookahead.next(), Some((Piece::Other(b'!'), _))) || !matches!(lookahead.peek(), Some((Piece::Open(b'['), _))) {
+      break;
+    }
+    pieces = lookahead;
+    let mut depth = 0;
+    for (piece, _) in pieces.by_ref() {
+      match piece {
+        Piece::Open(_) => depth += 1,
+        Piece::Close(_) => depth -= 1,
+        _ => {},
+      }
+      if depth == 0 {
+        break;
+      }
+    }
+  }
+  
+  let mut ranges = Vec::with_capacity(items.len());
+  for item in items {
+    let mut ends = item_ends(item);
+    if ends == 0 {
+      return None;
+    }
+    let (_, first) = pieces.peek()?.clone();
+    let mut depth = 0usize;
+    let mut last = first.clone();
+    while ends > 0 {
+      let (piece, range) = pieces.next()?;
+      match piece {
+        Piece::Open(_) => depth += 1,
+        Piece::Close(delimiter) => {
+          depth = depth.checked_sub(1)?;
+          if depth == 0 && delimiter == b'}' {
+            ends -= 1;
+          }
+        },
+        Piece::Other(b';') if depth == 0 => ends -= 1,
+        _ => {},
+      }
+      last = range;
+    }
+    ranges.push(first.start..last.end);
+  }
+  Some(ranges)
+}
+
+/// The 1-based lines each of `items` starts and ends on in `source`, as [item_ranges] finds them
+pub(crate) fn item_lines(source: &str, items: &[Item]) -> Option<Vec<(usize, usize)>> {
+  let line = |offset: usize| source[..offset].matches('\n').count() + 1;
+  Some(item_ranges(source, items)?.into_iter().map(|range| (line(range.start), line(range.end - 1))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    parse::{
+      respan,
+    },
+  };
+  use proc_macro2::{
+    Span,
+  };
+  
+  /// Parse `source`, then give every token the call site span, as the compiler does inside a proc macro
+  fn parse_at_call_site(source: &str) -> Vec<Item> {
+    let file = syn::parse_file(source).expect("Must parse");
+    syn::parse2::<syn::File>(respan(file.into_token_stream(), Span::call_site())).expect("Must parse again").items
+  }
+  
+  #[test]
+  fn test_item_ranges() {
+    let source = concat!(
+      "#![allow(dead_code)]\n",
+      "//! Inner docs\n",
+      "// @generated\n",
+      "/// Outer docs\n",
+      "#[derive(Debug)]\n",
+      "struct Unit;\n",
+      "const BRACED: Braced = Braced { text: \"};\", chars: ['}', '\\''], raw: r#\"\"}\"#, life: PhantomData::<&'static ()> };\n",
+      "/* block { */ use outer::{inner, other};\n",
+      "impl Unit {\n    fn f() -> [u8; 2] { [1; 2] }\n}\n",
+      "macro_rules! m { () => {}; }\n",
+      "m!();\n",
+      "// trailing\n",
+    );
+    let items = parse_at_call_site(source);
+    let ranges = item_ranges(source, &items).expect("Must find the items");
+    let texts: Vec<&str> = ranges.iter().map(|range| &source[range.clone()]).collect();
+    assert_eq!(
+      texts,
+      vec![
+        "/// Outer docs\n#[derive(Debug)]\nstruct Unit;",
+        "const BRACED: Braced = Braced { text: \"};\", chars: ['}', '\\''], raw: r#\"\"}\"#, life: PhantomData::<&'static ()> };",
+        "use outer::{inner, other};",
+        "impl Unit {\n    fn f() -> [u8; 2] { [1; 2] }\n}",
+        "macro_rules! m { () => {}; }",
+        "m!();",
+      ],
+      "Must find each item from the text alone, whatever the spans say"
+    );
+    assert_eq!(
+      item_lines(source, &items).expect("Must find the items"),
+      vec![(4, 6), (7, 7), (8, 8), (9, 11), (12, 12), (13, 13)],
+      "Must find each item's lines"
+    );
+    assert_eq!(item_ranges("mod other;", &items), None, "Must give up on text that doesn't hold the items");
+  }
+}
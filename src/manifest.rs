@@ -0,0 +1,73 @@
+//! A `Cargo.toml` that lets a dump compile outside the crate that produced it
+use std::{
+  fmt::{
+    Write,
+  },
+};
+
+/// Render a manifest with one `[[test]]` target for the file at `test_path`, relative to the manifest, and the 
+/// given dependencies
+/// - `deps` are `(name, version)` pairs. A version that starts with `{` is written as an inline table,
+///   e.g. `{ version = "1", features = ["full"] }`.
+/// - The empty `[workspace]` keeps cargo from treating it as part of an enclosing workspace.
+pub(crate) fn render_manifest(package_name: &str, test_path: &str, deps: &[(String, String)]) -> String {
+  let mut manifest = format!(
+    "# @generated by procout\n\
+    [package]\n\
+    name = {:?}\n\
+    version = \"0.0.0\"\n\
+    edition = \"2021\"\n\
+    publish = false\n\
+    \n\
+    [workspace]\n\
+    \n\
+    [[test]]\n\
+    name = {:?}\n\
+    path = {:?}\n\
+    \n\
+    [dependencies]\n",
+    package_name.replace('_', "-"),
+    package_name,
+    test_path,
+  );
+  for (name, version) in deps {
+    if version.trim_start().starts_with('{') {
+      writeln!(manifest, "{} = {}", name, version).expect("Writes to string");
+    } else {
+      writeln!(manifest, "{} = {:?}", name, version).expect("Writes to string");
+    }
+  }
+  manifest
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_render_manifest() {
+    let deps = vec![
+      ("quote".to_string(), "1".to_string()),
+      ("syn".to_string(), "{ version = \"1\", features = [\"full\"] }".to_string()),
+    ];
+    assert_eq!(
+      render_manifest("standalone_module", "../standalone_module.rs", &deps),
+      "# @generated by procout\n\
+      [package]\n\
+      name = \"standalone-module\"\n\
+      version = \"0.0.0\"\n\
+      edition = \"2021\"\n\
+      publish = false\n\
+      \n\
+      [workspace]\n\
+      \n\
+      [[test]]\n\
+      name = \"standalone_module\"\n\
+      path = \"../standalone_module.rs\"\n\
+      \n\
+      [dependencies]\n\
+      quote = \"1\"\n\
+      syn = { version = \"1\", features = [\"full\"] }\n",
+      "Must declare the file as a test target with its dependencies"
+    );
+  }
+}
@@ -0,0 +1,32 @@
+//! Writing the output as a doctest in a markdown file
+use crate::{
+  dumps::{
+    GENERATED_HEADER, MODULE_HEADER_PREFIX,
+  },
+};
+
+/// Render `source` as a fenced `rust` block that `cargo test --doc` checks, ending in the hidden `import`
+/// - The provenance header is written as HTML comments, which markdown doesn't show.
+pub(crate) fn render_markdown(module_name: &str, source: &str, import: &str) -> String {
+  format!(
+    "<!-- {} -->\n<!-- {}{} -->\n\n```rust\n{}\n# {}\n```\n",
+    GENERATED_HEADER.trim_start_matches("// "),
+    MODULE_HEADER_PREFIX.trim_start_matches("// "),
+    module_name,
+    source.trim_end(),
+    import,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_render_markdown() {
+    assert_eq!(
+      render_markdown("documented", "pub mod documented {}\n", "use documented::*;"),
+      "<!-- @generated by procout -->\n<!-- module: documented -->\n\n```rust\npub mod documented {}\n# use documented::*;\n```\n",
+      "Must fence the code with the import hidden"
+    );
+  }
+}
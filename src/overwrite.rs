@@ -0,0 +1,81 @@
+//! Leaving a file that's rewritten with the same contents looking untouched
+use std::{
+  fs::{
+    self, File,
+  },
+  path::{
+    PathBuf,
+  },
+  time::{
+    SystemTime,
+  },
+};
+use crate::{
+  Stream,
+};
+
+/// Restores a file's modification time when it's dropped, if its contents ended up as they were
+/// - Everything procout does to a file, formatting included, happens before it's dropped, so the 
+///   comparison is with the final contents.
+pub(crate) struct UnchangedGuard {
+  path: PathBuf,
+  previous: Option<(String, SystemTime)>,
+  stream: Stream,
+  notifies: bool,
+}
+
+impl UnchangedGuard {
+  /// Remember the contents and modification time of the file at `path`, if there is one, reporting to `stream`
+  /// - An unchanged file is only reported if it `notifies`, but failing to keep its modification time always is.
+  pub(crate) fn new(path: PathBuf, stream: Stream, notifies: bool) -> Self {
+    let previous = fs::read_to_string(&path).ok()
+      .and_then(|contents| Some((contents, fs::metadata(&path).ok()?.modified().ok()?)));
+    Self { path, previous, stream, notifies }
+  }
+}
+
+impl Drop for UnchangedGuard {
+  fn drop(&mut self) {
+    if let Some((previous_contents, previous_modified)) = &self.previous {
+      if fs::read_to_string(&self.path).ok().as_ref() == Some(previous_contents) {
+        let restored = File::options()
+          .write(true)
+          .open(&self.path)
+          .and_then(|file| file.set_modified(*previous_modified));
+        match restored {
+          Ok(()) if !self.notifies => {},
+          Ok(()) => self.stream.print(format_args!("Macro output `{}` is unchanged", self.path.display())),
+          Err(err) => self.stream.print(format_args!("Could not keep the modification time of `{}`:\n {:#?}", self.path.display(), err)),
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    env,
+  };
+  #[test]
+  fn test_unchanged_guard() {
+    let output_dir = env::temp_dir().join("procout_tests").join("test_unchanged_guard");
+    let _ = fs::remove_dir_all(&output_dir);
+    fs::create_dir_all(&output_dir).expect("Must create output dir");
+    let path = output_dir.join("guarded.rs");
+    fs::write(&path, "fn guarded() {}\n").expect("Must write file");
+    let old_modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    File::options().write(true).open(&path).and_then(|file| file.set_modified(old_modified)).expect("Must age file");
+    
+    let guard = UnchangedGuard::new(path.clone(), Stream::default(), true);
+    fs::write(&path, "fn guarded() {}\n").expect("Must rewrite file");
+    drop(guard);
+    assert_eq!(fs::metadata(&path).and_then(|metadata| metadata.modified()).ok(), Some(old_modified), "Must restore an unchanged file's time");
+    
+    let guard = UnchangedGuard::new(path.clone(), Stream::default(), true);
+    fs::write(&path, "fn changed() {}\n").expect("Must rewrite file");
+    drop(guard);
+    assert_ne!(fs::metadata(&path).and_then(|metadata| metadata.modified()).ok(), Some(old_modified), "Must leave a changed file's time");
+  }
+}
@@ -0,0 +1,327 @@
+//! Parsing the output of the macro being debugged
+use proc_macro2::{
+  Group, Span, TokenStream, TokenTree,
+};
+use std::{
+  collections::{
+    HashMap,
+  },
+};
+use quote::{
+  quote, ToTokens,
+};
+use syn::{
+  parse::{
+    Parser,
+  },
+  Attribute, Block, Expr, File, Item, Visibility,
+};
+
+/// The name of the function a fragment is wrapped in so it can be written as a file
+pub(crate) static FRAGMENT_FN: &str = "_procout_fragment";
+
+/// What a macro output that isn't a whole file turned out to be
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FragmentKind {
+  /// A braced block, `{ ... }`
+  Block,
+  /// An expression
+  Expr,
+  /// Statements, possibly ending in an expression
+  Statements,
+}
+
+impl FragmentKind {
+  /// The name recorded in the file header
+  pub(crate) fn name(&self) -> &'static str {
+    match self {
+      FragmentKind::Block => "block",
+      FragmentKind::Expr => "expression",
+      FragmentKind::Statements => "statements",
+    }
+  }
+}
+
+/// Detect output that doesn't parse as a [File] but does as a [Block], an [Expr] or statements, in that order, 
+/// and wrap it in a `fn` so it can be written, parsed and formatted like any other output
+/// - A lone item already parses as a [File], so it isn't a fragment.
+/// - The fragment is evaluated into `let _ = { ... };` so whatever its type the function compiles.
+pub(crate) fn wrap_fragment(code_block: &TokenStream) -> Option<(FragmentKind, TokenStream)> {
+  let kind = if syn::parse2::<Block>(code_block.clone()).is_ok() {
+    FragmentKind::Block
+  } else if syn::parse2::<Expr>(code_block.clone()).is_ok() {
+    FragmentKind::Expr
+  } else if Block::parse_within.parse2(code_block.clone()).is_ok() {
+    FragmentKind::Statements
+  } else {
+    return None;
+  };
+  let fragment_fn = quote::format_ident!("{}", FRAGMENT_FN);
+  Some((kind, quote!{
+    fn #fragment_fn() {
+      let _ = { #code_block };
+    }
+  }))
+}
+
+/// Put `#[rustfmt::skip]` on each item at the root of `file`, so `rustfmt` leaves the items as they are while 
+/// still formatting the rest of the file
+/// - Items that can't take attributes, which syn keeps as raw tokens, are left alone.
+pub(crate) fn mark_rustfmt_skip(file: &mut File) {
+  let skip: Attribute = syn::parse_quote!(#[rustfmt::skip]);
+  for item in file.items.iter_mut() {
+    let attrs = match item {
+      Item::Const(item) => &mut item.attrs,
+      Item::Enum(item) => &mut item.attrs,
+      Item::ExternCrate(item) => &mut item.attrs,
+      Item::Fn(item) => &mut item.attrs,
+      Item::ForeignMod(item) => &mut item.attrs,
+      Item::Impl(item) => &mut item.attrs,
+      Item::Macro(item) => &mut item.attrs,
+      Item::Macro2(item) => &mut item.attrs,
+      Item::Mod(item) => &mut item.attrs,
+      Item::Static(item) => &mut item.attrs,
+      Item::Struct(item) => &mut item.attrs,
+      Item::Trait(item) => &mut item.attrs,
+      Item::TraitAlias(item) => &mut item.attrs,
+      Item::Type(item) => &mut item.attrs,
+      Item::Union(item) => &mut item.attrs,
+      Item::Use(item) => &mut item.attrs,
+      _ => continue,
+    };
+    if !attrs.iter().any(|attr| attr.path.to_token_stream().to_string() == skip.path.to_token_stream().to_string()) {
+      attrs.insert(0, skip.clone());
+    }
+  }
+}
+
+/// Give each `pub` item at the root of `file` the narrower `visibility` instead
+/// - Items without a visibility of their own, like `impl`s, and items that aren't `pub` are left alone.
+pub(crate) fn restrict_visibility(file: &mut File, visibility: &Visibility) {
+  for item in file.items.iter_mut() {
+    let vis = match item {
+      Item::Const(item) => &mut item.vis,
+      Item::Enum(item) => &mut item.vis,
+      Item::ExternCrate(item) => &mut item.vis,
+      Item::Fn(item) => &mut item.vis,
+      Item::Macro2(item) => &mut item.vis,
+      Item::Mod(item) => &mut item.vis,
+      Item::Static(item) => &mut item.vis,
+      Item::Struct(item) => &mut item.vis,
+      Item::Trait(item) => &mut item.vis,
+      Item::TraitAlias(item) => &mut item.vis,
+      Item::Type(item) => &mut item.vis,
+      Item::Union(item) => &mut item.vis,
+      Item::Use(item) => &mut item.vis,
+      _ => continue,
+    };
+    if let Visibility::Public(_) = vis {
+      *vis = visibility.clone();
+    }
+  }
+}
+
+/// How deeply the groups of `code_block` nest, counted without recursing so any depth can be measured
+/// - Tokens outside any group are at depth `0`, and those inside `( ... )` at the root at depth `1`.
+pub(crate) fn nesting_depth(code_block: &TokenStream) -> usize {
+  let mut deepest = 0;
+  let mut pending = vec![(code_block.clone(), 0)];
+  while let Some((tokens, depth)) = pending.pop() {
+    deepest = deepest.max(depth);
+    for token in tokens {
+      if let TokenTree::Group(group) = token {
+        pending.push((group.stream(), depth + 1));
+      }
+    }
+  }
+  deepest
+}
+
+/// Memoizes parsed [File]s keyed by the string representation of their [TokenStream]
+/// - One cache belongs to one call to [crate::ProcoutConfig::procout] and is dropped with it, 
+///   so parses never leak between unrelated calls.
+/// - Everything in a call that needs the parsed output goes through here, so it's parsed at most once.
+/// - Output nested deeper than the `max_depth` isn't parsed at all, since `syn` parses it recursively and could 
+///   overflow the stack. It fails to parse instead.
+#[derive(Default)]
+pub(crate) struct ParseCache {
+  files: HashMap<String, Result<File, syn::Error>>,
+  max_depth: Option<usize>,
+}
+
+impl ParseCache {
+  /// Start an empty cache that won't parse output nested deeper than `max_depth`
+  pub(crate) fn new(max_depth: Option<usize>) -> Self {
+    Self { files: HashMap::new(), max_depth }
+  }
+  
+  /// Parse `code_block` as a [File], or return the result of parsing it earlier in this call
+  pub(crate) fn parse_file(&mut self, code_block: &TokenStream) -> Result<&File, &syn::Error> {
+    let max_depth = self.max_depth;
+    self.files
+      .entry(code_block.to_string())
+      .or_insert_with(|| match max_depth {
+        Some(max_depth) if nesting_depth(code_block) > max_depth => Err(syn::Error::new(
+          Span::call_site(),
+          format!("the groups nest deeper than the max depth of {}, so it isn't parsed", max_depth),
+        )),
+        _ => syn::parse2(code_block.clone()),
+      })
+      .as_ref()
+  }
+}
+
+/// The kind of an item and its name, if it has one
+pub(crate) fn describe_item(item: &Item) -> (&'static str, Option<String>) {
+  let (kind, ident) = match item {
+    Item::Const(item) => ("const", Some(&item.ident)),
+    Item::Enum(item) => ("enum", Some(&item.ident)),
+    Item::ExternCrate(item) => ("extern crate", Some(&item.ident)),
+    Item::Fn(item) => ("fn", Some(&item.sig.ident)),
+    Item::ForeignMod(_) => ("extern", None),
+    Item::Impl(_) => ("impl", None),
+    Item::Macro(item) => ("macro", item.ident.as_ref()),
+    Item::Macro2(item) => ("macro", Some(&item.ident)),
+    Item::Mod(item) => ("mod", Some(&item.ident)),
+    Item::Static(item) => ("static", Some(&item.ident)),
+    Item::Struct(item) => ("struct", Some(&item.ident)),
+    Item::Trait(item) => ("trait", Some(&item.ident)),
+    Item::TraitAlias(item) => ("trait", Some(&item.ident)),
+    Item::Type(item) => ("type", Some(&item.ident)),
+    Item::Union(item) => ("union", Some(&item.ident)),
+    Item::Use(_) => ("use", None),
+    _ => ("item", None),
+  };
+  (kind, ident.map(ToString::to_string))
+}
+
+/// Give every token of `tokens`, nested ones included, the span `span`, see [crate::ProcoutConfig::call_site_spans]
+pub(crate) fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+  tokens.into_iter()
+    .map(|token| match token {
+      TokenTree::Group(group) => {
+        let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+        respanned.set_span(span);
+        TokenTree::Group(respanned)
+      },
+      mut token => {
+        token.set_span(span);
+        token
+      },
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_nesting_depth() {
+    assert_eq!(nesting_depth(&quote!{ const FLAT: u8 = 1; }), 0, "Must count no groups as depth 0");
+    assert_eq!(nesting_depth(&quote!{ fn nested() { let _ = [(1), ((2))]; } }), 4, "Must find the deepest group");
+    let mut deep = quote!{ 1 };
+    for _ in 0..10_000 {
+      deep = quote!{ (#deep) };
+    }
+    assert_eq!(nesting_depth(&deep), 10_000, "Must measure any depth without overflowing");
+    
+    let mut parse_cache = ParseCache::new(Some(3));
+    assert!(parse_cache.parse_file(&quote!{ const SHALLOW: u8 = ((1)); }).is_ok(), "Must parse within the max depth");
+    assert!(parse_cache.parse_file(&quote!{ const DEEP: u8 = ((((1)))); }).is_err(), "Must not parse beyond the max depth");
+  }
+  
+  #[test]
+  fn test_parse_cache() {
+    let code_block = quote!{ pub mod cached { const CUSS: &str = "SPIT"; } };
+    let mut parse_cache = ParseCache::default();
+    assert!(parse_cache.parse_file(&code_block).is_ok(), "Must parse a valid file");
+    assert!(parse_cache.parse_file(&code_block.clone()).is_ok(), "Must parse an identical stream");
+    assert_eq!(parse_cache.files.len(), 1, "Must parse identical streams only once");
+    
+    let broken_block = quote!{ pub mod broken { const } };
+    assert!(parse_cache.parse_file(&broken_block).is_err(), "Must remember failed parses too");
+    assert_eq!(parse_cache.files.len(), 2, "Must key streams by their string representation");
+  }
+  
+  #[test]
+  fn test_mark_rustfmt_skip() {
+    let mut file: File = syn::parse2(quote!{
+      pub mod table { pub const ROWS: [u8; 2] = [1, 2]; }
+      #[rustfmt::skip] fn already() {}
+    }).expect("Must parse the file");
+    mark_rustfmt_skip(&mut file);
+    assert_eq!(
+      file.into_token_stream().to_string(),
+      quote!{
+        #[rustfmt::skip] pub mod table { pub const ROWS: [u8; 2] = [1, 2]; }
+        #[rustfmt::skip] fn already() {}
+      }.to_string(),
+      "Must mark each root item once"
+    );
+  }
+  
+  #[test]
+  fn test_restrict_visibility() {
+    let mut file: File = syn::parse2(quote!{
+      pub mod restricted { pub struct Nested; }
+      pub(crate) fn already() {}
+      fn private() {}
+      impl Restricted {}
+    }).expect("Must parse the file");
+    restrict_visibility(&mut file, &syn::parse_quote!(pub(super)));
+    assert_eq!(
+      file.into_token_stream().to_string(),
+      quote!{
+        pub(super) mod restricted { pub struct Nested; }
+        pub(crate) fn already() {}
+        fn private() {}
+        impl Restricted {}
+      }.to_string(),
+      "Must only restrict the root items that are pub"
+    );
+  }
+  
+  #[test]
+  fn test_wrap_fragment() {
+    let kinds: Vec<Option<FragmentKind>> = [
+      quote!{ { let x = 1; x } },
+      quote!{ 1 + 2 },
+      quote!{ let x = 1; x + 1 },
+      quote!{ let = ; },
+    ].iter()
+      .map(|code_block| wrap_fragment(code_block).map(|(kind, _)| kind))
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![Some(FragmentKind::Block), Some(FragmentKind::Expr), Some(FragmentKind::Statements), None],
+      "Must detect each kind of fragment, leaving garbage alone"
+    );
+    let (_, wrapped) = wrap_fragment(&quote!{ 1 + 2 }).expect("Must wrap an expression");
+    assert!(syn::parse2::<File>(wrapped).is_ok(), "Must wrap a fragment into a file");
+  }
+  
+  #[test]
+  fn test_respan() {
+    let tokens: TokenStream = "use spanned::{ inner::* };".parse().expect("Must parse tokens");
+    let call_site = format!("{:?}", Span::call_site());
+    fn spans(tokens: TokenStream, found: &mut Vec<String>) {
+      for token in tokens {
+        found.push(format!("{:?}", token.span()));
+        if let TokenTree::Group(group) = token {
+          spans(group.stream(), found);
+        }
+      }
+    }
+    // Spans only tell apart where they're from when they have locations
+    #[cfg(feature = "span_map")]
+    {
+      let mut found = Vec::new();
+      spans(tokens.clone(), &mut found);
+      assert!(found.iter().any(|span| span != &call_site), "Must start with spans from the source");
+    }
+    let mut found = Vec::new();
+    spans(respan(tokens.clone(), Span::call_site()), &mut found);
+    assert!(found.iter().all(|span| span == &call_site), "Must respan every token, nested ones included: {:?}", found);
+    assert_eq!(respan(tokens.clone(), Span::call_site()).to_string(), tokens.to_string(), "Must keep the tokens");
+  }
+}
@@ -0,0 +1,144 @@
+//! Project-wide settings read once per process
+use std::{
+  env,
+  ffi::{
+    OsString,
+  },
+  fs,
+  path::{
+    Path, PathBuf,
+  },
+  process::{
+    Command,
+  },
+  sync::{
+    OnceLock,
+  },
+};
+
+/// The name of the project config file looked for in the project root
+pub(crate) static CONFIG_FILE: &str = "procout.toml";
+
+/// The env var naming a file to use as the header instead of the one in [CONFIG_FILE]
+pub(crate) static HEADER_VAR: &str = "PROCOUT_HEADER";
+
+/// The prefix of the header line recording the commit, see [crate::ProcoutConfig::git_commit]
+pub(crate) static COMMIT_HEADER_PREFIX: &str = "// commit: ";
+
+static PROJECT_HEADER: OnceLock<Option<String>> = OnceLock::new();
+
+static GIT_COMMIT: OnceLock<Option<String>> = OnceLock::new();
+
+/// The project root, `CARGO_MANIFEST_DIR` or else the current dir
+fn project_root() -> PathBuf {
+  env::var_os("CARGO_MANIFEST_DIR")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| env::current_dir().expect("Must identify current dir"))
+}
+
+/// The project's header comment block, read the first time it's needed
+pub(crate) fn project_header() -> Option<&'static str> {
+  PROJECT_HEADER
+    .get_or_init(|| load_header(env::var_os(HEADER_VAR), &project_root()))
+    .as_deref()
+}
+
+/// The commit the project is checked out at, asked of `git` the first time it's needed
+pub(crate) fn git_commit() -> Option<&'static str> {
+  GIT_COMMIT
+    .get_or_init(|| load_git_commit(&project_root()))
+    .as_deref()
+}
+
+/// Ask `git` for the commit checked out at `project_root`
+/// - `None` if it isn't a git repo, or `git` isn't installed, so there's just no commit in the header.
+pub(crate) fn load_git_commit(project_root: &Path) -> Option<String> {
+  let output = Command::new("git")
+    .arg("rev-parse")
+    .arg("HEAD")
+    .current_dir(project_root)
+    .output()
+    .ok()?;
+  let commit = String::from_utf8(output.stdout).ok()?;
+  let commit = commit.trim();
+  (output.status.success() && !commit.is_empty()).then(|| commit.to_string())
+}
+
+/// Load the header from the file named by `header_var`, or else from the `header` key of [CONFIG_FILE] in 
+/// `project_root`, as a comment block
+/// - Panics if either exists but can't be read, so a broken config isn't silently ignored.
+pub(crate) fn load_header(header_var: Option<OsString>, project_root: &Path) -> Option<String> {
+  let header = match header_var {
+    Some(header_path) => fs::read_to_string(&header_path)
+      .unwrap_or_else(|err| panic!("{} file `{}` must be readable: {}", HEADER_VAR, Path::new(&header_path).display(), err)),
+    None => {
+      let config_path = project_root.join(CONFIG_FILE);
+      let config = fs::read_to_string(&config_path).ok()?;
+      let config: toml::Table = config.parse()
+        .unwrap_or_else(|err| panic!("`{}` must be valid TOML: {}", config_path.display(), err));
+      match config.get("header") {
+        Some(toml::Value::String(header)) => header.clone(),
+        Some(_) => panic!("`header` in `{}` must be a string", config_path.display()),
+        None => return None,
+      }
+    },
+  };
+  Some(render_comment_block(&header))
+}
+
+/// Turn each line into a `//` comment, leaving lines that already are one alone
+fn render_comment_block(text: &str) -> String {
+  text.lines()
+    .map(|line| {
+      if line.trim_start().starts_with("//") {
+        format!("{}\n", line)
+      } else if line.is_empty() {
+        "//\n".to_string()
+      } else {
+        format!("// {}\n", line)
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_load_header() {
+    let project_root = env::temp_dir().join("procout_tests").join("test_load_header");
+    let _ = fs::remove_dir_all(&project_root);
+    fs::create_dir_all(&project_root).expect("Must create project root");
+    assert_eq!(load_header(None, &project_root), None, "Must do without a config file");
+    
+    fs::write(project_root.join(CONFIG_FILE), "header = \"\"\"\nCopyright Blah\n\n// SPDX-License-Identifier: MIT\n\"\"\"\n")
+      .expect("Must write config file");
+    assert_eq!(
+      load_header(None, &project_root).as_deref(),
+      Some("// Copyright Blah\n//\n// SPDX-License-Identifier: MIT\n"),
+      "Must read the header from the config file as a comment block"
+    );
+    
+    let header_path = project_root.join("HEADER");
+    fs::write(&header_path, "Overridden\n").expect("Must write header file");
+    assert_eq!(
+      load_header(Some(header_path.into_os_string()), &project_root).as_deref(),
+      Some("// Overridden\n"),
+      "Must prefer the file named by the env var"
+    );
+  }
+  
+  #[test]
+  fn test_load_git_commit() {
+    let commit = load_git_commit(Path::new(env!("CARGO_MANIFEST_DIR")));
+    if let Some(commit) = commit {
+      assert!(commit.chars().all(|c| c.is_ascii_hexdigit()), "Must read a commit hash: {}", commit);
+    }
+    
+    let project_root = env::temp_dir().join("procout_tests").join("test_load_git_commit");
+    let _ = fs::remove_dir_all(&project_root);
+    fs::create_dir_all(&project_root).expect("Must create project root");
+    fs::write(project_root.join(".git"), "gitdir: nowhere\n").expect("Must write broken git file");
+    assert_eq!(load_git_commit(&project_root), None, "Must do without a git repo");
+  }
+}
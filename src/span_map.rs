@@ -0,0 +1,204 @@
+//! A sidecar mapping each item's lines in the written file back to where its tokens came from
+//!
+//! `<name>.map` has one tab-separated line per item of the code, in order:
+//!
+//! ```text
+//! <first line>-<last line>  <kind> <ident>  <input file>:<line>:<column>-<line>:<column>
+//! ```
+//!
+//! - Lines are 1-based and columns 0-based, following [proc_macro2::LineColumn].
+//! - Input locations come from the spans of the macro's tokens. Inside a proc macro, compilers that can't
+//!   report span locations give line `0`, and tokens built by `quote!` point at the macro's call site.
+//! - With the `json` feature, the same information is written as JSON instead:
+//!   `{"file": <name>, "items": [{"kind", "ident", "output": {"start_line", "end_line"}, "input": {"file", "start": {"line", "column"}, "end": {..}}}]}`
+//!
+//! The same locations can instead be written into the file as a `// procout-input: <file>:<line>:<column>` 
+//! comment above each item, see [crate::ProcoutConfig::line_hints]. What the compiler tells a proc macro 
+//! about its input depends on the channel:
+//! - Lines and columns are reported on stable from Rust 1.88, and on nightly before that.
+//! - The file is reported on stable from Rust 1.88 too, and otherwise only as `<token stream>`.
+//! - Older stable compilers report line `0`, so no hints are written, and the span map is all zeroes.
+use proc_macro2::{
+  LineColumn, Span, TokenStream,
+};
+use quote::{
+  ToTokens,
+};
+use syn::{
+  Item,
+};
+use crate::{
+  parse::{
+    describe_item,
+  },
+};
+
+/// Where one item of the code ended up, and where it came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SpanMapping {
+  pub(crate) kind: &'static str,
+  pub(crate) ident: Option<String>,
+  pub(crate) output_lines: (usize, usize),
+  pub(crate) input_file: String,
+  pub(crate) input_start: LineColumn,
+  pub(crate) input_end: LineColumn,
+}
+
+/// Pair the items of the code with the first and last lines of the items the written file holds, `output_lines`
+/// - The output lines come from the file's text, since the spans of items parsed back from it inside a proc macro 
+///   don't point into it, see [crate::locate::item_lines].
+pub(crate) fn map_items(input_items: &[Item], output_lines: &[(usize, usize)]) -> Vec<SpanMapping> {
+  input_items.iter()
+    .zip(output_lines)
+    .map(|(input_item, output_lines)| {
+      let (kind, ident) = describe_item(input_item);
+      let (input_first, input_last) = outer_spans(input_item.to_token_stream());
+      SpanMapping {
+        kind,
+        ident,
+        output_lines: *output_lines,
+        input_file: input_first.file(),
+        input_start: input_first.start(),
+        input_end: input_last.end(),
+      }
+    })
+    .collect()
+}
+
+/// The spans of the first and last tokens, which unlike a joined span are also reported on stable
+fn outer_spans(tokens: TokenStream) -> (Span, Span) {
+  let mut tokens = tokens.into_iter();
+  let first = tokens.next().map_or_else(Span::call_site, |token| token.span());
+  let last = tokens.last().map_or(first, |token| token.span());
+  (first, last)
+}
+
+/// The prefix of each line hint comment
+pub(crate) static LINE_HINT_PREFIX: &str = "// procout-input: ";
+
+/// Insert a comment above each mapped item of `source` recording where its tokens came from, with a 1-based 
+/// column as the compiler prints them, so an error in the file leads straight back to the macro input
+/// - Items the compiler gave no location for, with line `0`, get no hint.
+pub(crate) fn insert_line_hints(source: &str, mappings: &[SpanMapping]) -> String {
+  let mut hinted = String::with_capacity(source.len());
+  for (index, line) in source.split_inclusive('\n').enumerate() {
+    for mapping in mappings.iter().filter(|mapping| mapping.output_lines.0 == index + 1 && mapping.input_start.line > 0) {
+      let indent = &line[..line.len() - line.trim_start().len()];
+      hinted.push_str(&format!(
+        "{}{}{}:{}:{}\n",
+        indent, LINE_HINT_PREFIX, mapping.input_file, mapping.input_start.line, mapping.input_start.column + 1,
+      ));
+    }
+    hinted.push_str(line);
+  }
+  hinted
+}
+
+/// Render the mappings in the format described above
+#[cfg(not(feature = "json"))]
+pub(crate) fn render_span_map(_file_name: &str, mappings: &[SpanMapping]) -> String {
+  mappings.iter()
+    .map(|mapping| format!(
+      "{}-{}\t{}{}\t{}:{}:{}-{}:{}\n",
+      mapping.output_lines.0,
+      mapping.output_lines.1,
+      mapping.kind,
+      mapping.ident.as_ref().map(|ident| format!(" {}", ident)).unwrap_or_default(),
+      mapping.input_file,
+      mapping.input_start.line,
+      mapping.input_start.column,
+      mapping.input_end.line,
+      mapping.input_end.column,
+    ))
+    .collect()
+}
+
+/// Render the mappings as the JSON described above
+#[cfg(feature = "json")]
+pub(crate) fn render_span_map(file_name: &str, mappings: &[SpanMapping]) -> String {
+  use serde_json::{json};
+  let line_column = |line_column: LineColumn| json!({ "line": line_column.line, "column": line_column.column });
+  let items: Vec<serde_json::Value> = mappings.iter()
+    .map(|mapping| json!({
+      "kind": mapping.kind,
+      "ident": mapping.ident,
+      "output": { "start_line": mapping.output_lines.0, "end_line": mapping.output_lines.1 },
+      "input": {
+        "file": mapping.input_file,
+        "start": line_column(mapping.input_start),
+        "end": line_column(mapping.input_end),
+      },
+    }))
+    .collect();
+  let mut rendered = serde_json::to_string_pretty(&json!({ "file": file_name, "items": items }))
+    .expect("Span map must serialize");
+  rendered.push('\n');
+  rendered
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    locate::{
+      item_lines,
+    },
+    parse::{
+      respan,
+    },
+  };
+  #[test]
+  fn test_map_items() {
+    let input: syn::File = syn::parse_str("pub mod mapped {\n  const CUSS: &str = \"SPIT\";\n}\n\nimpl Mapped {}\n")
+      .expect("Must parse input");
+    let output_source = "// header\npub mod mapped {\n    const CUSS: &str = \"SPIT\";\n}\nimpl Mapped {}\n";
+    // Parsed back inside a proc macro, the output's items all have the call site's span
+    let output: syn::File = syn::parse2(respan(output_source.parse().expect("Must lex output"), Span::call_site()))
+      .expect("Must parse output");
+    let mappings = map_items(&input.items, &item_lines(output_source, &output.items).expect("Must find the output items"));
+    assert_eq!(
+      mappings.iter().map(|mapping| (mapping.kind, mapping.ident.clone(), mapping.output_lines)).collect::<Vec<_>>(),
+      vec![("mod", Some("mapped".to_string()), (2, 4)), ("impl", None, (5, 5))],
+      "Must find each item's lines in the output"
+    );
+    assert_eq!(
+      (mappings[0].input_start, mappings[0].input_end),
+      (LineColumn { line: 1, column: 0 }, LineColumn { line: 3, column: 1 }),
+      "Must record where each item's tokens came from"
+    );
+    assert_eq!(
+      (mappings[1].input_start, mappings[1].input_end),
+      (LineColumn { line: 5, column: 0 }, LineColumn { line: 5, column: 14 }),
+      "Must record where each item's tokens came from"
+    );
+  }
+  
+  #[test]
+  fn test_insert_line_hints() {
+    let mapping = |output_line: usize, input_line: usize| SpanMapping {
+      kind: "mod",
+      ident: None,
+      output_lines: (output_line, output_line),
+      input_file: "src/lib.rs".to_string(),
+      input_start: LineColumn { line: input_line, column: 4 },
+      input_end: LineColumn { line: input_line, column: 20 },
+    };
+    assert_eq!(
+      insert_line_hints("// header\nmod hinted {}\nmod unknown {}\n", &[mapping(2, 12), mapping(3, 0)]),
+      "// header\n// procout-input: src/lib.rs:12:5\nmod hinted {}\nmod unknown {}\n",
+      "Must hint each located item, skipping those without a location"
+    );
+    
+    // Inside a proc macro the items parsed back from the file all have the call site's span
+    let input: syn::File = syn::parse_str("\nmod first {}\n\nmod second {}\n").expect("Must parse input");
+    let output_source = "// header\nmod first {}\n#[allow(dead_code)]\nmod second {}\n";
+    let output: syn::File = syn::parse2(respan(output_source.parse().expect("Must lex output"), Span::call_site()))
+      .expect("Must parse output");
+    let mappings = map_items(&input.items, &item_lines(output_source, &output.items).expect("Must find the output items"));
+    let hinted = format!(
+      "// header\n// procout-input: {}:2:1\nmod first {{}}\n// procout-input: {}:4:1\n#[allow(dead_code)]\nmod second {{}}\n",
+      mappings[0].input_file, mappings[1].input_file,
+    );
+    assert_eq!(insert_line_hints(output_source, &mappings), hinted, "Must hint each item above it, whatever the spans of the file's items say");
+  }
+}
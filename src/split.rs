@@ -0,0 +1,49 @@
+//! Splitting `impl` blocks out into a file of their own
+use proc_macro2::{
+  TokenStream,
+};
+use quote::{
+  quote,
+};
+use syn::{
+  File, Item,
+};
+
+/// Partition the top-level items of `file` into the definitions and the `impl` blocks
+/// - The impls are given a `use super::*;` so they still see the definitions from a child module.
+/// - Returns [None] if there are no impls to split out.
+pub(crate) fn split_impls(file: &File) -> Option<(TokenStream, TokenStream)> {
+  let (impls, definitions): (Vec<&Item>, Vec<&Item>) = file.items.iter().partition(|item| matches!(item, Item::Impl(_)));
+  if impls.is_empty() {
+    return None;
+  }
+  Some((
+    quote!{ #(#definitions)* },
+    quote!{
+      use super::*;
+      #(#impls)*
+    },
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_split_impls() {
+    let file: File = syn::parse2(quote!{
+      pub struct Split;
+      impl Split {}
+      impl Default for Split { fn default() -> Self { Split } }
+    }).expect("Must parse");
+    let (definitions, impls) = split_impls(&file).expect("Must split out impls");
+    assert_eq!(definitions.to_string(), quote!{ pub struct Split; }.to_string(), "Must keep the definitions");
+    assert_eq!(
+      impls.to_string(),
+      quote!{ use super::*; impl Split {} impl Default for Split { fn default() -> Self { Split } } }.to_string(),
+      "Must move the impls, importing the definitions"
+    );
+    let file: File = syn::parse2(quote!{ pub struct Unsplit; }).expect("Must parse");
+    assert!(split_impls(&file).is_none(), "Must leave a file without impls alone");
+  }
+}
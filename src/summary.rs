@@ -0,0 +1,217 @@
+//! A roll-up of every file written during the process
+use std::{
+  collections::{
+    BTreeMap,
+  },
+  fs,
+  io,
+  path::{
+    Path, PathBuf,
+  },
+  sync::{
+    Mutex,
+  },
+};
+use crate::{
+  Stream,
+};
+
+/// Every path written so far, in order
+static WRITTEN_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Remember that `path` was written
+pub(crate) fn record_written(path: &Path) {
+  WRITTEN_FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(path.to_path_buf());
+}
+
+/// How many times each module has been written so far
+static WRITE_COUNTS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+/// Count another write of `module_ident`, returning how many there have been including this one
+pub(crate) fn count_write(module_ident: &str) -> usize {
+  let mut write_counts = WRITE_COUNTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  let count = write_counts.entry(module_ident.to_string()).or_default();
+  *count += 1;
+  *count
+}
+
+/// How many times `module_ident` has been written so far in this process
+/// - A macro written far more often than it's used in the source is often being invoked by mistake, 
+///   e.g. expanded again inside its own output.
+/// - A [crate::ProcoutConfig::module_path] is counted by the whole path, e.g. `outer::inner`.
+/// - Only writes that made it to the file are counted, not ones that panicked or were refused.
+pub fn write_count(module_ident: &str) -> usize {
+  WRITE_COUNTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(module_ident).copied().unwrap_or_default()
+}
+
+/// The report file written by a [SummaryGuard::with_report] by default
+pub static REPORT_FILE: &str = "procout_report.md";
+
+/// One file written, as the report describes it, see [crate::ProcoutConfig::report]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ReportEntry {
+  pub(crate) module_name: String,
+  pub(crate) path: PathBuf,
+  pub(crate) lines: usize,
+  pub(crate) parsed: bool,
+  /// Whether the [crate::FormatCommand] formatted it, if it was run on the file at all
+  pub(crate) formatted: Option<bool>,
+}
+
+/// Every file reported so far, in order
+static REPORT: Mutex<Vec<ReportEntry>> = Mutex::new(Vec::new());
+
+/// Add a file to the report
+pub(crate) fn record_report(entry: ReportEntry) {
+  REPORT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(entry);
+}
+
+/// Render the report of every file written with [crate::ProcoutConfig::report] so far in this process, 
+/// as a markdown table
+pub fn render_report() -> String {
+  let report = REPORT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  let mut rendered = format!(
+    "# procout report\n\n{} file{} written.\n\n| Module | Path | Lines | Parsed | Formatted |\n| --- | --- | --- | --- | --- |\n",
+    report.len(),
+    if report.len() == 1 { "" } else { "s" },
+  );
+  let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+  for entry in report.iter() {
+    rendered.push_str(&format!(
+      "| `{}` | `{}` | {} | {} | {} |\n",
+      entry.module_name,
+      entry.path.display(),
+      entry.lines,
+      yes_no(entry.parsed),
+      entry.formatted.map_or("-", yes_no),
+    ));
+  }
+  rendered
+}
+
+/// Write the [render_report] to `path`, e.g. at the end of a run to triage its output
+pub fn write_report(path: &Path) -> io::Result<()> {
+  fs::write(path, render_report())
+}
+
+/// The paths written so far in this process, in order
+pub fn written_files() -> Vec<PathBuf> {
+  WRITTEN_FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// Render a summary like `procout wrote 2 files:` followed by one path per line
+pub fn render_summary() -> String {
+  let written_files = written_files();
+  let mut summary = format!(
+    "procout wrote {} file{}{}",
+    written_files.len(),
+    if written_files.len() == 1 { "" } else { "s" },
+    if written_files.is_empty() { "" } else { ":" },
+  );
+  for path in written_files {
+    summary.push_str(&format!("\n  {}", path.display()));
+  }
+  summary
+}
+
+/// Prints the summary of all files written when it's dropped 
+/// - Rust doesn't run destructors for statics at exit, so hold one of these for as long as you want covered, 
+///   e.g. at the top of `main` or of a long test.
+/// - It's controlled separately from the per-call messages, but like them it's only printed with the 
+///   `notification` feature unless [SummaryGuard::notify] says otherwise.
+/// - It's printed to stderr unless [SummaryGuard::notification_stream] says otherwise.
+/// ```ignore
+/// let _summary = procout::SummaryGuard::new();
+/// ```
+#[derive(Debug, Default)]
+#[must_use = "the summary is printed when the guard is dropped"]
+pub struct SummaryGuard {
+  report_path: Option<PathBuf>,
+  notify: Option<bool>,
+  notification_stream: Stream,
+}
+
+impl SummaryGuard {
+  /// Start a guard that prints the summary when dropped 
+  pub fn new() -> Self {
+    Self::default()
+  }
+  
+  /// Also write the report to `path`, e.g. [REPORT_FILE], when dropped, see [write_report]
+  pub fn with_report(mut self, path: impl Into<PathBuf>) -> Self {
+    self.report_path = Some(path.into());
+    self
+  }
+  
+  /// Choose at runtime whether the summary is printed, overriding the `notification` feature, as 
+  /// [crate::ProcoutConfig::notify] does for each file's messages
+  /// - `None`, the default, leaves it to the feature.
+  /// - A report that can't be written is still printed.
+  pub fn notify(mut self, notify: Option<bool>) -> Self {
+    self.notify = notify;
+    self
+  }
+  
+  /// Choose whether the summary goes to stderr, the default, or stdout, as [crate::ProcoutConfig::notification_stream] 
+  /// does for each file's messages
+  pub fn notification_stream(mut self, notification_stream: Stream) -> Self {
+    self.notification_stream = notification_stream;
+    self
+  }
+}
+
+impl Drop for SummaryGuard {
+  fn drop(&mut self) {
+    if self.notify.unwrap_or(cfg!(feature = "notification")) {
+      self.notification_stream.print(format_args!("{}", render_summary()));
+    }
+    if let Some(report_path) = &self.report_path {
+      if let Err(err) = write_report(report_path) {
+        self.notification_stream.print(format_args!("Could not write the procout report `{}`:\n {:#?}", report_path.display(), err));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_summary() {
+    let path = PathBuf::from("summarized/summarized_module.rs");
+    record_written(&path);
+    assert!(written_files().contains(&path), "Must remember written files");
+    assert!(
+      render_summary().contains(&format!("\n  {}", path.display())),
+      "Must list written files in the summary"
+    );
+    
+    drop(SummaryGuard::new().notify(Some(true)).notification_stream(Stream::Captured));
+    assert!(crate::take_captured().starts_with("procout wrote "), "Must print the summary to the stream");
+    drop(SummaryGuard::new().notify(Some(false)).notification_stream(Stream::Captured));
+    assert_eq!(crate::take_captured(), "", "Must print nothing when told to stay quiet");
+  }
+  
+  #[test]
+  fn test_write_count() {
+    assert_eq!(write_count("counted_module"), 0, "Must start at zero");
+    assert_eq!(count_write("counted_module"), 1, "Must count the first write");
+    assert_eq!(count_write("counted_module"), 2, "Must count each write");
+    assert_eq!(write_count("counted_module"), 2, "Must expose the count");
+  }
+  
+  #[test]
+  fn test_render_report() {
+    record_report(ReportEntry {
+      module_name: "reported_module".to_string(),
+      path: PathBuf::from("reported/reported_module.rs"),
+      lines: 12,
+      parsed: true,
+      formatted: None,
+    });
+    assert!(
+      render_report().contains("| `reported_module` | `reported/reported_module.rs` | 12 | yes | - |\n"),
+      "Must describe each reported file"
+    );
+  }
+}
@@ -0,0 +1,123 @@
+//! Merging and sorting the `use` items of the output
+use quote::{
+  ToTokens,
+};
+use std::{
+  collections::{
+    BTreeMap, BTreeSet,
+  },
+};
+use syn::{
+  File, Item, ItemUse, UseTree,
+};
+
+/// The paths imported under one prefix
+#[derive(Default)]
+struct UseNode {
+  children: BTreeMap<String, UseNode>,
+  leaves: BTreeSet<String>,
+}
+
+impl UseNode {
+  /// Add every path in `tree` under this node
+  fn insert(&mut self, tree: &UseTree) {
+    match tree {
+      UseTree::Path(use_path) => self.children.entry(use_path.ident.to_string()).or_default().insert(&use_path.tree),
+      UseTree::Name(use_name) => {
+        self.leaves.insert(use_name.ident.to_string());
+      },
+      UseTree::Rename(use_rename) => {
+        self.leaves.insert(format!("{} as {}", use_rename.ident, use_rename.rename));
+      },
+      UseTree::Glob(_) => {
+        self.leaves.insert("*".to_string());
+      },
+      UseTree::Group(use_group) => for tree in use_group.items.iter() {
+        self.insert(tree);
+      },
+    }
+  }
+  
+  /// Render what's imported under this node, one entry per leaf or child, sorted with `self` first
+  fn render(&self) -> Vec<String> {
+    let mut entries: Vec<String> = self.leaves.iter().cloned().collect();
+    for (segment, child) in &self.children {
+      let child_entries = child.render();
+      entries.push(match child_entries.as_slice() {
+        [only] if only == "self" => segment.clone(),
+        [only] if only.starts_with("self as ") => format!("{}{}", segment, &only["self".len()..]),
+        [only] => format!("{}::{}", segment, only),
+        _ => format!("{}::{{{}}}", segment, child_entries.join(", ")),
+      });
+    }
+    entries.sort_by(|a, b| (!a.starts_with("self"), a).cmp(&(!b.starts_with("self"), b)));
+    entries.dedup();
+    entries
+  }
+}
+
+/// Merge the `use` items at the root of `file` into one per crate or module they import from, sorted, 
+/// in place of the first of them
+/// - Items with attributes, e.g. `#[cfg]`, are left where they are.
+/// - `use`s with different visibilities or leading `::` aren't merged together.
+pub(crate) fn tidy_imports(file: &mut File) {
+  let mut groups: BTreeMap<(String, bool), UseNode> = BTreeMap::new();
+  let mut first_use = None;
+  let mut items = Vec::with_capacity(file.items.len());
+  for item in file.items.drain(..) {
+    match item {
+      Item::Use(item_use) if item_use.attrs.is_empty() => {
+        first_use.get_or_insert(items.len());
+        let key = (item_use.vis.to_token_stream().to_string(), item_use.leading_colon.is_some());
+        groups.entry(key).or_default().insert(&item_use.tree);
+      },
+      item => items.push(item),
+    }
+  }
+  let tidied = groups.iter().flat_map(|((vis, leading_colon), node)| {
+    node.render().into_iter().map(move |entry| {
+      syn::parse_str::<ItemUse>(&format!("{} use {}{};", vis, if *leading_colon { "::" } else { "" }, entry))
+        .map(Item::Use)
+        .expect("Tidied imports must parse")
+    })
+  });
+  let first_use = first_use.unwrap_or(items.len());
+  items.splice(first_use..first_use, tidied.collect::<Vec<_>>());
+  file.items = items;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{
+    quote,
+  };
+  #[test]
+  fn test_tidy_imports() {
+    let mut file: File = syn::parse2(quote!{
+      extern crate alloc;
+      use std::fmt::Debug;
+      pub struct Tidied;
+      use std::{collections::HashMap, fmt::{self, Display}};
+      use std::fmt::Debug;
+      #[cfg(test)]
+      use std::io;
+      use syn::Ident as SynIdent;
+      pub use std::rc::Rc;
+    }).expect("Must parse");
+    tidy_imports(&mut file);
+    assert_eq!(
+      file.into_token_stream().to_string(),
+      quote!{
+        extern crate alloc;
+        use std::{collections::HashMap, fmt::{self, Debug, Display}};
+        use syn::Ident as SynIdent;
+        pub use std::rc::Rc;
+        pub struct Tidied;
+        #[cfg(test)]
+        use std::io;
+      }.to_string(),
+      "Must merge, dedup and sort the imports in place of the first"
+    );
+  }
+}
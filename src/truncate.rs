@@ -0,0 +1,103 @@
+//! Cutting enormous output down to a size worth looking at
+use proc_macro2::{
+  TokenStream,
+};
+use quote::{
+  ToTokens,
+};
+use syn::{
+  Item,
+};
+
+/// Keep the leading items of `items` that fit in about `max_bytes` of token string, and count the rest
+/// - An inline `mod` that doesn't fit is kept with as much of its own content as fits, 
+///   so the module the test imports is still there.
+/// - Whole items are kept or omitted, so the result still parses.
+pub(crate) fn truncate_items(items: &[Item], max_bytes: usize) -> (TokenStream, usize) {
+  let mut kept = TokenStream::new();
+  let mut omitted = 0;
+  let mut remaining = max_bytes;
+  for item in items {
+    if omitted > 0 {
+      omitted += count_items(item);
+      continue;
+    }
+    let size = item.to_token_stream().to_string().len();
+    if size <= remaining {
+      remaining -= size;
+      item.to_tokens(&mut kept);
+      continue;
+    }
+    match item {
+      Item::Mod(item_mod) if item_mod.content.is_some() => {
+        let mut shell = item_mod.clone();
+        let content = shell.content.as_mut().map(|(_, content)| std::mem::take(content)).unwrap_or_default();
+        let shell_size = shell.to_token_stream().to_string().len();
+        let (kept_content, omitted_content) = truncate_items(&content, remaining.saturating_sub(shell_size));
+        let kept_items: syn::File = syn::parse2(kept_content).expect("Kept items must parse");
+        if let Some((_, shell_content)) = shell.content.as_mut() {
+          *shell_content = kept_items.items;
+        }
+        shell.to_tokens(&mut kept);
+        omitted += omitted_content;
+        remaining = 0;
+      },
+      _ => {
+        omitted += count_items(item);
+      },
+    }
+  }
+  (kept, omitted)
+}
+
+/// How many items `item` is, counting what's inside an inline `mod` instead of the `mod` itself
+fn count_items(item: &Item) -> usize {
+  match item {
+    Item::Mod(item_mod) => item_mod.content.as_ref()
+      .map_or(1, |(_, content)| content.iter().map(count_items).sum::<usize>().max(1)),
+    _ => 1,
+  }
+}
+
+/// Cut `source` to at most `max_bytes` on a character boundary, returning how many bytes were cut
+pub(crate) fn truncate_raw(source: &mut String, max_bytes: usize) -> usize {
+  let mut cut = max_bytes.min(source.len());
+  while !source.is_char_boundary(cut) {
+    cut -= 1;
+  }
+  let omitted = source.len() - cut;
+  source.truncate(cut);
+  omitted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quote::{quote};
+  #[test]
+  fn test_truncate_items() {
+    let file: syn::File = syn::parse2(quote!{
+      pub mod truncated {
+        const A: u8 = 0;
+        const B: u8 = 1;
+        const C: u8 = 2;
+      }
+      const D: u8 = 3;
+    }).expect("Must parse");
+    let budget = quote!{ pub mod truncated { const A: u8 = 0; } }.to_string().len();
+    let (kept, omitted) = truncate_items(&file.items, budget);
+    assert_eq!(
+      kept.to_string(),
+      quote!{ pub mod truncated { const A: u8 = 0; } }.to_string(),
+      "Must keep the module with the content that fits"
+    );
+    assert_eq!(omitted, 3, "Must count the omitted items");
+  }
+  
+  #[test]
+  fn test_truncate_raw() {
+    let mut source = "const CUSS: &str = \"SPİT\";".to_string();
+    assert_eq!(truncate_raw(&mut source, 23), 5, "Must cut on a character boundary");
+    assert_eq!(source, "const CUSS: &str = \"SP");
+  }
+}
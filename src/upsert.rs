@@ -0,0 +1,85 @@
+//! Updating one module within an existing file, leaving the rest of it intact 
+use proc_macro2::{
+  Span,
+};
+use syn::{
+  Ident, Item,
+};
+use crate::{
+  locate::{
+    item_ranges,
+  },
+};
+
+/// Replace the `mod <module_ident>` item and the `fn <test_ident>` item in `existing` with `new_items`
+/// - Everything else in `existing`, comments included, is left byte for byte.
+/// - If neither item is there, `new_items` is appended.
+/// - The items are found in the text rather than by their spans, which inside a proc macro don't point into it.
+pub(crate) fn upsert_items(
+  existing: &str,
+  module_ident: &Ident,
+  test_ident: &Ident,
+  new_items: &str,
+) -> Result<String, syn::Error> {
+  let file = syn::parse_file(existing)?;
+  let item_ranges = item_ranges(existing, &file.items)
+    .ok_or_else(|| syn::Error::new(Span::call_site(), "could not find the items in the file"))?;
+  let mut ranges: Vec<(usize, usize)> = file.items.iter()
+    .zip(item_ranges)
+    .filter(|(item, _)| match item {
+      Item::Mod(item_mod) => &item_mod.ident == module_ident,
+      Item::Fn(item_fn) => &item_fn.sig.ident == test_ident,
+      _ => false,
+    })
+    .map(|(_, range)| (range.start, range.end))
+    .collect();
+  ranges.sort_unstable();
+  
+  let mut upserted = String::with_capacity(existing.len() + new_items.len());
+  match ranges.first() {
+    Some(&(first_start, _)) => {
+      let mut copied_to = 0;
+      for (start, end) in ranges {
+        upserted.push_str(&existing[copied_to..start]);
+        if start == first_start {
+          upserted.push_str(new_items);
+        }
+        copied_to = end;
+      }
+      upserted.push_str(&existing[copied_to..]);
+    },
+    None => {
+      upserted.push_str(existing);
+      if !existing.ends_with('\n') {
+        upserted.push('\n');
+      }
+      upserted.push_str(new_items);
+      upserted.push('\n');
+    },
+  }
+  Ok(upserted)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_upsert_items() {
+    let existing = "// curated\nmod kept {}\n\nmod updated { const OLD: u8 = 0; }\n#[test]\nfn macro_test_updated() { use updated::*; }\n// trailing\n";
+    let module_ident = Ident::new("updated", Span::call_site());
+    let test_ident = Ident::new("macro_test_updated", Span::call_site());
+    assert_eq!(
+      upsert_items(existing, &module_ident, &test_ident, "mod updated { const NEW: u8 = 1; }").expect("Must parse"),
+      "// curated\nmod kept {}\n\nmod updated { const NEW: u8 = 1; }\n\n// trailing\n",
+      "Must replace the module and its test, leaving everything else intact"
+    );
+    
+    let appended_ident = Ident::new("appended", Span::call_site());
+    let appended_test_ident = Ident::new("macro_test_appended", Span::call_site());
+    assert_eq!(
+      upsert_items("mod kept {}", &appended_ident, &appended_test_ident, "mod appended {}").expect("Must parse"),
+      "mod kept {}\nmod appended {}\n",
+      "Must append a module that isn't there yet"
+    );
+  }
+}
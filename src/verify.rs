@@ -0,0 +1,142 @@
+//! Checking that the written file compiles
+use std::{
+  env, fs,
+  io,
+  path::{
+    Path, PathBuf,
+  },
+  process::{
+    self, Command, Output,
+  },
+};
+use crate::{
+  dumps::{
+    list_dumps,
+  },
+};
+
+/// A message from the compiler about a dump, see [verify_dir]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+  /// The dump the message is about
+  pub path: PathBuf,
+  /// Where in the dump, as `(line, column)`, if the compiler said
+  pub location: Option<(usize, usize)>,
+  /// The message in `rustc`'s short format, e.g. ``error[E0425]: cannot find value `x` in this scope``
+  pub message: String,
+}
+
+impl Diagnostic {
+  /// Whether it's an error rather than a warning
+  pub fn is_error(&self) -> bool {
+    self.message.starts_with("error")
+  }
+}
+
+/// Add the arguments type checking the file at `target_path` as a test crate
+/// - Only metadata is emitted, into the temp dir, so nothing is linked or left next to the file.
+fn check_args<'a>(command: &'a mut Command, target_path: &Path) -> &'a mut Command {
+  command
+    .arg("--edition").arg("2021")
+    .arg("--test")
+    .arg("--emit=metadata")
+    .arg("--out-dir").arg(env::temp_dir().join("procout_verify"))
+    .arg(target_path)
+}
+
+/// The `rustup run <toolchain> rustc` command type checking the file at `target_path` as a test crate
+pub(crate) fn toolchain_command(toolchain: &str, target_path: &Path) -> Command {
+  let mut command = Command::new("rustup");
+  check_args(command.arg("run").arg(toolchain).arg("rustc"), target_path);
+  command
+}
+
+/// Type check the file at `target_path` with `toolchain`, returning the compiler's output
+pub(crate) fn verify_with_toolchain(toolchain: &str, target_path: &Path) -> io::Result<Output> {
+  toolchain_command(toolchain, target_path).output()
+}
+
+/// Type check `source` on its own as a test crate with the `rustc` named by the `RUSTC` env var, or on the path, 
+/// returning whether it compiled, see [crate::DumpWhen::CheckFails]
+/// - `name` names the scratch file it's written to in the temp dir, which is removed afterwards.
+pub(crate) fn check_compiles(source: &str, name: &str) -> io::Result<bool> {
+  let scratch_dir = env::temp_dir().join("procout_verify");
+  fs::create_dir_all(&scratch_dir)?;
+  let scratch_path = scratch_dir.join(format!("check_{}_{}.rs", name, process::id()));
+  fs::write(&scratch_path, source)?;
+  let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+  let status = check_args(&mut Command::new(rustc), &scratch_path).output().map(|output| output.status.success());
+  let _ = fs::remove_file(&scratch_path);
+  status
+}
+
+/// Type check every dump in `dir` on its own, as the test crate it's written to be, and collect what the 
+/// compiler says about each, so a whole directory of committed dumps can be guarded against regressions at once
+/// - Dumps are found as by [list_dumps], and checked with the `rustc` named by the `RUSTC` env var, or on the path, 
+///   just as [crate::ProcoutConfig::verify_toolchain] checks a single file.
+/// - An empty list means everything compiled without warnings. Check [Diagnostic::is_error] to allow warnings.
+/// - Dumps needing dependencies can't be checked on their own, and show up as unresolved imports.
+pub fn verify_dir(dir: &Path) -> io::Result<Vec<Diagnostic>> {
+  let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+  let mut diagnostics = Vec::new();
+  for dump in list_dumps(dir)? {
+    let output = check_args(Command::new(&rustc).arg("--error-format=short"), &dump.path).output()?;
+    diagnostics.extend(parse_diagnostics(&dump.path, &String::from_utf8_lossy(&output.stderr)));
+  }
+  Ok(diagnostics)
+}
+
+/// Collect the diagnostics `rustc --error-format=short` printed about the file at `path`
+/// - Lines without a location, like the closing `aborting due to` tally, are left out.
+fn parse_diagnostics(path: &Path, stderr: &str) -> Vec<Diagnostic> {
+  let prefix = format!("{}:", path.display());
+  stderr.lines()
+    .filter_map(|line| {
+      let mut parts = line.strip_prefix(&prefix)?.splitn(3, ':');
+      let line_number = parts.next()?.parse().ok()?;
+      let column = parts.next()?.parse().ok()?;
+      Some(Diagnostic {
+        path: path.to_path_buf(),
+        location: Some((line_number, column)),
+        message: parts.next()?.trim().to_string(),
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_toolchain_command() {
+    let command = toolchain_command("nightly", Path::new("tests/verified.rs"));
+    assert_eq!(command.get_program(), "rustup", "Must run through rustup");
+    let args: Vec<_> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    assert_eq!(&args[..3], ["run", "nightly", "rustc"], "Must run rustc with the toolchain");
+    assert_eq!(args.last().map(String::as_str), Some("tests/verified.rs"), "Must check the file");
+  }
+  
+  #[test]
+  fn test_check_compiles() {
+    assert!(check_compiles("pub fn sound() -> u8 { 1 }\n", "sound").expect("Must run rustc"), "Must pass sound code");
+    assert!(!check_compiles("pub fn broken() -> u8 { missing }\n", "broken").expect("Must run rustc"), "Must fail broken code");
+  }
+  
+  #[test]
+  fn test_verify_dir() {
+    let dir = env::temp_dir().join("procout_tests").join("test_verify_dir");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("Must create test dir");
+    let header = crate::dumps::render_header;
+    std::fs::write(dir.join("sound_module.rs"), format!("{}pub mod sound_module {{}}\n", header("sound_module")))
+      .expect("Must write dump");
+    std::fs::write(dir.join("broken_module.rs"), format!("{}pub fn broken_module() -> u8 {{ missing }}\n", header("broken_module")))
+      .expect("Must write dump");
+    
+    let diagnostics = verify_dir(&dir).expect("Must check the dumps");
+    assert_eq!(diagnostics.len(), 1, "Must only report the broken dump: {:?}", diagnostics);
+    assert_eq!(diagnostics[0].path, dir.join("broken_module.rs"), "Must name the broken dump");
+    assert_eq!(diagnostics[0].location, Some((3, 32)), "Must locate the error");
+    assert!(diagnostics[0].is_error() && diagnostics[0].message.contains("missing"), "Must keep the message");
+  }
+}
@@ -0,0 +1,77 @@
+//! Noticing dumps edited by hand, see [crate::ProcoutConfig::watch_and_format]
+//!
+//! The directory is polled with the standard library rather than watched with OS notifications, so the 
+//! feature adds no dependencies. Dumps are few and small, so polling is cheap.
+use std::{
+  collections::{
+    HashMap,
+  },
+  io,
+  path::{
+    Path, PathBuf,
+  },
+  time::{
+    SystemTime,
+  },
+};
+use crate::{
+  dumps::{
+    list_dumps,
+  },
+};
+
+/// How often the directory is polled
+pub(crate) const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The modification times of the dumps in a directory as last seen
+#[derive(Default)]
+pub(crate) struct DumpTimes {
+  seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl DumpTimes {
+  /// List the dumps in `dir` changed since the last poll, remembering their times for the next one
+  /// - The first poll only remembers, so the dumps already there aren't all reformatted at startup.
+  pub(crate) fn poll(&mut self, dir: &Path, first_poll: bool) -> io::Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for dump in list_dumps(dir)? {
+      let previous = self.seen.insert(dump.path.clone(), dump.modified);
+      if !first_poll && previous != Some(dump.modified) {
+        changed.push(dump.path);
+      }
+    }
+    Ok(changed)
+  }
+  
+  /// Remember the time of a dump after procout itself rewrote it, so that isn't seen as an edit
+  pub(crate) fn refresh(&mut self, path: &Path) {
+    if let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) {
+      self.seen.insert(path.to_path_buf(), modified);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    env, fs,
+  };
+  #[test]
+  fn test_dump_times() {
+    let dir = env::temp_dir().join("procout_tests").join("test_dump_times");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Must create dir");
+    let dump_path = dir.join("watched.rs");
+    fs::write(&dump_path, "// @generated by procout\n").expect("Must write dump");
+    
+    let mut dump_times = DumpTimes::default();
+    assert!(dump_times.poll(&dir, true).expect("Must poll").is_empty(), "Must only remember on the first poll");
+    assert!(dump_times.poll(&dir, false).expect("Must poll").is_empty(), "Must not report untouched dumps");
+    
+    let edited = SystemTime::now() + std::time::Duration::from_secs(10);
+    fs::File::options().write(true).open(&dump_path).and_then(|file| file.set_modified(edited)).expect("Must touch dump");
+    assert_eq!(dump_times.poll(&dir, false).expect("Must poll"), vec![dump_path.clone()], "Must report an edited dump");
+    assert!(dump_times.poll(&dir, false).expect("Must poll").is_empty(), "Must report an edit once");
+  }
+}